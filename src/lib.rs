@@ -0,0 +1,11002 @@
+//! A fast and simple recursive 'large file finder'. As well as the `lff` binary, this library can
+//! be used to embed scans in other tools - see [run_finder] to run `lff` as on the command line,
+//! or [scan_start_directories] to retrieve the found [LffFile]s directly.
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use eyre::{eyre, EyreHandler, Result, WrapErr};
+use glob::glob;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::{debug, log, log_enabled, Level};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use regex::{Match, Regex};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string, to_string_pretty};
+use size::{Base, Size, Style};
+use std::cmp::Reverse;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::convert::Infallible;
+use std::env::{var, var_os};
+use std::error::Error as StdError;
+use std::ffi::{OsStr, OsString};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{
+    canonicalize, copy, create_dir_all, metadata, read_dir, read_to_string, remove_file, rename,
+    symlink_metadata, write, DirEntry, File, FileType, Metadata, ReadDir,
+};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{stdin, stdout, BufRead, ErrorKind, IsTerminal, Result as IoResult};
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
+use std::process::{exit, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread::{scope, spawn, ScopedJoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+// For convenience's sake, define the size of a mebibyte.
+const MEBIBYTE: u64 = 1024 * 1024;
+
+// How long the watched directories must go without changing before they are scanned again, so that
+// a burst of changes, e.g. from extracting an archive, only triggers a single scan.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+// How often to check for Ctrl-C while waiting for the watched directories to change.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// The ANSI escape sequences to clear the terminal and move the cursor to its top-left corner.
+const ANSI_CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+// The ANSI escape sequences used to colour file sizes by their tier, and to reset the colour after.
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_YELLOW: &str = "\x1b[33m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+// The abbreviations of the units that sizes are pretty-printed in, in ascending order, for base 2
+// and base 10 respectively. Each unit is 1024 or 1000 times larger than the previous one.
+const BASE_TWO_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const BASE_TEN_UNITS: [&str; 7] = ["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+
+// The header row of the TSV format, naming the fields returned by delimited_fields().
+const TSV_HEADER: &str = "size\tformatted_size\textension\thidden\tname";
+
+// The number of found files that can be buffered when scanning lazily before the scan pauses.
+const SCAN_ITER_BUFFER_SIZE: usize = 1024;
+
+// The offset basis and prime of the 64-bit FNV-1a hash, used in [seeded_hash].
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// The minimum size in MiB for found files when none is supplied.
+const DEFAULT_MIN_SIZE_MIB: f64 = 50.0;
+
+// The fraction of the free disk space that files must be at least to be displayed with the min size
+// auto flag, as its reciprocal.
+const MIN_SIZE_AUTO_DIVISOR: u64 = 100;
+
+// The version of the snapshot format written with --save-snapshot, to be incremented whenever its
+// structure changes incompatibly.
+const SNAPSHOT_VERSION: u32 = 1;
+
+// The name of the files listing patterns to skip when respecting ignore files.
+const IGNORE_FILE_NAME: &str = ".lffignore";
+
+// The width of the bar for the most populous size range when printing a histogram.
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+// The maximum number of bytes of arguments to pass to each batch command, kept well under the
+// ARG_MAX limits of common platforms, which also count the environment.
+const BATCH_EXEC_MAX_ARG_BYTES: usize = 128 * 1024;
+
+// The message to return when no files are found matching the supplied arguments.
+const NO_FILES_FOUND_STR: &str = "No files found for the specified arguments!";
+
+// Whether the user has interrupted the scan with Ctrl-C, set by the handler installed in
+// [install_interrupt_handler], and reset at the start of each run in [run_finder].
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// The exit code to use when the user interrupts `lff` a second time, following the convention of
+// 128 plus the number of the SIGINT signal.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+// The exit code to use when the number of found files is outside the supplied count bounds, so that
+// scripts can distinguish this from other errors, as well as from usage errors, which clap exits
+// with a code of 2 for.
+pub const COUNT_OUT_OF_BOUNDS_EXIT_CODE: i32 = 3;
+
+/// The ways in which displayed files can be sorted. Derives `ValueEnum` and `Clone` so that it can
+/// be used as a type for the clap command-line arguments, and `Deserialize` so that it can be set
+/// in config files.
+#[derive(ValueEnum, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortMethod {
+    /// Largest files first.
+    Size,
+    /// Alphabetically by file name, regardless of the directory the file is in.
+    Name,
+    /// Alphabetically by full path, so that files in the same directory are grouped together.
+    Path,
+    /// Alphabetically by extension, so that files of the same type are grouped together. Files
+    /// without an extension are displayed last.
+    Extension,
+    /// In a random order, which is the same for the same seed and files, e.g. with --limit for a
+    /// random subset. Reversing a random order has no effect.
+    Random,
+}
+
+/// The formats in which found files can be displayed. Derives `ValueEnum` and `Clone` so that it
+/// can be used as a type for the clap command-line arguments, and `Deserialize` so that it can be
+/// set in config files.
+#[derive(ValueEnum, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// A flat list of files, one per line.
+    Text,
+    /// An indented directory hierarchy containing the files, similar to the `tree` command.
+    Tree,
+    /// The tree format with the total size of the files within each directory, recursively, and
+    /// with chains of directories containing only a single directory collapsed onto one line.
+    HumanTree,
+    /// A compact JSON array, with one object per file.
+    Json,
+    /// An indented JSON array, with one object per file, for reading and diffing.
+    JsonPretty,
+    /// Newline-delimited JSON, with one object per file. With --unordered, each object is output as
+    /// soon as its file is found.
+    Ndjson,
+    /// Newline-delimited JSON events, each with a type - a start event with the start directories,
+    /// output before the scan, a file event with the same fields as the other JSON formats for each
+    /// file, and a summary event with the number and total size of the files. With --unordered,
+    /// each file event is output as soon as its file is found.
+    JsonStream,
+    /// A YAML sequence, with one mapping per file, in the same shape as the JSON formats.
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// Tab-separated values, with a header row and then one row per file, e.g. for awk or cut.
+    /// Tabs and newlines within names are escaped as '\t' and '\n', and backslashes as '\\'.
+    Tsv,
+    /// Aligned columns for the size, modification time, and name of each file, with a header.
+    Table,
+    /// A GitHub-flavoured Markdown table of the size and name of each file, or of the columns
+    /// supplied with --columns, e.g. for pasting into issues.
+    Markdown,
+    /// A short natural-language summary of the number of files, their total size, and the largest.
+    Human,
+    /// Bare paths, one per line, without quoting or any other columns, e.g. for shell loops.
+    /// Paths that aren't valid Unicode are converted lossily.
+    Path,
+    /// Prometheus text-format metrics for the number and total size of the files, overall and per
+    /// extension, e.g. for the node exporter's textfile collector. Files without an extension have
+    /// an empty extension label.
+    Prometheus,
+    /// Nothing at all, not even when no files are found, e.g. to benchmark the scan itself. Any
+    /// statistics are still printed.
+    None,
+}
+
+/// When to colour the output. Derives `ValueEnum` and `Clone` so that it can be used as a type for
+/// the clap command-line arguments.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ColorChoice {
+    /// Only when standard output is a terminal, and the NO_COLOR environment variable isn't set.
+    Auto,
+    /// Always, even when the output is redirected, e.g. to a pager.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// The columns that can be displayed in the text, table, and Markdown formats. Derives `ValueEnum`
+/// and `Clone` so that it can be used as a type for the clap command-line arguments.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum Column {
+    /// The depth of the file relative to its start directory.
+    Depth,
+    /// The formatted size of the file.
+    Size,
+    /// The inode number of the file, on Unix.
+    Inode,
+    /// The time the file was last modified, in UTC.
+    Modified,
+    /// How long ago the file was last modified, relative to now.
+    Age,
+    /// The extension of the file, if it has one.
+    #[value(name = "ext")]
+    Extension,
+    /// The name of the file, or its full path.
+    Name,
+}
+
+impl Column {
+    /// Returns the header to display above this column.
+    fn header(&self) -> &'static str {
+        match self {
+            Column::Depth => "Depth",
+            Column::Size => "Size",
+            Column::Inode => "Inode",
+            Column::Modified => "Modified (UTC)",
+            Column::Age => "Age",
+            Column::Extension => "Ext",
+            Column::Name => "Name",
+        }
+    }
+
+    /// Returns the value to display in this column for the supplied file.
+    fn cell(&self, file: &LffFile) -> String {
+        match self {
+            Column::Depth => file.depth.to_string(),
+            Column::Size => file.formatted_size.clone(),
+            Column::Inode => file
+                .inode
+                .map_or_else(|| String::from("-"), |inode| inode.to_string()),
+            Column::Modified => match file.modified {
+                Some(modified) => format_timestamp(modified),
+                None => String::from("-"),
+            },
+            // Files modified in the future, e.g. due to clock skew, are treated as just modified.
+            Column::Age => match file.modified {
+                Some(modified) => format_relative_time(
+                    SystemTime::now()
+                        .duration_since(modified)
+                        .unwrap_or_default(),
+                ),
+                None => String::from("-"),
+            },
+            Column::Extension => file.extension.as_ref().map_or_else(
+                || String::from("-"),
+                |ext| ext.to_string_lossy().into_owned(),
+            ),
+            Column::Name => format!("{:?}", file.name),
+        }
+    }
+}
+
+/// A representation of a file from within the file system. `OsString`s are used because Rust
+/// `String`s are UTF-8 encoded, and not all file names and extensions will be UTF-8 encoded in a
+/// file system.
+///
+/// The file's `formatted_size` refers to how it will be displayed in the output. Some examples
+/// include `1024`, `1 KiB`, or `1.02 KB`.
+///
+/// Directories can also be represented when they are included in the output, in which case
+/// `is_dir` is true and the size is that of the directory entry itself.
+#[derive(Debug)]
+pub struct LffFile {
+    pub name: OsString,
+    pub extension: Option<OsString>,
+    pub size: u64,
+    pub formatted_size: String,
+    pub hidden: bool,
+    pub is_dir: bool,
+    pub modified: Option<SystemTime>,
+    pub depth: usize,
+    pub inode: Option<u64>,
+    pub device: Option<u64>,
+}
+
+/// The serialisation of found files for the machine-readable output formats, shared between all of
+/// them so that the fields are consistent. Since JSON strings must be valid Unicode, names and
+/// extensions that aren't are converted lossily.
+impl Serialize for LffFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut file_struct: S::SerializeStruct = serializer.serialize_struct("LffFile", 6)?;
+        file_struct.serialize_field("name", &self.name.to_string_lossy())?;
+        file_struct.serialize_field(
+            "extension",
+            &self.extension.as_ref().map(|ext| ext.to_string_lossy()),
+        )?;
+        file_struct.serialize_field("size", &self.size)?;
+        file_struct.serialize_field("formatted_size", &self.formatted_size)?;
+        file_struct.serialize_field("hidden", &self.hidden)?;
+        file_struct.serialize_field("is_dir", &self.is_dir)?;
+        file_struct.end()
+    }
+}
+
+/// The events output by the JSON stream format, one per line, each tagged with its type.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum LffEvent<'a> {
+    /// Output before any files, with the directories that were searched.
+    Start { directories: &'a [String] },
+    /// Output for each found file.
+    File(&'a LffFile),
+    /// Output after all files, with their number and total size in bytes.
+    Summary { count: usize, total: u64 },
+}
+
+/// A snapshot of the files matched by a scan, as saved with --save-snapshot. The version allows the
+/// structure to change without older snapshots being misread.
+#[derive(Serialize)]
+struct LffSnapshot<'a> {
+    version: u32,
+    files: &'a [LffFile],
+}
+
+/// A file as recorded in a snapshot of a previous scan, which is just the JSON serialisation of an
+/// [LffFile]. Only the fields needed to compare scans are read, and the rest are ignored.
+#[derive(Deserialize)]
+struct LffSnapshotFile {
+    name: String,
+    size: u64,
+}
+
+/// The contents of a snapshot file when read - either a versioned snapshot, or just the array of
+/// files output by the JSON formats.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LffSnapshotContents {
+    Versioned {
+        version: u32,
+        files: Vec<LffSnapshotFile>,
+    },
+    Unversioned(Vec<LffSnapshotFile>),
+}
+
+/// A difference in a file between a snapshot and the current scan. A file without a previous size
+/// is new, and one without a current size has been deleted.
+struct LffSnapshotChange {
+    name: String,
+    previous_size: Option<u64>,
+    current_size: Option<u64>,
+}
+
+impl LffSnapshotChange {
+    /// Returns the marker for the kind of change - + for new files, - for deleted files, and ~
+    /// for files that have changed size.
+    fn marker(&self) -> char {
+        match (self.previous_size, self.current_size) {
+            (None, _) => '+',
+            (_, None) => '-',
+            _ => '~',
+        }
+    }
+
+    /// Returns the change in size, where deleted files are considered to have shrunk to nothing.
+    fn delta(&self) -> i128 {
+        self.current_size.unwrap_or(0) as i128 - self.previous_size.unwrap_or(0) as i128
+    }
+}
+
+/// Recursively finds large files.
+#[derive(Parser, Clone)]
+#[command(version, about)]
+// The flags that a seed can be supplied for, either of which may be supplied.
+#[command(group(ArgGroup::new("seedable").multiple(true)))]
+pub struct LffArgs {
+    /// The directories to begin searching in. A leading ~ and environment variables, e.g. $HOME, or
+    /// %USERPROFILE% on Windows, are expanded, with $$ for a literal $, as are quoted glob
+    /// patterns, e.g. 'projects/*/build'.
+    #[arg(required_unless_present_any = ["stdin", "paths_from_file"])]
+    pub directories: Vec<String>,
+    /// Display absolute paths for files.
+    /// Automatically true if the supplied directory isn't relative.
+    #[arg(short, long)]
+    pub absolute: bool,
+    /// Display paths relative to this base directory, e.g. your home directory, rather than to the
+    /// current directory. Files that aren't within the base are displayed with absolute paths.
+    /// Since the displayed paths can't be acted upon, this can't be combined with any actions.
+    #[arg(long, conflicts_with_all = ["move_to", "exec", "batch_exec"])]
+    pub absolute_from: Option<PathBuf>,
+    /// Run this command through the system shell once the scan is complete and the found files
+    /// have been displayed, e.g. to send a notification. The number and total size in bytes of the
+    /// found files are passed in the LFF_COUNT and LFF_TOTAL_BYTES environment variables. Respects
+    /// --dry-run, and isn't run if the scan is interrupted.
+    #[arg(long, value_parser = parse_command)]
+    pub after_scan_command: Option<String>,
+    /// Display how long ago each file was last modified, e.g. 3d for three days, between the size
+    /// and the name in the text and table formats.
+    #[arg(long)]
+    pub age: bool,
+    /// Pretty-print every file's size in the same unit, e.g. all in MiB, picked from the median
+    /// size of the found files, so that the sizes can be compared at a glance. Totals are still
+    /// pretty-printed in whichever unit suits them.
+    #[arg(long, requires = "pretty")]
+    pub auto_units: bool,
+    /// Traverse directories breadth-first, so that shallower files are found before deeper ones.
+    /// When a limit is supplied with the unordered flag, shallower files are therefore preferred.
+    #[arg(long)]
+    pub breadth_first: bool,
+    /// The columns to display in the text, table, and Markdown formats, in order, separated by
+    /// commas, e.g. 'size,modified,ext,name'. Replaces the default columns, and those added by
+    /// other flags.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with = "group_by_extension"
+    )]
+    pub columns: Option<Vec<Column>>,
+    /// Compare the found files to those in this snapshot, previously saved with --save-snapshot or
+    /// --format json, displaying only the files that are new (+), deleted (-), or have changed size
+    /// (~) since, along with the change in size. Files are matched by path, so the snapshot should
+    /// be taken with the same directories. All matched files are compared, regardless of any limit.
+    #[arg(long, conflicts_with_all = ["total_only", "largest_dir"])]
+    pub compare_to: Option<PathBuf>,
+    /// Whether to display file sizes in KB/MB/GB over KiB/MiB/GiB when pretty-printing is enabled.
+    #[arg(long)]
+    pub base_ten: bool,
+    /// Run this command once with the paths of all found files appended as arguments, e.g. 'rm -f',
+    /// rather than once per file as with --exec. If there are too many paths for one invocation,
+    /// the command is run several times with a batch of paths each. Respects --dry-run.
+    #[arg(long, value_parser = parse_command, conflicts_with_all = ["exec", "move_to"])]
+    pub batch_exec: Option<String>,
+    /// Match extensions and name patterns exactly, byte for byte. This is already the default, but
+    /// makes the intent explicit, e.g. in scripts, and can't be combined with --ignore-case.
+    #[arg(long, conflicts_with = "ignore_case")]
+    pub case_sensitive: bool,
+    /// When to colour the output, for the flags that add colour.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    /// Files at least this size have their sizes coloured red by --color-tiers. Accepts the same
+    /// sizes as --exclude-larger-than.
+    #[arg(long, value_parser = parse_human_size, default_value = "1GiB", requires = "color_tiers")]
+    pub color_tier_red: u64,
+    /// Files at least this size, but smaller than the red tier, have their sizes coloured yellow by
+    /// --color-tiers. Accepts the same sizes as --exclude-larger-than.
+    #[arg(
+        long,
+        value_parser = parse_human_size,
+        default_value = "100MiB",
+        requires = "color_tiers"
+    )]
+    pub color_tier_yellow: u64,
+    /// Colour file sizes in the text format by their magnitude, so that large files stand out -
+    /// green below 100 MiB, yellow below 1 GiB, and red otherwise, by default.
+    #[arg(long)]
+    pub color_tiers: bool,
+    /// Read default values for flags from this TOML file, rather than from
+    /// ~/.config/lff/config.toml. Flags supplied on the command line take precedence, and any
+    /// values in the config file that conflict with them are ignored. Flags switched on in the
+    /// config file can't otherwise be switched off on the command line, so supply a different
+    /// config file to do so.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Count files that are hardlinked to the same underlying file only once towards size totals,
+    /// e.g. in percentages, subtotals and summaries, since they share storage. Each hardlink is
+    /// still listed. Only has an effect on Unix.
+    #[arg(long)]
+    pub dedup_hardlinks: bool,
+    /// Display each file only once, even if it was found more than once, e.g. when one start
+    /// directory is within another. Files are compared by their canonical paths.
+    #[arg(long)]
+    pub dedupe_output: bool,
+    /// Print the total size of found files at each depth of the directory tree, after the files
+    /// themselves. Files directly within a start directory are at depth 0.
+    #[arg(long)]
+    pub depth_summary: bool,
+    /// Follow each symlink found to check whether its target exists, and list those that are
+    /// broken, along with their count, after the found files. Dangling symlinks often indicate
+    /// stale state.
+    #[arg(long)]
+    pub dereference_count: bool,
+    /// Print what any actions that modify the file system would do, without performing them.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Exclude empty files, even when the minimum size is zero, e.g. to skip placeholder files.
+    #[arg(long)]
+    pub exclude_empty: bool,
+    /// Exclude files with this extension, e.g. iso, which can be supplied multiple times. Takes
+    /// precedence over the extension filter. As with the extension filter, a leading dot is
+    /// ignored.
+    #[arg(long = "exclude-extension")]
+    pub exclude_extensions: Vec<OsString>,
+    /// Exclude hidden files and directories.
+    #[arg(long)]
+    pub exclude_hidden: bool,
+    /// Exclude files larger than this size, e.g. to trim outliers. Equivalent to the maximum size
+    /// in bytes, but accepts human-readable sizes, e.g. 500K, 1.5GiB or 10MB. Single-letter units
+    /// and those containing an 'i' are powers of 1024, while other units are powers of 1000.
+    #[arg(long, value_parser = parse_human_size, conflicts_with = "max_size_bytes")]
+    pub exclude_larger_than: Option<u64>,
+    /// Run this command for each found file, like `find -exec`, substituting each {} with the
+    /// file's path, or appending the path if there is no {}. The command is split on whitespace,
+    /// with no support for quoting, and run directly, without a shell, unless --exec-shell is
+    /// passed, e.g. for arguments containing spaces. Respects --dry-run.
+    #[arg(long, value_parser = parse_command, conflicts_with = "move_to")]
+    pub exec: Option<String>,
+    /// Run the --exec command through the system shell, so that pipes, redirections and quoting can
+    /// be used. The file's path is passed to the shell as an argument rather than substituted into
+    /// the command, so {} is replaced with "$1" rather than the path itself.
+    #[arg(long, requires = "exec")]
+    pub exec_shell: bool,
+    /// Print how the supplied flags will be interpreted, e.g. sizes in bytes, and then exit
+    /// without scanning.
+    #[arg(long)]
+    pub explain: bool,
+    /// Minimum sizes for files with particular extensions, in place of the global minimum size,
+    /// e.g. 'mp4=500M,txt=1M'. Accepts the same sizes as --exclude-larger-than. Files with other
+    /// extensions, or none, use the global minimum size.
+    #[arg(long = "ext-threshold", value_parser = parse_ext_thresholds)]
+    pub ext_thresholds: Option<BTreeMap<OsString, u64>>,
+    /// Filter files by extension, e.g. txt. A leading dot is ignored, so .txt works too.
+    #[arg(short, long)]
+    pub extension: Option<OsString>,
+    /// Lowercase extensions when grouping and sorting by them, so that e.g. JPG and jpg files are
+    /// treated as the same type. Files themselves are unaffected.
+    #[arg(long)]
+    pub extension_case_fold: bool,
+    /// Follow symlinks to directories that are directly within a start directory, but not those
+    /// found deeper in the directory tree.
+    #[arg(long)]
+    pub follow_only_top_level_symlinks: bool,
+    /// How to display found files.
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Group displayed files by extension, with a subtotal for each group.
+    #[arg(long)]
+    pub group_by_extension: bool,
+    /// Print a histogram of the number and total size of found files in power-of-two size ranges,
+    /// after the files themselves. Ranges without any files are omitted.
+    #[arg(long)]
+    pub histogram: bool,
+    /// Only display hidden files, and files within hidden directories.
+    #[arg(long, conflicts_with = "exclude_hidden")]
+    pub hidden_only: bool,
+    /// Match name patterns case-insensitively.
+    #[arg(short, long)]
+    pub ignore_case: bool,
+    /// Include directories matching the filters in the output, alongside files.
+    /// The size displayed for directories is that of the directory entry itself.
+    #[arg(long)]
+    pub include_dirs: bool,
+    /// Search within zip archives, displaying their entries as 'archive.zip!entry' with their
+    /// uncompressed sizes. Entries are subject to the same filters as files, including the
+    /// per-directory limit for each archive, and archives that can't be read are skipped with a
+    /// warning. Since entries aren't files themselves, this can't be combined with actions.
+    #[arg(long, conflicts_with_all = ["move_to", "exec", "batch_exec"])]
+    pub into_archives: bool,
+    /// Display only the largest directory by the combined size of the found files within it,
+    /// including those in its subdirectories, or the largest N directories if a count is supplied
+    /// with an equals sign, e.g. --largest-dir=5, instead of the files themselves. The start
+    /// directories themselves are not included.
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        conflicts_with = "total_only"
+    )]
+    pub largest_dir: Option<usize>,
+    /// Return a maximum of this many files.
+    #[arg(short, long)]
+    pub limit: Option<usize>,
+    /// Return a maximum of this many files from each directory. Unlike the overall limit, files
+    /// from subdirectories don't count towards their parent directory's limit.
+    #[arg(long)]
+    pub limit_per_dir: Option<usize>,
+    /// Match name patterns against each file's whole path, as displayed, rather than just its file
+    /// name, e.g. so that '*/logs/*' yields every file within a logs directory.
+    #[arg(long, requires = "name_pattern")]
+    pub match_full_path: bool,
+    /// Exit with an error, and an exit code of 3, if more than this many files are found, after
+    /// any limit is applied.
+    #[arg(long)]
+    pub max_count: Option<usize>,
+    /// Only display files modified at most this long ago, e.g. 48h, 30d, 6mo or 2y. Combine with
+    /// --min-age to only display files modified within a window.
+    #[arg(long, value_parser = parse_duration, conflicts_with_all = ["newer_than", "since_boot"])]
+    pub max_age: Option<Duration>,
+    /// Only display the largest files until their combined size would exceed this budget, e.g. 1GiB
+    /// for roughly a gibibyte's worth of files to delete. Accepts the same sizes as
+    /// --exclude-larger-than, and requires sorting by size.
+    #[arg(long, value_parser = parse_human_size)]
+    pub max_results_bytes: Option<u64>,
+    /// Stop scanning once this much time has passed, e.g. 30s, 5m or 500ms, and display the files
+    /// found so far. Durations without a unit are in seconds.
+    #[arg(long, value_parser = parse_duration)]
+    pub max_time: Option<Duration>,
+    /// The maximum size in bytes for displayed files, inclusive.
+    #[arg(long)]
+    pub max_size_bytes: Option<u64>,
+    /// Only display files modified at least this long ago, e.g. 48h, 30d, 6mo or 2y, to find stale
+    /// files.
+    #[arg(long, value_parser = parse_duration, conflicts_with = "older_than")]
+    pub min_age: Option<Duration>,
+    /// Exit with an error, and an exit code of 3, if fewer than this many files are found, after
+    /// any limit is applied.
+    #[arg(long)]
+    pub min_count: Option<usize>,
+    /// Display directories directly containing at least this many files, rather than files, e.g. to
+    /// find directories bloated with small files. Files are counted regardless of the other
+    /// filters, and the size displayed for directories is that of the directory entry itself.
+    #[arg(long, conflicts_with = "include_dirs")]
+    pub min_files_in_dir: Option<usize>,
+    /// The minimum size in bytes for displayed files, inclusive.
+    /// Takes precedence over the minimum size in MiB.
+    #[arg(long)]
+    pub min_size_bytes: Option<u64>,
+    /// Set the minimum size to 1% of the free space on the file system of the start directories,
+    /// or of the fullest of them, but at least 1 MiB, so that only files worth deleting are
+    /// displayed. The fuller the disk, the smaller the files displayed. Only supported on Unix.
+    #[arg(long, conflicts_with_all = ["min_size_bytes", "min_size_mib", "stdin"])]
+    pub min_size_auto: bool,
+    /// The minimum size in MiB for displayed files, e.g. 10 = 10 MiB, 0.1 = 100 KiB.
+    #[arg(short, long, default_value_t = DEFAULT_MIN_SIZE_MIB)]
+    pub min_size_mib: f64,
+    /// Only display files at least this percentage, from 0 to 100, of the size of the largest file
+    /// that would otherwise be displayed, e.g. 10 for files at least a tenth of its size.
+    #[arg(long, value_parser = parse_percentage)]
+    pub min_size_percent: Option<f64>,
+    /// Move found files into this directory, preserving their relative paths. Files already in it
+    /// are never overwritten. Requires --yes, unless combined with --dry-run.
+    #[arg(long)]
+    pub move_to: Option<PathBuf>,
+    /// Filter file names by quoted glob patterns, e.g. '*abc*' will yield 1abc2.txt. Patterns are
+    /// matched against the file name alone, unless --match-full-path is passed.
+    #[arg(short, long, conflicts_with = "regex_pattern")]
+    pub name_pattern: Option<String>,
+    /// Only display files modified more recently than this reference file, like `find -newer`.
+    #[arg(long)]
+    pub newer_than: Option<PathBuf>,
+    /// Only search the immediate contents of the start directories, without descending into any
+    /// subdirectories.
+    #[arg(long)]
+    pub no_recurse: bool,
+    /// Only display files modified before this reference file. Combine with --newer-than to only
+    /// display files modified within a window.
+    #[arg(long)]
+    pub older_than: Option<PathBuf>,
+    /// Separate the paths read from standard input with NUL characters rather than newlines, e.g.
+    /// for the output of `find -print0`.
+    #[arg(long, requires = "stdin")]
+    pub null: bool,
+    /// Don't descend into directories on other file systems, e.g. mounted drives, rather than
+    /// crossing mount points as is done by default. Symlinked directories followed with
+    /// --follow-only-top-level-symlinks are judged by their targets, so one pointing to another
+    /// file system is skipped too. Only supported on Unix.
+    #[arg(long, visible_alias = "no-cross-mounts")]
+    pub one_file_system: bool,
+    /// Also search the directories listed in this file, one per line, as if they were supplied
+    /// as start directories. Blank lines and lines starting with # are ignored.
+    #[arg(long, conflicts_with = "stdin")]
+    pub paths_from_file: Option<PathBuf>,
+    /// Display each file's share of the total size of the displayed files, as a percentage column
+    /// between the size and name columns.
+    #[arg(long)]
+    pub percent: bool,
+    /// Pretty-prints file sizes.
+    #[arg(short, long)]
+    pub pretty: bool,
+    /// Display directories that don't contain any files, even within their subdirectories, rather
+    /// than files, e.g. to remove them with --exec. Only the outermost of nested empty directories
+    /// are displayed. Directories that aren't searched, e.g. because they're hidden or unreadable,
+    /// are assumed not to be empty.
+    #[arg(long, conflicts_with_all = ["include_dirs", "min_files_in_dir"])]
+    pub prune_empty_dirs: bool,
+    /// Don't print a message to standard error when no files are found.
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Filter file names by quoted regular expressions, e.g. '\.txt$' will yield abc.txt.
+    #[arg(short, long, value_parser = parse_regex)]
+    pub regex_pattern: Option<Regex>,
+    /// Log the number and total size of the found files, and the largest of them, to syslog once
+    /// the scan is complete, e.g. for centralised auditing on servers. Only supported on Unix.
+    #[arg(long)]
+    pub report_to_syslog: bool,
+    /// Skip files and directories matching the glob patterns listed in .lffignore files, one per
+    /// line, within the scanned directories. Each ignore file applies to the directory it is in and
+    /// everything below it, alongside the ignore files of parent directories.
+    #[arg(long)]
+    pub respect_ignore_files: bool,
+    /// Reverse the order in which found files are displayed, e.g. smallest first when sorting by
+    /// size. Applied before any limit, so that e.g. the smallest files are kept instead.
+    #[arg(long)]
+    pub reverse: bool,
+    /// Right-align file sizes, so that they line up on their least significant digit.
+    #[arg(long)]
+    pub right_align: bool,
+    /// Display a uniform random sample of this many of the matched files, e.g. to spot-check a
+    /// large tree. Only the sampled files are kept in memory during the scan. Unlike the limit,
+    /// the sample doesn't depend on the order files are found in.
+    #[arg(long, group = "seedable")]
+    pub sample: Option<usize>,
+    /// Save all of the matched files, regardless of any limit or the output format, to this file
+    /// as a JSON snapshot, e.g. to compare a later scan to with --compare-to. The snapshot isn't
+    /// saved if the scan is interrupted.
+    #[arg(long)]
+    pub save_snapshot: Option<PathBuf>,
+    /// The seed for the random sample or random sort order, so that the same files are sampled,
+    /// or sorted in the same order, from the same tree across runs. A random seed is used if one
+    /// isn't supplied. Requires --sample or --sort-method, and has no effect on sort methods other
+    /// than random.
+    #[arg(long, requires = "seedable")]
+    pub seed: Option<u64>,
+    /// The separator to place between the file size and name columns, e.g. '\t'.
+    /// Disables padding, so columns will not be aligned. Defaults to two spaces with padding.
+    #[arg(long, value_parser = parse_separator)]
+    pub separator: Option<String>,
+    /// Display the depth of each file relative to its start directory, as the first column in the
+    /// text and table formats. Files directly within a start directory are at depth 0.
+    #[arg(long)]
+    pub show_depth: bool,
+    /// Display the inode number of each file, e.g. to spot hardlinks to the same file, between the
+    /// size and the name in the text and table formats. Only supported on Unix.
+    #[arg(long)]
+    pub show_inode: bool,
+    /// Only display files modified since the system last booted, e.g. to spot runaway logs.
+    /// Currently only supported on Linux.
+    #[arg(long, conflicts_with = "newer_than")]
+    pub since_boot: bool,
+    /// How to sort found files.
+    #[arg(short, long, value_enum, group = "seedable")]
+    pub sort_method: Option<SortMethod>,
+    /// Print statistics about the scan to standard error once it is complete.
+    #[arg(long)]
+    pub stats: bool,
+    /// Read the paths to search from standard input rather than the supplied directories.
+    /// Directories are searched recursively, and files are checked directly.
+    #[arg(long, conflicts_with = "directories")]
+    pub stdin: bool,
+    /// Treat the paths read from standard input as individual files, ignoring any directories
+    /// rather than searching them, so that only the filters and formatting are applied, e.g. to
+    /// narrow down an existing list of files in a pipeline.
+    #[arg(long, requires = "stdin")]
+    pub stdin_filter: bool,
+    /// Base the summaries, i.e. the human format, total only, depth summary, and histogram, on all
+    /// of the matched files, rather than just those displayed after applying any limits. This
+    /// means a limited scan can't exit early, even if the output is unordered.
+    #[arg(long)]
+    pub summary_all: bool,
+    /// Only print the combined size of the found files, formatted per the pretty-printing flags,
+    /// rather than the files themselves. When no files are found, a total of zero is printed.
+    #[arg(long)]
+    pub total_only: bool,
+    /// Display files in the order they're found, rather than ordering them by path when no sort
+    /// method is supplied. This is faster, particularly with a limit, since the scan can stop as
+    /// soon as enough files are found, but the output may differ between runs.
+    #[arg(long)]
+    pub unordered: bool,
+    /// Print each directory entered and each file considered to standard error as the scan runs,
+    /// along with whether the file was kept or the reason it was dropped. Since directories are
+    /// scanned in parallel, lines may be interleaved.
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// Print a warning to standard error listing any directories that couldn't be read, e.g. due
+    /// to permissions, once the scan is complete.
+    #[arg(long)]
+    pub warn_unreadable: bool,
+    /// After the initial scan, watch the start directories for changes, clearing the screen and
+    /// scanning again once they settle. Runs until interrupted with Ctrl-C.
+    #[arg(long, conflicts_with_all = ["stdin", "move_to", "exec", "batch_exec"])]
+    pub watch: bool,
+    /// Confirm that actions modifying the file system, such as moving files, should be performed.
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// The default arguments are the same as those used on the command line when no flags are passed,
+/// so that library users can construct arguments without clap, e.g.
+/// `LffArgs { directories: vec![String::from("logs")], ..LffArgs::default() }`.
+impl Default for LffArgs {
+    fn default() -> Self {
+        LffArgs {
+            directories: Vec::new(),
+            absolute: false,
+            absolute_from: None,
+            after_scan_command: None,
+            age: false,
+            auto_units: false,
+            base_ten: false,
+            batch_exec: None,
+            case_sensitive: false,
+            color: ColorChoice::Auto,
+            color_tier_red: 1024 * MEBIBYTE,
+            color_tier_yellow: 100 * MEBIBYTE,
+            color_tiers: false,
+            compare_to: None,
+            config: None,
+            breadth_first: false,
+            columns: None,
+            dedup_hardlinks: false,
+            dedupe_output: false,
+            depth_summary: false,
+            dereference_count: false,
+            dry_run: false,
+            exclude_empty: false,
+            exclude_extensions: Vec::new(),
+            exclude_hidden: false,
+            exclude_larger_than: None,
+            exec: None,
+            exec_shell: false,
+            explain: false,
+            ext_thresholds: None,
+            extension: None,
+            extension_case_fold: false,
+            follow_only_top_level_symlinks: false,
+            format: OutputFormat::Text,
+            group_by_extension: false,
+            histogram: false,
+            hidden_only: false,
+            ignore_case: false,
+            include_dirs: false,
+            into_archives: false,
+            largest_dir: None,
+            limit: None,
+            limit_per_dir: None,
+            match_full_path: false,
+            max_age: None,
+            max_count: None,
+            max_results_bytes: None,
+            max_time: None,
+            max_size_bytes: None,
+            min_age: None,
+            min_count: None,
+            min_files_in_dir: None,
+            min_size_bytes: None,
+            min_size_auto: false,
+            min_size_mib: DEFAULT_MIN_SIZE_MIB,
+            min_size_percent: None,
+            move_to: None,
+            name_pattern: None,
+            newer_than: None,
+            no_recurse: false,
+            older_than: None,
+            null: false,
+            one_file_system: false,
+            paths_from_file: None,
+            percent: false,
+            pretty: false,
+            prune_empty_dirs: false,
+            quiet: false,
+            regex_pattern: None,
+            report_to_syslog: false,
+            respect_ignore_files: false,
+            reverse: false,
+            right_align: false,
+            sample: None,
+            save_snapshot: None,
+            seed: None,
+            separator: None,
+            show_depth: false,
+            show_inode: false,
+            since_boot: false,
+            sort_method: None,
+            stats: false,
+            stdin: false,
+            stdin_filter: false,
+            summary_all: false,
+            total_only: false,
+            unordered: false,
+            verbose: false,
+            warn_unreadable: false,
+            watch: false,
+            yes: false,
+        }
+    }
+}
+
+/// The default values for flags read from a config file, each of which is only applied if the flag
+/// wasn't supplied on the command line. Unknown keys are rejected so that typos don't go unnoticed.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LffConfig {
+    absolute: Option<bool>,
+    base_ten: Option<bool>,
+    exclude_hidden: Option<bool>,
+    format: Option<OutputFormat>,
+    limit: Option<usize>,
+    min_size_mib: Option<f64>,
+    pretty: Option<bool>,
+    quiet: Option<bool>,
+    right_align: Option<bool>,
+    sort_method: Option<SortMethod>,
+}
+
+impl LffConfig {
+    /// Returns the command-line arguments equivalent to the values in this config, except for
+    /// those supplied on the command line, as recorded in the supplied matches, and those that
+    /// conflict with them. Flags switched off in the config are already off by default, so they
+    /// have no equivalent.
+    fn to_cli_args(&self, matches: &ArgMatches) -> Vec<OsString> {
+        let command: clap::Command = LffArgs::command();
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        // Conflicts are only declared on one of each pair of arguments, so both are checked.
+        let conflicts = |first: &Arg, second: &Arg| {
+            command
+                .get_arg_conflicts_with(first)
+                .iter()
+                .any(|arg| arg.get_id() == second.get_id())
+        };
+        let conflicts_with_cli = |id: &str| {
+            let Some(config_arg) = command.get_arguments().find(|arg| arg.get_id() == id) else {
+                return false;
+            };
+            command
+                .get_arguments()
+                .filter(|arg| from_cli(arg.get_id().as_str()))
+                .any(|arg| conflicts(arg, config_arg) || conflicts(config_arg, arg))
+        };
+        let mut cli_args: Vec<OsString> = Vec::new();
+        let mut push = |id: &str, value: Option<String>| {
+            if !from_cli(id) && !conflicts_with_cli(id) {
+                let flag: String = format!("--{}", id.replace('_', "-"));
+                cli_args.push(OsString::from(match value {
+                    Some(value) => format!("{flag}={value}"),
+                    None => flag,
+                }));
+            }
+        };
+        for (id, enabled) in [
+            ("absolute", self.absolute),
+            ("base_ten", self.base_ten),
+            ("exclude_hidden", self.exclude_hidden),
+            ("pretty", self.pretty),
+            ("quiet", self.quiet),
+            ("right_align", self.right_align),
+        ] {
+            if enabled == Some(true) {
+                push(id, None);
+            }
+        }
+        if let Some(format) = self.format.as_ref().and_then(ValueEnum::to_possible_value) {
+            push("format", Some(format.get_name().to_string()));
+        }
+        if let Some(limit) = self.limit {
+            push("limit", Some(limit.to_string()));
+        }
+        if let Some(min_size_mib) = self.min_size_mib {
+            push("min_size_mib", Some(min_size_mib.to_string()));
+        }
+        if let Some(sort_method) = self
+            .sort_method
+            .as_ref()
+            .and_then(ValueEnum::to_possible_value)
+        {
+            push("sort_method", Some(sort_method.get_name().to_string()));
+        }
+        cli_args
+    }
+}
+
+/// Returns the default location of the config file, based on the `$XDG_CONFIG_HOME` and `$HOME`
+/// environment variables.
+fn default_config_path() -> Option<PathBuf> {
+    config_path_in(var_os("XDG_CONFIG_HOME"), var_os("HOME"))
+}
+
+/// Returns the location of the config file - `lff/config.toml` within the supplied config home, or
+/// `.config` within the supplied home directory if there is no config home - or `None` if neither
+/// is supplied.
+fn config_path_in(config_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let config_dir: PathBuf = match config_home {
+        Some(config_home) => PathBuf::from(config_home),
+        None => PathBuf::from(home?).join(".config"),
+    };
+    Some(config_dir.join("lff").join("config.toml"))
+}
+
+/// Reads the config file at the supplied path. If the file doesn't exist and wasn't explicitly
+/// supplied, an empty config is returned, since having no config file is perfectly normal.
+///
+/// # Errors
+///
+/// - If the config file was explicitly supplied, but doesn't exist.
+/// - If the config file cannot be read.
+/// - If the config file is not valid TOML, or contains unknown keys or invalid values.
+fn read_config(config_path: &Path, explicit: bool) -> Result<LffConfig> {
+    let contents: String = match read_to_string(config_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound && !explicit => {
+            return Ok(LffConfig::default())
+        }
+        Err(err) => {
+            return Err(err).wrap_err_with(|| format!("Could not read config file {config_path:?}"))
+        }
+    };
+    toml::from_str(&contents).wrap_err_with(|| format!("Invalid config file {config_path:?}"))
+}
+
+/// Parses the supplied command-line arguments, including the name of the binary, applying the
+/// defaults from the config file for any flags that weren't supplied. As with clap's own parsing,
+/// the process exits with a usage message if the arguments are invalid, or if help was requested.
+///
+/// # Errors
+///
+/// - If there is an issue reading or applying the config file in [parse_args_with_config_from].
+pub fn parse_args_with_config<I, T>(raw_args: I) -> Result<LffArgs>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    parse_args_with_config_from(raw_args, default_config_path())
+}
+
+/// Parses the supplied command-line arguments as in [parse_args_with_config], reading the config
+/// file from the supplied default path if none was supplied on the command line. The config values
+/// are supplied to clap as extra arguments, so that they are validated in the same way as the
+/// arguments on the command line, except for those that conflict with the supplied arguments,
+/// which are dropped in favour of them.
+///
+/// # Errors
+///
+/// - If there is an issue reading the config file in [read_config].
+/// - If the values in the config file are invalid.
+fn parse_args_with_config_from<I, T>(raw_args: I, default_path: Option<PathBuf>) -> Result<LffArgs>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut raw_args: Vec<OsString> = raw_args.into_iter().map(Into::into).collect();
+    let matches: ArgMatches = LffArgs::command().get_matches_from(&raw_args);
+    let config_path: Option<(PathBuf, bool)> = match matches.get_one::<PathBuf>("config") {
+        Some(config_path) => Some((config_path.clone(), true)),
+        None => default_path.map(|default_path| (default_path, false)),
+    };
+    let Some((config_path, explicit)) = config_path else {
+        return Ok(LffArgs::from_arg_matches(&matches)?);
+    };
+    let config_args: Vec<OsString> = read_config(&config_path, explicit)?.to_cli_args(&matches);
+    if config_args.is_empty() {
+        return Ok(LffArgs::from_arg_matches(&matches)?);
+    }
+    // The config arguments go straight after the name of the binary, so that they can't be taken
+    // as start directories.
+    let insert_at: usize = raw_args.len().min(1);
+    raw_args.splice(insert_at..insert_at, config_args);
+    let matches: ArgMatches = LffArgs::command()
+        .try_get_matches_from(raw_args)
+        .wrap_err_with(|| format!("Invalid arguments with config file {config_path:?} applied"))?;
+    Ok(LffArgs::from_arg_matches(&matches)?)
+}
+
+/// The reasons for which a file can be rejected by the filters imposed by the command-line
+/// arguments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LffRejection {
+    TooSmall,
+    TooLarge,
+    WrongExtension,
+    WrongName,
+    Hidden,
+    NotHidden,
+    TooOld,
+    TooNew,
+}
+
+/// Describes the rejection as the reason a file was dropped, for diagnostic output.
+impl Display for LffRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let reason: &str = match self {
+            LffRejection::TooSmall => "too small",
+            LffRejection::TooLarge => "too large",
+            LffRejection::WrongExtension => "wrong extension",
+            LffRejection::WrongName => "wrong name",
+            LffRejection::Hidden => "hidden",
+            LffRejection::NotHidden => "not hidden",
+            LffRejection::TooOld => "too old",
+            LffRejection::TooNew => "too new",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// Totals the sizes of found files, counting files that are hardlinked to the same underlying file
+/// only once if hardlinks are being deduplicated. Underlying files are identified by their device
+/// and inode, so files without these, e.g. on non-Unix platforms, are always counted.
+struct LffSizeTotaller {
+    seen: Option<HashSet<(u64, u64)>>,
+}
+
+impl LffSizeTotaller {
+    /// Creates a totaller, which only tracks the underlying files it has counted if hardlinks are
+    /// being deduplicated.
+    fn new(dedup_hardlinks: bool) -> Self {
+        LffSizeTotaller {
+            seen: dedup_hardlinks.then(HashSet::new),
+        }
+    }
+
+    /// Returns the size of the supplied file to count towards a total, which is zero if it is a
+    /// hardlink to an underlying file that has already been counted.
+    fn counted_size(&mut self, file: &LffFile) -> u64 {
+        let already_counted: bool = match (&mut self.seen, file.device.zip(file.inode)) {
+            (Some(seen), Some(identity)) => !seen.insert(identity),
+            _ => false,
+        };
+        match already_counted {
+            true => 0,
+            false => file.size,
+        }
+    }
+
+    /// Returns the total size of the supplied files.
+    fn total<'a>(&mut self, files: impl IntoIterator<Item = &'a LffFile>) -> u64 {
+        files.into_iter().map(|file| self.counted_size(file)).sum()
+    }
+}
+
+/// Statistics about the work done during a scan, recorded when the stats flag is passed. Atomic
+/// counters are used since directories are handled in parallel. Derives `Default` so that all
+/// counters start at zero.
+#[derive(Default)]
+struct LffStats {
+    files_examined: AtomicUsize,
+    dirs_traversed: AtomicUsize,
+    too_small: AtomicUsize,
+    too_large: AtomicUsize,
+    wrong_extension: AtomicUsize,
+    wrong_name: AtomicUsize,
+    hidden: AtomicUsize,
+    not_hidden: AtomicUsize,
+    too_old: AtomicUsize,
+    too_new: AtomicUsize,
+}
+
+impl LffStats {
+    /// Records that a file was examined, along with the reason it was rejected by the filters, if
+    /// it was.
+    fn record_file(&self, rejection: Option<LffRejection>) {
+        self.files_examined.fetch_add(1, Ordering::Relaxed);
+        let skipped_counter: &AtomicUsize = match rejection {
+            Some(LffRejection::TooSmall) => &self.too_small,
+            Some(LffRejection::TooLarge) => &self.too_large,
+            Some(LffRejection::WrongExtension) => &self.wrong_extension,
+            Some(LffRejection::WrongName) => &self.wrong_name,
+            Some(LffRejection::Hidden) => &self.hidden,
+            Some(LffRejection::NotHidden) => &self.not_hidden,
+            Some(LffRejection::TooOld) => &self.too_old,
+            Some(LffRejection::TooNew) => &self.too_new,
+            None => return,
+        };
+        skipped_counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a directory was traversed.
+    fn record_dir(&self) {
+        self.dirs_traversed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A time by which a scan should stop, which records whether it was reached, so that the results
+/// can be reported as partial.
+struct LffDeadline {
+    at: Instant,
+    reached: AtomicBool,
+}
+
+impl LffDeadline {
+    /// Creates a deadline the supplied duration after the supplied start time.
+    fn new(start_time: Instant, duration: Duration) -> Self {
+        LffDeadline {
+            at: start_time + duration,
+            reached: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns whether the deadline has been reached, recording it if so.
+    fn check(&self) -> bool {
+        if Instant::now() >= self.at {
+            self.reached.store(true, Ordering::Relaxed);
+        }
+        self.reached.load(Ordering::Relaxed)
+    }
+}
+
+/// Hashes the supplied file name with the supplied seed, giving the file a pseudo-random position
+/// that only depends on the two, e.g. for sampling and sorting randomly. The 64-bit FNV-1a hash is
+/// used, rather than the standard library's, since that may change between Rust versions, which
+/// would change which files a seed selects.
+fn seeded_hash(seed: u64, name: &OsStr) -> u64 {
+    seed.to_le_bytes()
+        .iter()
+        .chain(name.as_encoded_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// Returns the supplied seed, or a random one if none was supplied.
+fn seed_or_random(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| RandomState::new().build_hasher().finish())
+}
+
+/// A uniform random sample of a fixed number of found files, gathered as the scan runs so that only
+/// the sampled files are kept in memory. Each file is keyed by hashing its name with the seed, and
+/// the files with the smallest keys are kept. Since a file's key doesn't depend on when it was
+/// found, the batches found in parallel can be merged in any order, and the same seed always
+/// samples the same files from the same tree.
+struct LffSampler {
+    size: usize,
+    seed: u64,
+    reservoir: Mutex<BTreeMap<(u64, OsString), LffFile>>,
+}
+
+impl LffSampler {
+    /// Creates an empty sample of the supplied size, using the supplied seed.
+    fn new(size: usize, seed: u64) -> Self {
+        LffSampler {
+            size,
+            seed,
+            reservoir: Mutex::default(),
+        }
+    }
+
+    /// Merges the supplied files into the sample, evicting the files with the largest keys once
+    /// the sample is full. The name is included in the key so that colliding hashes can't evict
+    /// each other.
+    fn offer(&self, files: Vec<LffFile>) {
+        // The keys are computed before locking, so that other threads aren't held up by hashing.
+        let keyed_files: Vec<((u64, OsString), LffFile)> = files
+            .into_iter()
+            .map(|file| {
+                (
+                    (seeded_hash(self.seed, &file.name), file.name.clone()),
+                    file,
+                )
+            })
+            .collect();
+        let mut reservoir: MutexGuard<BTreeMap<(u64, OsString), LffFile>> =
+            self.reservoir.lock().unwrap();
+        for (key, file) in keyed_files {
+            reservoir.insert(key, file);
+            if reservoir.len() > self.size {
+                reservoir.pop_last();
+            }
+        }
+    }
+
+    /// Takes the sampled files, leaving the sample empty.
+    fn take(&self) -> Vec<LffFile> {
+        std::mem::take(&mut *self.reservoir.lock().unwrap())
+            .into_values()
+            .collect()
+    }
+}
+
+/// A directory searched when looking for empty directories, linked to its parent so that finding a
+/// file within it marks every directory above it as occupied too. The `depth` is that of the
+/// directory itself, and the `occupied` flag is atomic since the directory's descendants are
+/// handled in parallel.
+struct LffOccupancy {
+    path: PathBuf,
+    depth: usize,
+    occupied: AtomicBool,
+    parent: Option<Arc<LffOccupancy>>,
+}
+
+impl LffOccupancy {
+    /// Creates an unoccupied directory with the supplied details, recording it in the supplied list
+    /// of searched directories.
+    fn track(
+        path: &Path,
+        depth: usize,
+        parent: Option<Arc<LffOccupancy>>,
+        searched_dirs: &Mutex<Vec<Arc<LffOccupancy>>>,
+    ) -> Arc<Self> {
+        let occupancy: Arc<LffOccupancy> = Arc::new(LffOccupancy {
+            path: path.to_path_buf(),
+            depth,
+            occupied: AtomicBool::new(false),
+            parent,
+        });
+        searched_dirs.lock().unwrap().push(occupancy.clone());
+        occupancy
+    }
+
+    /// Marks this directory, and every directory above it, as occupied. We can stop at the first
+    /// directory that was already occupied, since every directory above it must be too.
+    fn mark(&self) {
+        let mut current: Option<&LffOccupancy> = Some(self);
+        while let Some(dir) = current {
+            if dir.occupied.swap(true, Ordering::Relaxed) {
+                break;
+            }
+            current = dir.parent.as_deref();
+        }
+    }
+
+    /// Returns whether this directory is empty, but its parent isn't, meaning it's the outermost
+    /// of any nested empty directories.
+    fn is_outermost_empty(&self) -> bool {
+        !self.occupied.load(Ordering::Relaxed)
+            && self
+                .parent
+                .as_ref()
+                .is_none_or(|parent| parent.occupied.load(Ordering::Relaxed))
+    }
+}
+
+/// The state for a scan of a single start directory, shared between each of the directories
+/// handled within it. Derives `Default` for convenience's sake when scanning with no state, e.g. in
+/// tests.
+///
+/// The `start_device` is the ID of the device the start directory resides on, and is only present
+/// if the scan is restricted to a single file system. Similarly, `stats` is only present if
+/// statistics are being recorded, and is shared between the scans of all start directories, as is
+/// the `modified_after` and `modified_before` cutoffs, which are only present if filtering by
+/// reference files, the `unreadable` list of directories that couldn't be opened, and the
+/// `broken_symlinks` list of symlinks whose targets don't exist, which are only present if these
+/// are being reported. If the `sink` is present, found files are sent to it as soon as they are
+/// found, rather than being returned, and if the `sampler` is present, they are offered to it
+/// instead. Likewise, if `diagnostics` is present, a line is written to it for each directory
+/// entered and each file considered. If the `cancelled` flag is present and gets set, or the
+/// `deadline` is present and is reached, no further entries are handled, so that the scan winds
+/// down with the files found so far. When looking for empty directories, the `occupancy` is that of
+/// the directory currently being handled, and every directory searched is recorded in
+/// `searched_dirs`.
+///
+/// The `depth` is that of the directory currently being handled, with the start directory at depth
+/// 0, and `in_hidden_dir` is whether the directory is, or is within, a hidden directory. The
+/// `ignore_rules` are those of the ignore files in the directory and its parents, if any, and are
+/// reference-counted since they're shared with the contexts of its subdirectories. The same goes
+/// for the `found` count of files found so far, which is shared by every directory of the scan, so
+/// that an unordered scan with a limit can stop once enough files have been found.
+#[derive(Default, Clone)]
+pub struct LffScanContext<'a> {
+    start_device: Option<u64>,
+    stats: Option<&'a LffStats>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    unreadable: Option<&'a Mutex<Vec<PathBuf>>>,
+    broken_symlinks: Option<&'a Mutex<Vec<PathBuf>>>,
+    sink: Option<&'a SyncSender<Result<LffFile>>>,
+    sampler: Option<&'a LffSampler>,
+    diagnostics: Option<&'a LffDiagnosticSink<'a>>,
+    cancelled: Option<&'a AtomicBool>,
+    deadline: Option<&'a LffDeadline>,
+    depth: usize,
+    in_hidden_dir: bool,
+    ignore_rules: Option<Arc<LffIgnoreRules>>,
+    occupancy: Option<Arc<LffOccupancy>>,
+    searched_dirs: Option<&'a Mutex<Vec<Arc<LffOccupancy>>>>,
+    found: Arc<AtomicUsize>,
+}
+
+impl LffScanContext<'_> {
+    /// Sends the supplied found files to the sink or sampler, if there is one, returning an empty
+    /// `Vec` in their place. Otherwise, the files are returned as they are.
+    ///
+    /// # Errors
+    ///
+    /// - If the receiving end of the sink has been dropped, meaning the scan should stop.
+    fn emit(&self, files: Vec<LffFile>) -> Result<Vec<LffFile>> {
+        self.found.fetch_add(files.len(), Ordering::Relaxed);
+        if let Some(sampler) = self.sampler {
+            sampler.offer(files);
+            return Ok(Vec::new());
+        }
+        let Some(sink) = self.sink else {
+            return Ok(files);
+        };
+        for file in files {
+            sink.send(Ok(file))
+                .map_err(|_| eyre!("The scan was stopped by the receiver"))?;
+        }
+        Ok(Vec::new())
+    }
+
+    /// Returns whether the supplied limit has been reached by the files found so far, and the scan
+    /// can stop since its output is unordered. Since entries are handled in parallel, more files
+    /// than the limit may have been found by the time this is the case.
+    fn reached_limit(&self, args: &LffArgs) -> bool {
+        args.limit
+            .is_some_and(|lim| can_stop_at_limit(args) && self.found.load(Ordering::Relaxed) >= lim)
+    }
+
+    /// Logs a diagnostic line at the supplied level, and writes it to the diagnostics sink, if
+    /// there is one. The line is only built if it will be used, so that scans without diagnostics
+    /// don't pay for the formatting.
+    fn diagnose(&self, level: Level, line: impl FnOnce() -> String) {
+        let logged: bool = log_enabled!(level);
+        if !logged && self.diagnostics.is_none() {
+            return;
+        }
+        let line: String = line();
+        if logged {
+            log!(level, "{}", line);
+        }
+        if let Some(diagnostics) = self.diagnostics {
+            diagnostics(line);
+        }
+    }
+
+    /// Returns whether the scan has been cancelled, or its deadline has been reached, meaning no
+    /// further entries should be handled.
+    fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(LffDeadline::check)
+    }
+
+    /// Returns the context for handling the subdirectory of the current directory at the supplied
+    /// path, reading the subdirectory's ignore file if ignore files are being respected.
+    ///
+    /// # Errors
+    ///
+    /// - If there is an issue reading the subdirectory's ignore file in [read_ignore_file].
+    fn descend(&self, dir_path: &Path, args: &LffArgs) -> Result<Self> {
+        Ok(LffScanContext {
+            depth: self.depth + 1,
+            in_hidden_dir: self.in_hidden_dir || path_is_hidden(dir_path),
+            ignore_rules: match args.respect_ignore_files {
+                true => read_ignore_file(dir_path, self)?,
+                false => None,
+            },
+            occupancy: self.searched_dirs.zip(self.occupancy.clone()).map(
+                |(searched_dirs, parent)| {
+                    LffOccupancy::track(dir_path, self.depth, Some(parent), searched_dirs)
+                },
+            ),
+            ..self.clone()
+        })
+    }
+
+    /// Marks the directory currently being handled as occupied, if looking for empty directories.
+    /// This is done for every entry other than a subdirectory being descended into, so that
+    /// directories which aren't searched are assumed not to be empty.
+    fn mark_occupied(&self) {
+        if let Some(occupancy) = &self.occupancy {
+            occupancy.mark();
+        }
+    }
+}
+
+/// The glob patterns read from an ignore file, along with the rules of the ignore files in parent
+/// directories, which also apply to the directory containing the ignore file.
+struct LffIgnoreRules {
+    directory: PathBuf,
+    patterns: GlobSet,
+    parent: Option<Arc<LffIgnoreRules>>,
+}
+
+impl LffIgnoreRules {
+    /// Returns whether the supplied path matches any of the patterns of these rules, or of their
+    /// parents. Patterns are matched against both the path's file name and the path relative to
+    /// the directory containing the ignore file, so that e.g. both `*.md` and `docs/*.md` work.
+    fn is_ignored(&self, path: &Path) -> bool {
+        let mut rules: Option<&LffIgnoreRules> = Some(self);
+        while let Some(current) = rules {
+            let name_matches: bool = path
+                .file_name()
+                .is_some_and(|name| current.patterns.is_match(name));
+            let relative_matches: bool = path
+                .strip_prefix(&current.directory)
+                .is_ok_and(|relative| current.patterns.is_match(relative));
+            if name_matches || relative_matches {
+                return true;
+            }
+            rules = current.parent.as_deref();
+        }
+        false
+    }
+}
+
+/// Reads the ignore file in the supplied directory, returning its rules on top of the parent rules
+/// in the supplied context. If there is no ignore file, the parent rules are returned unchanged, as
+/// they are if the ignore file can't be read, in which case it is skipped with a warning, like an
+/// unreadable directory. Blank lines and lines beginning with a `#` are skipped.
+///
+/// # Errors
+///
+/// - If the ignore file contains an invalid glob pattern.
+fn read_ignore_file(
+    directory: &Path,
+    context: &LffScanContext,
+) -> Result<Option<Arc<LffIgnoreRules>>> {
+    let parent: Option<Arc<LffIgnoreRules>> = context.ignore_rules.clone();
+    let ignore_path: PathBuf = directory.join(IGNORE_FILE_NAME);
+    let contents: String = match read_to_string(&ignore_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(parent),
+        Err(err) => {
+            context.diagnose(Level::Warn, || {
+                format!("Skipping unreadable ignore file {ignore_path:?}: {err}")
+            });
+            return Ok(parent);
+        }
+    };
+    let mut patterns: GlobSetBuilder = GlobSetBuilder::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        patterns.add(
+            GlobBuilder::new(line)
+                .literal_separator(true)
+                .build()
+                .wrap_err_with(|| {
+                    format!("Invalid glob in ignore file {ignore_path:?}: '{line}'")
+                })?,
+        );
+    }
+    Ok(Some(Arc::new(LffIgnoreRules {
+        directory: directory.to_path_buf(),
+        // A set of individually valid globs always builds successfully.
+        patterns: patterns.build()?,
+        parent,
+    })))
+}
+
+/// A destination for the diagnostic lines written when the verbose flag is passed. This must be
+/// `Sync`, since directories are handled in parallel.
+type LffDiagnosticSink<'a> = dyn Fn(String) + Sync + 'a;
+
+/// The state shared between all of the entries in a single directory while it is handled. Derives
+/// `Default` so that each directory starts with no canonical path and no matches.
+///
+/// The `canonical_parent` is populated by the first entry to need it when the absolute flag is
+/// passed, so that the directory is only canonicalised once rather than once per entry. If the
+/// directory cannot be canonicalised, each entry falls back to being canonicalised individually.
+/// The `matches` counter is atomic since entries are handled in parallel. Likewise, when looking
+/// for crowded directories, the `files` within the directory are counted, and its `path` is
+/// populated by the first file counted.
+#[derive(Default)]
+struct LffDirState {
+    canonical_parent: OnceLock<Option<PathBuf>>,
+    matches: AtomicUsize,
+    path: OnceLock<PathBuf>,
+    files: AtomicUsize,
+}
+
+impl LffDirState {
+    /// Claims a place for a matching entry within the per-directory limit, if one was supplied,
+    /// returning whether there was a place left.
+    fn claim_match(&self, args: &LffArgs) -> bool {
+        match args.limit_per_dir {
+            Some(lim) => self.matches.fetch_add(1, Ordering::Relaxed) < lim,
+            None => true,
+        }
+    }
+
+    /// Counts the file at the supplied path towards the number of files within the directory.
+    fn count_file(&self, file_path: &Path) {
+        self.path
+            .get_or_init(|| file_path.parent().unwrap_or(file_path).to_path_buf());
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the directory as a found file at the depth of the supplied context, if at least the
+    /// minimum number of files were counted within it. Directories without any files are never
+    /// returned, since their path isn't known.
+    ///
+    /// # Errors
+    ///
+    /// - If there is an issue handling the directory in [handle_entry].
+    fn crowded_directory(
+        &self,
+        args: &LffArgs,
+        context: &LffScanContext,
+    ) -> Result<Option<LffFile>> {
+        let (Some(min_files), Some(dir_path)) = (args.min_files_in_dir, self.path.get()) else {
+            return Ok(None);
+        };
+        if self.files.load(Ordering::Relaxed) < min_files {
+            return Ok(None);
+        }
+        context.diagnose(Level::Trace, || {
+            format!("{dir_path:?} - kept: contains at least {min_files} file(s)")
+        });
+        Ok(Some(LffFile {
+            // The context's depth is that of the directory's contents, which are one level deeper.
+            depth: context.depth.saturating_sub(1),
+            ..handle_entry(dir_path.clone(), args, None)?
+        }))
+    }
+}
+
+/// The result of handling a single directory entry - the found files, and the path of the
+/// subdirectory with the context to handle it with, if the entry is a directory that should be
+/// descended into.
+type LffEntryResult<'a> = (Vec<LffFile>, Option<(PathBuf, LffScanContext<'a>)>);
+
+/// A node in the directory hierarchy reconstructed from found files when displaying them in the
+/// tree formats. Directory nodes have no formatted size unless their totals are displayed, whereas
+/// file nodes - the leaves of the tree - do. The total is the combined size of the files at or
+/// below the node. Children are kept in a `BTreeMap` so that they are displayed in alphabetical
+/// order.
+#[derive(Default)]
+struct LffTreeNode {
+    formatted_size: Option<String>,
+    total: u64,
+    children: BTreeMap<OsString, LffTreeNode>,
+}
+
+/// The outcome of a successful run of the finder, describing the files that were found after any
+/// limits were applied, so that callers can act on them without inspecting the printed output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LffScanOutcome {
+    /// The number of files found.
+    pub count: usize,
+    /// The combined size of the files found, in bytes.
+    pub total_size: u64,
+}
+
+/// The error returned when the number of found files is outside the bounds imposed by the minimum
+/// and maximum count command-line arguments. This is a distinct type so that it can be given its
+/// own exit code.
+#[derive(Debug)]
+pub enum LffCountError {
+    TooFew { found: usize, min: usize },
+    TooMany { found: usize, max: usize },
+}
+
+impl Display for LffCountError {
+    /// Describes the number of files found, and the bound that was violated.
+    ///
+    /// # Errors
+    ///
+    /// - If there is an issue writing to the supplied formatter.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            LffCountError::TooFew { found, min } => {
+                write!(
+                    f,
+                    "Found {found} file(s), fewer than the minimum count of {min}"
+                )
+            }
+            LffCountError::TooMany { found, max } => {
+                write!(
+                    f,
+                    "Found {found} file(s), more than the maximum count of {max}"
+                )
+            }
+        }
+    }
+}
+
+impl StdError for LffCountError {}
+
+/// A custom handler for eyre - we want to omit the location from returned errors.
+pub struct LffEyreHandler;
+
+/// The implementation of the EyreHandler trait for our custom eyre handler.
+impl EyreHandler for LffEyreHandler {
+    /// Defines the format for our custom handler - exactly the same as the standard format except
+    /// without the location.
+    ///
+    /// # Errors
+    /// - If there is an issue writing to the supplied formatter.
+    #[cfg(not(tarpaulin_include))]
+    fn debug(&self, error: &(dyn StdError + 'static), f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{}\n", error)?;
+        if let Some(src) = error.source() {
+            write!(f, "Caused by:\n    {}", src)?;
+        }
+        Ok(())
+    }
+}
+
+/// A custom printer trait - we define this in order to inject a printer dependency into our tests
+/// in order to test standard output and standard error.
+pub trait LffPrinter {
+    /// Prints the given `String` value - we maintain a reference to `self` so that the test
+    /// implementations of this trait can supply data structures to keep track of passed values.
+    fn println(&mut self, value: String);
+    /// Prints the given `String` value as a diagnostic, i.e. separately from the found files.
+    fn eprintln(&mut self, value: String);
+}
+
+/// The standard printer, printing straight to standard out.
+pub struct LffStdoutPrinter;
+
+/// The implementation of our printer trait for the standard printer used in the business logic.
+impl LffPrinter for LffStdoutPrinter {
+    /// Prints the given `String` value using the `println!` macro.
+    #[cfg(not(tarpaulin_include))]
+    fn println(&mut self, value: String) {
+        println!("{}", value);
+    }
+
+    /// Prints the given `String` value to standard error using the `eprintln!` macro.
+    #[cfg(not(tarpaulin_include))]
+    fn eprintln(&mut self, value: String) {
+        eprintln!("{}", value);
+    }
+}
+
+/// An action which modifies the file system, to be run via an [LffActionLog]. Actions must be
+/// `Sync` so that they can be performed in parallel.
+trait LffAction: Sync {
+    /// Returns a description of what the action does, to be displayed in dry runs.
+    fn describe(&self) -> String;
+    /// Performs the action.
+    ///
+    /// # Errors
+    ///
+    /// - If the file system cannot be modified.
+    fn perform(&self) -> Result<()>;
+    /// Returns whether the action can be performed in parallel with others of its kind. Actions
+    /// that could interfere with each other, e.g. moves to the same destination, are performed
+    /// sequentially by default.
+    fn is_parallel(&self) -> bool {
+        false
+    }
+}
+
+/// The central path through which all actions that modify the file system are run. If a dry run
+/// was requested, actions are not performed, and what they would do is printed instead.
+struct LffActionLog {
+    dry_run: bool,
+}
+
+impl LffActionLog {
+    /// Performs the supplied actions, in parallel on the thread pool if they all can be, or prints
+    /// what each would do in turn if this is a dry run. If an action fails, the error is printed
+    /// once all actions have been run, and the remaining actions are still run.
+    ///
+    /// # Errors
+    ///
+    /// - If there is an issue performing any of the actions.
+    fn run(&self, actions: &[Box<dyn LffAction>], printer: &mut dyn LffPrinter) -> Result<()> {
+        if self.dry_run {
+            for action in actions {
+                printer.println(format!("Would {}", action.describe()));
+            }
+            return Ok(());
+        }
+        let results: Vec<Result<()>> = match actions.iter().all(|action| action.is_parallel()) {
+            true => actions.par_iter().map(|action| action.perform()).collect(),
+            false => actions.iter().map(|action| action.perform()).collect(),
+        };
+        let mut failures: usize = 0;
+        // Failures are printed in the order of the actions, regardless of when they occurred.
+        for (action, result) in actions.iter().zip(results) {
+            if let Err(err) = result {
+                failures += 1;
+                printer.eprintln(format!("Could not {}: {}", action.describe(), err));
+            }
+        }
+        match failures {
+            0 => Ok(()),
+            _ => Err(eyre!("{} of {} actions failed", failures, actions.len())),
+        }
+    }
+}
+
+/// An action that moves a file to a new location, creating any missing parent directories.
+struct LffMoveAction {
+    source: PathBuf,
+    destination: PathBuf,
+}
+
+impl LffAction for LffMoveAction {
+    /// Describes the move with the source and destination paths.
+    fn describe(&self) -> String {
+        format!("move {:?} to {:?}", self.source, self.destination)
+    }
+
+    /// Moves the file by renaming it, falling back to copying and then removing it when the
+    /// destination is on another file system, since files cannot be renamed across file systems.
+    /// Existing files are never overwritten.
+    ///
+    /// # Errors
+    ///
+    /// - If something already exists at the destination.
+    /// - If the destination's parent directories cannot be created.
+    /// - If the file cannot be renamed for any reason other than the destination being on another
+    ///   file system.
+    /// - If the file cannot be copied and removed.
+    fn perform(&self) -> Result<()> {
+        // Both renaming and copying would silently replace whatever is already there.
+        if symlink_metadata(&self.destination).is_ok() {
+            return Err(eyre!(
+                "Could not move {:?} to {:?}, since the destination already exists",
+                self.source,
+                self.destination
+            ));
+        }
+        if let Some(parent) = self.destination.parent() {
+            create_dir_all(parent)?;
+        }
+        match rename(&self.source, &self.destination) {
+            Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+                copy(&self.source, &self.destination)?;
+                remove_file(&self.source)?;
+            }
+            result => result?,
+        }
+        Ok(())
+    }
+}
+
+/// An action that runs a command for a file, as built by [exec_action].
+struct LffExecAction {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(&'static str, String)>,
+}
+
+impl LffAction for LffExecAction {
+    /// Describes the command with its program, each of its arguments, and any environment
+    /// variables set for it.
+    fn describe(&self) -> String {
+        let mut description: String = format!("run {:?}", self.program);
+        for arg in &self.args {
+            description.push_str(&format!(" {:?}", arg));
+        }
+        if !self.envs.is_empty() {
+            description.push_str(" with");
+        }
+        for (key, value) in &self.envs {
+            description.push_str(&format!(" {}={:?}", key, value));
+        }
+        description
+    }
+
+    /// Runs the command, waiting for it to finish.
+    ///
+    /// # Errors
+    ///
+    /// - If the command cannot be started.
+    /// - If the command exits with a non-zero status.
+    fn perform(&self) -> Result<()> {
+        let status: ExitStatus = Command::new(&self.program)
+            .args(&self.args)
+            .envs(self.envs.iter().cloned())
+            .status()?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(eyre!("{}", status)),
+        }
+    }
+
+    /// Commands are run in parallel, since each is for different files.
+    fn is_parallel(&self) -> bool {
+        true
+    }
+}
+
+/// Returns an action that runs the supplied command for the file at the supplied path. Without a
+/// shell, the command is split on whitespace, ignoring any quotes, and each {} within it is
+/// replaced with the path, which is appended instead if there is no {}. Since the path is
+/// substituted as an `OsStr` into the arguments, it is passed through exactly, regardless of any
+/// spaces or special characters in it.
+///
+/// With a shell, the path is instead passed to the shell as its first positional argument, and {}
+/// is replaced with a quoted reference to it, so that the path is never interpreted by the shell.
+fn exec_action(command: &str, file_path: &OsStr, shell: bool) -> LffExecAction {
+    if shell {
+        return shell_exec_action(command, file_path);
+    }
+    let mut words: Vec<OsString> = command
+        .split_whitespace()
+        .map(|word| {
+            let mut arg: OsString = OsString::new();
+            for (idx, part) in word.split("{}").enumerate() {
+                if idx > 0 {
+                    arg.push(file_path);
+                }
+                arg.push(part);
+            }
+            arg
+        })
+        .collect();
+    if !command.contains("{}") {
+        words.push(file_path.to_os_string());
+    }
+    // Commands are ensured to be non-empty when parsed, so there is always a program.
+    let program: OsString = words.remove(0);
+    LffExecAction {
+        program,
+        args: words,
+        envs: Vec::new(),
+    }
+}
+
+/// Returns actions that run the supplied command with the supplied paths appended as arguments,
+/// splitting them into batches so that the arguments to each invocation take up at most the
+/// supplied number of bytes. Each argument is counted along with its terminator and pointer, as
+/// they are in `ARG_MAX`. A batch always contains at least one path, even if it is too long alone.
+fn batch_exec_actions(command: &str, paths: &[&OsStr], max_arg_bytes: usize) -> Vec<LffExecAction> {
+    let arg_bytes = |arg: &OsStr| arg.len() + 1 + size_of::<usize>();
+    let mut words: Vec<OsString> = command.split_whitespace().map(OsString::from).collect();
+    // Commands are ensured to be non-empty when parsed, so there is always a program.
+    let program: OsString = words.remove(0);
+    let command_bytes: usize =
+        arg_bytes(&program) + words.iter().map(|w| arg_bytes(w)).sum::<usize>();
+    let mut actions: Vec<LffExecAction> = Vec::new();
+    let mut batch: Vec<OsString> = words.clone();
+    let mut batch_bytes: usize = command_bytes;
+    for path in paths {
+        let path_bytes: usize = arg_bytes(path);
+        if batch.len() > words.len() && batch_bytes + path_bytes > max_arg_bytes {
+            actions.push(LffExecAction {
+                program: program.clone(),
+                args: batch,
+                envs: Vec::new(),
+            });
+            batch = words.clone();
+            batch_bytes = command_bytes;
+        }
+        batch.push(path.to_os_string());
+        batch_bytes += path_bytes;
+    }
+    if batch.len() > words.len() {
+        actions.push(LffExecAction {
+            program,
+            args: batch,
+            envs: Vec::new(),
+        });
+    }
+    actions
+}
+
+/// Returns an action that runs the supplied command through `sh`, with the file's path as its first
+/// positional argument.
+#[cfg(unix)]
+fn shell_exec_action(command: &str, file_path: &OsStr) -> LffExecAction {
+    let script: String = match command.contains("{}") {
+        true => command.replace("{}", "\"$1\""),
+        false => format!("{} \"$1\"", command),
+    };
+    LffExecAction {
+        program: OsString::from("sh"),
+        args: vec![
+            OsString::from("-c"),
+            OsString::from(script),
+            OsString::from("sh"),
+            file_path.to_os_string(),
+        ],
+        envs: Vec::new(),
+    }
+}
+
+/// Returns an action that runs the supplied command through `cmd`. Since `cmd` has no positional
+/// arguments, the path is quoted and substituted into the command directly.
+#[cfg(not(unix))]
+fn shell_exec_action(command: &str, file_path: &OsStr) -> LffExecAction {
+    let quoted_path: String = format!("\"{}\"", file_path.to_string_lossy());
+    let script: String = match command.contains("{}") {
+        true => command.replace("{}", &quoted_path),
+        false => format!("{} {}", command, quoted_path),
+    };
+    LffExecAction {
+        program: OsString::from("cmd"),
+        args: vec![OsString::from("/C"), OsString::from(script)],
+        envs: Vec::new(),
+    }
+}
+
+/// Returns an action that runs the supplied command through `sh` once the scan is complete, with
+/// the supplied number and total size of the found files in its environment.
+#[cfg(unix)]
+fn after_scan_action(command: &str, count: usize, total_size: u64) -> LffExecAction {
+    LffExecAction {
+        program: OsString::from("sh"),
+        args: vec![OsString::from("-c"), OsString::from(command)],
+        envs: vec![
+            ("LFF_COUNT", count.to_string()),
+            ("LFF_TOTAL_BYTES", total_size.to_string()),
+        ],
+    }
+}
+
+/// Returns an action that runs the supplied command through `cmd` once the scan is complete, with
+/// the supplied number and total size of the found files in its environment.
+#[cfg(not(unix))]
+fn after_scan_action(command: &str, count: usize, total_size: u64) -> LffExecAction {
+    LffExecAction {
+        program: OsString::from("cmd"),
+        args: vec![OsString::from("/C"), OsString::from(command)],
+        envs: vec![
+            ("LFF_COUNT", count.to_string()),
+            ("LFF_TOTAL_BYTES", total_size.to_string()),
+        ],
+    }
+}
+
+/// Returns the message logged to syslog once the scan is complete, summarising the supplied number
+/// and total size of the found files, and the largest of them if there are any.
+fn syslog_message(count: usize, total_size: u64, largest: Option<&LffFile>) -> String {
+    let summary: String = format!("lff found {count} file(s) totalling {total_size} bytes");
+    match largest {
+        Some(file) => format!(
+            "{summary}, the largest being {:?} at {} bytes",
+            file.name, file.size
+        ),
+        None => summary,
+    }
+}
+
+/// Logs the supplied message to the local syslog daemon at the informational level, under the
+/// user facility.
+///
+/// # Errors
+///
+/// - If the syslog daemon can't be connected to, or the message can't be sent.
+#[cfg(unix)]
+#[cfg(not(tarpaulin_include))]
+fn report_to_syslog(message: &str) -> Result<()> {
+    use syslog::{Facility, Formatter3164};
+
+    let formatter: Formatter3164 = Formatter3164 {
+        facility: Facility::LOG_USER,
+        hostname: None,
+        process: String::from("lff"),
+        pid: std::process::id(),
+    };
+    syslog::unix(formatter)
+        .and_then(|mut logger| logger.info(message))
+        .map_err(|error| eyre!("Could not report to syslog: {error}"))
+}
+
+/// Syslog is only available on Unix, so reporting to it is unsupported elsewhere.
+///
+/// # Errors
+///
+/// - Always, since this is unsupported.
+#[cfg(not(unix))]
+fn report_to_syslog(_message: &str) -> Result<()> {
+    Err(eyre!(
+        "The report to syslog flag is not supported on this platform"
+    ))
+}
+
+/// Returns the path the file at the supplied path should be moved to within the supplied target
+/// directory, preserving its relative structure. Any root, prefix, or parent directory components
+/// are dropped, so that the destination is always within the target directory.
+fn move_destination(target_dir: &Path, file_path: &Path) -> PathBuf {
+    let mut destination: PathBuf = target_dir.to_path_buf();
+    for component in file_path.components() {
+        if let Component::Normal(part) = component {
+            destination.push(part);
+        }
+    }
+    destination
+}
+
+/// Returns whether the file at the supplied path is a hidden file, i.e. whether its name starts
+/// with a '.' character.
+///
+/// If a file's name cannot be represented in UTF-8, we assume it's not hidden, since we can't
+/// inspect the first character of its name.
+///
+/// Non-file paths will also return false.
+fn path_is_hidden(file_path: &Path) -> bool {
+    match file_path.file_name() {
+        Some(name) => match name.to_str() {
+            Some(str_name) => str_name.starts_with('.'),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+/// Formats the supplied size in bytes for display, pretty-printing it in base 2 or base 10 units if
+/// the appropriate command-line arguments were supplied.
+fn format_size(size: u64, args: &LffArgs) -> String {
+    match args.pretty {
+        true => Size::from_bytes(size)
+            .format()
+            .with_base(if args.base_ten {
+                Base::Base10
+            } else {
+                Base::Base2
+            })
+            // Abbreviate the size so that we don't get the whole word 'bytes' in the output.
+            .with_style(Style::Abbreviated)
+            .to_string(),
+        false => size.to_string(),
+    }
+}
+
+/// Returns the index of the unit that the supplied sizes should all be pretty-printed in, which is
+/// the largest unit that their median size is at least one of. Sizes are in base 10 units if
+/// `base_ten` is true, and in base 2 units otherwise.
+fn auto_unit(sizes: &[u64], base_ten: bool) -> usize {
+    let mut sorted_sizes: Vec<u64> = sizes.to_vec();
+    sorted_sizes.sort_unstable();
+    let median: u64 = sorted_sizes
+        .get(sorted_sizes.len() / 2)
+        .copied()
+        .unwrap_or(0);
+    let unit_size: u64 = if base_ten { 1000 } else { 1024 };
+    let mut unit: usize = 0;
+    while unit + 1 < BASE_TWO_UNITS.len() && median >= unit_size.pow(unit as u32 + 1) {
+        unit += 1;
+    }
+    unit
+}
+
+/// Formats the supplied size in bytes in the unit with the supplied index, in base 10 units if
+/// `base_ten` is true and in base 2 units otherwise. Sizes in bytes are whole numbers, and all
+/// other sizes have two decimal places, so that sizes in the same unit line up.
+fn format_size_in_unit(size: u64, unit: usize, base_ten: bool) -> String {
+    let (unit_size, units): (f64, [&str; 7]) = match base_ten {
+        true => (1000.0, BASE_TEN_UNITS),
+        false => (1024.0, BASE_TWO_UNITS),
+    };
+    match unit {
+        0 => format!("{} {}", size, units[0]),
+        _ => format!(
+            "{:.2} {}",
+            size as f64 / unit_size.powi(unit as i32),
+            units[unit]
+        ),
+    }
+}
+
+/// Returns the inode number of the file with the supplied metadata.
+#[cfg(unix)]
+fn file_inode(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+/// Inode numbers are not exposed on non-Unix platforms, so files go without.
+#[cfg(not(unix))]
+fn file_inode(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Returns the ID of the device that the file with the supplied metadata resides on.
+#[cfg(unix)]
+fn file_device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Device IDs are not exposed on non-Unix platforms, so files go without.
+#[cfg(not(unix))]
+fn file_device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Returns the ID of the device that the supplied path resides on, following any symlink to its
+/// target, since that is where a followed symlinked directory is actually read from.
+///
+/// # Errors
+///
+/// - If metadata cannot be retrieved for the path.
+#[cfg(unix)]
+fn device_id(path: &Path) -> Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(metadata(path)
+        .wrap_err_with(|| format!("Could not retrieve device for {:?}", path))?
+        .dev())
+}
+
+/// Device IDs are not exposed on non-Unix platforms, so restricting scans to a single file system
+/// is unsupported.
+///
+/// # Errors
+///
+/// - Always, since this is unsupported.
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Result<u64> {
+    Err(eyre!(
+        "The one file system flag is not supported on this platform"
+    ))
+}
+
+/// Returns whether a directory residing on the supplied device is on the same file system as the
+/// start directory, and can thus be descended into when the scan is restricted to a single file
+/// system.
+fn is_same_device(start_device: u64, dir_device: u64) -> bool {
+    start_device == dir_device
+}
+
+/// Returns whether the directory at the supplied path is on another file system to the start
+/// directory, which resides on the supplied device if the scan is restricted to a single file
+/// system. The directory's device is only read if the scan is restricted, so this is always false
+/// otherwise.
+///
+/// # Errors
+///
+/// - If the scan is restricted, and the device of the directory cannot be retrieved.
+fn is_across_mount(start_device: Option<u64>, dir_path: &Path) -> Result<bool> {
+    match start_device {
+        Some(start_device) => Ok(!is_same_device(start_device, device_id(dir_path)?)),
+        None => Ok(false),
+    }
+}
+
+/// Extract file details from the supplied `PathBuf`, applying the appropriate command-line
+/// arguments, and returning the created `LffFile` in success cases.
+///
+/// If the absolute flag is passed and the canonical path of the file's parent directory is
+/// supplied, the file's absolute path is generated by joining its name onto that directory, rather
+/// than canonicalising the file's path. This should only be done for entries that are not symlinks,
+/// since canonicalising would otherwise resolve the link.
+///
+/// # Errors
+///
+/// - If the absolute flag is passed, and the file's path cannot be canonicalised.
+/// - If metadata cannot be retrieved for the file.
+fn handle_entry(
+    file_path: PathBuf,
+    args: &LffArgs,
+    canonical_parent: Option<&Path>,
+) -> Result<LffFile> {
+    // The OsString representation of PathBufs is actually pretty good, so we can just use that no
+    // matter what the absolute flag value is.
+    let file_name: OsString = match args.absolute {
+        true => match canonical_parent.zip(file_path.file_name()) {
+            Some((parent, name)) => parent.join(name).into_os_string(),
+            None => canonicalize(&file_path)
+                .wrap_err_with(|| format!("Could not generate absolute path for {:?}", &file_path))?
+                .into_os_string(),
+        },
+        // Yes, cloning isn't good, but it's an extremely minor performance hit in this case.
+        false => file_path.clone().into_os_string(),
+    };
+    let file_extension: Option<OsString> = file_path.extension().map(|ext| ext.to_os_string());
+    // We use symlink_metadata() here rather than just metadata() because we don't want to follow
+    // all the links around the filesystem - this improves performance somewhat. Some other tools in
+    // this area use blocks() and then multiply by the block size to get the true file size, but
+    // we're not overly concerned about that.
+    let metadata: Metadata = symlink_metadata(&file_path)
+        .wrap_err_with(|| format!("Could not retrieve metadata for {:?}", &file_path))?;
+
+    Ok(LffFile {
+        name: file_name,
+        extension: file_extension,
+        size: metadata.len(),
+        formatted_size: format_size(metadata.len(), args),
+        hidden: path_is_hidden(&file_path),
+        is_dir: metadata.is_dir(),
+        // Not all platforms support modification times, in which case we just go without.
+        modified: metadata.modified().ok(),
+        // Files are assumed to be at the top level, unless found while traversing a directory.
+        depth: 0,
+        inode: file_inode(&metadata),
+        device: file_device(&metadata),
+    })
+}
+
+/// Returns whether the supplied file is a zip archive that can be searched within, judging by its
+/// extension.
+fn is_zip_archive(file: &LffFile) -> bool {
+    file.extension
+        .as_ref()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Returns the entries within the supplied zip archive as virtual files, named with the archive's
+/// name and the entry's path separated by a '!', e.g. `assets.zip!images/logo.png`. The size of
+/// each entry is its uncompressed size. Since entries aren't on the file system themselves, they
+/// take on the archive's modification time, and have no inode or device. Directory entries are
+/// skipped.
+///
+/// # Errors
+///
+/// - If the archive cannot be opened, or isn't a valid zip archive.
+/// - If an entry's details cannot be read from the archive.
+fn zip_entries(archive_file: &LffFile, args: &LffArgs) -> Result<Vec<LffFile>> {
+    let mut archive: ZipArchive<File> = ZipArchive::new(File::open(&archive_file.name)?)?;
+    let mut entries: Vec<LffFile> = Vec::new();
+    for idx in 0..archive.len() {
+        // Only the entry's details are needed, so we read it raw to avoid decompressing it.
+        let entry: ZipFile<File> = archive.by_index_raw(idx)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let entry_path: &Path = Path::new(entry.name());
+        let mut entry_name: OsString = archive_file.name.clone();
+        entry_name.push("!");
+        entry_name.push(entry.name());
+        entries.push(LffFile {
+            name: entry_name,
+            extension: entry_path.extension().map(|ext| ext.to_os_string()),
+            size: entry.size(),
+            formatted_size: format_size(entry.size(), args),
+            hidden: archive_file.hidden || path_is_hidden(entry_path),
+            is_dir: false,
+            modified: archive_file.modified,
+            // Archives are treated as directories, so their entries are one level deeper.
+            depth: archive_file.depth + 1,
+            inode: None,
+            device: None,
+        });
+    }
+    Ok(entries)
+}
+
+/// Returns whether the supplied file has the supplied extension.
+fn has_extension(file: &LffFile, extension: &OsStr) -> bool {
+    match file.extension {
+        // We need to use a ref to the file's extension in order to compare OsString equality.
+        Some(ref file_ext) => file_ext == extension,
+        None => false,
+    }
+}
+
+/// Returns the supplied extension without a single leading dot, if it has one, since file
+/// extensions are compared without their dot, but users naturally supply e.g. .txt. Extensions that
+/// aren't valid Unicode are returned as is.
+fn strip_extension_dot(extension: OsString) -> OsString {
+    match extension.to_str().and_then(|ext| ext.strip_prefix('.')) {
+        Some(stripped) => OsString::from(stripped),
+        None => extension,
+    }
+}
+
+/// Returns the reason the supplied file is rejected by the optional conditions imposed by the
+/// supplied command-line arguments and scan context, or `None` if it meets all of them. If the file
+/// fails multiple conditions, the first in the order of [LffRejection] is returned.
+///
+/// # Errors
+///
+/// - If the supplied glob pattern to filter on is invalid.
+fn find_rejection(
+    file: &LffFile,
+    args: &LffArgs,
+    context: &LffScanContext,
+) -> Result<Option<LffRejection>> {
+    // Byte thresholds are compared directly, avoiding the imprecision of floating point arithmetic.
+    let ext_threshold: Option<u64> = args
+        .ext_thresholds
+        .as_ref()
+        .zip(file.extension.as_ref())
+        .and_then(|(thresholds, ext)| thresholds.get(ext).copied());
+    let large_enough: bool = match ext_threshold.or(args.min_size_bytes) {
+        Some(min_bytes) => file.size >= min_bytes,
+        None => file.size as f64 / MEBIBYTE as f64 >= args.min_size_mib,
+    } && !(args.exclude_empty && file.size == 0);
+    // Excluding larger files is an alias for the maximum size, so the two can't both be present.
+    let small_enough: bool = match args.max_size_bytes.or(args.exclude_larger_than) {
+        Some(max_bytes) => file.size <= max_bytes,
+        None => true,
+    };
+    // Excluded extensions win over the extension filter, should a file match both.
+    let excluded_ext: bool = args
+        .exclude_extensions
+        .iter()
+        .any(|arg_ext| has_extension(file, arg_ext));
+    let correct_ext: bool = !excluded_ext
+        && match &args.extension {
+            Some(arg_ext) => has_extension(file, arg_ext),
+            None => true,
+        };
+    // Name patterns are matched against the file name alone by default, since otherwise patterns
+    // like 'snow*' would never match, with the path of the start directory in front of the name.
+    let pattern_target: &OsStr = match args.match_full_path {
+        true => &file.name,
+        false => Path::new(&file.name).file_name().unwrap_or(&file.name),
+    };
+    // Case-sensitive matching takes precedence, in case both flags are set outside of the CLI.
+    let correct_name: bool = match &args.name_pattern {
+        Some(arg_np) => GlobBuilder::new(arg_np)
+            .case_insensitive(args.ignore_case && !args.case_sensitive)
+            .build()
+            .wrap_err_with(|| eyre!("Invalid glob from name pattern flag: '{arg_np}'"))?
+            .compile_matcher()
+            .is_match(pattern_target),
+        None => match &args.regex_pattern {
+            Some(arg_rp) => arg_rp.is_match(&file.name.to_string_lossy()),
+            None => true,
+        },
+    };
+    // Files within hidden directories are considered hidden too, even if their names aren't.
+    let hidden: bool = file.hidden || context.in_hidden_dir;
+    let is_not_hidden: bool = match &args.exclude_hidden {
+        true => !hidden,
+        false => true,
+    };
+    let is_hidden: bool = match &args.hidden_only {
+        true => hidden,
+        false => true,
+    };
+    // Files without a modification time can't be shown to be newer or older, so they're excluded.
+    let new_enough: bool = match context.modified_after {
+        Some(cutoff) => file.modified.is_some_and(|modified| modified > cutoff),
+        None => true,
+    };
+    let old_enough: bool = match context.modified_before {
+        Some(cutoff) => file.modified.is_some_and(|modified| modified < cutoff),
+        None => true,
+    };
+    let rejection: Option<LffRejection> = if !large_enough {
+        Some(LffRejection::TooSmall)
+    } else if !small_enough {
+        Some(LffRejection::TooLarge)
+    } else if !correct_ext {
+        Some(LffRejection::WrongExtension)
+    } else if !correct_name {
+        Some(LffRejection::WrongName)
+    } else if !is_not_hidden {
+        Some(LffRejection::Hidden)
+    } else if !is_hidden {
+        Some(LffRejection::NotHidden)
+    } else if !new_enough {
+        Some(LffRejection::TooOld)
+    } else if !old_enough {
+        Some(LffRejection::TooNew)
+    } else {
+        None
+    };
+    Ok(rejection)
+}
+
+/// Handle a single directory entry, applying the appropriate command-line arguments. Returns a
+/// `Vec` of created `LffFile`s - containing the entry itself if it matches the filters - as well as
+/// the path and the context to handle it with if the entry is a directory that should be
+/// descended into. The caller is responsible for opening the directory with [open_directory] and
+/// handling it, so that it can choose the traversal order.
+///
+/// The supplied `dir_state` should be shared between all entries in the same directory.
+///
+/// # Errors
+///
+/// - If the directory entry cannot be retrieved.
+/// - If the file type cannot be determined for the retrieved directory entry.
+/// - If there is an issue handling the directory entry in [handle_entry].
+/// - If the supplied glob pattern to filter on is invalid.
+/// - If the scan is restricted to a single file system, and the device cannot be determined for a
+///   subdirectory in [device_id].
+fn handle_dir_entry<'a>(
+    entry_result: IoResult<DirEntry>,
+    args: &LffArgs,
+    context: &LffScanContext<'a>,
+    dir_state: &LffDirState,
+) -> Result<LffEntryResult<'a>> {
+    // The remaining entries are skipped rather than stopping the iteration outright, since that
+    // isn't possible from within rayon's parallel iterators.
+    if context.is_cancelled() {
+        return Ok((vec![], None));
+    }
+    let entry: DirEntry = entry_result?;
+    let file_path: PathBuf = entry.path();
+    if let Some(ignore_rules) = &context.ignore_rules {
+        if ignore_rules.is_ignored(&file_path) {
+            context.diagnose(Level::Debug, || {
+                format!("Skipping {file_path:?}, which matches an ignore file")
+            });
+            return Ok((vec![], None));
+        }
+    }
+    // For whatever reason, using the FileType here to determine whether the entry is a file or a
+    // directory is significantly faster than using the same methods on the PathBuf.
+    let mut entry_type: FileType = entry.file_type()?;
+    // Symlinks to directories directly within a start directory are followed if we've been told
+    // to, since the user explicitly pointed us there. Broken symlinks are left alone.
+    if entry_type.is_symlink() && args.follow_only_top_level_symlinks && context.depth == 0 {
+        if let Ok(target_metadata) = metadata(&file_path) {
+            if target_metadata.is_dir() {
+                entry_type = target_metadata.file_type();
+            }
+        }
+    }
+    // Following a symlink with metadata() fails if its target doesn't exist, unlike with the
+    // symlink_metadata() used for files.
+    if let Some(broken_symlinks) = context.broken_symlinks {
+        if entry_type.is_symlink() && metadata(&file_path).is_err() {
+            context.diagnose(Level::Debug, || {
+                format!("Found broken symlink {file_path:?}")
+            });
+            broken_symlinks.lock().unwrap().push(file_path);
+            return Ok((vec![], None));
+        }
+    }
+    let parent: Option<&Path> = match args.absolute {
+        true => dir_state
+            .canonical_parent
+            .get_or_init(|| file_path.parent().and_then(|dir| canonicalize(dir).ok()))
+            .as_deref(),
+        false => None,
+    };
+    if entry_type.is_file() {
+        // When looking for empty directories, files only need to be noticed, which the caller does.
+        if args.prune_empty_dirs {
+            return Ok((vec![], None));
+        }
+        // When looking for crowded directories, files are only counted, so their details aren't
+        // needed.
+        if args.min_files_in_dir.is_some() {
+            dir_state.count_file(&file_path);
+            return Ok((vec![], None));
+        }
+        let file: LffFile = LffFile {
+            depth: context.depth,
+            ..handle_entry(file_path, args, parent)?
+        };
+        let rejection: Option<LffRejection> = find_rejection(&file, args, context)?;
+        if let Some(stats) = context.stats {
+            stats.record_file(rejection);
+        }
+        // If all our optional conditions are met, and this directory hasn't already contributed
+        // its share of files, return a Vec with a single file.
+        let kept: bool = rejection.is_none() && dir_state.claim_match(args);
+        context.diagnose(Level::Trace, || match rejection {
+            Some(reason) => format!("{:?} - dropped: {}", file.name, reason),
+            None if !kept => format!("{:?} - dropped: directory limit reached", file.name),
+            None => format!("{:?} - kept", file.name),
+        });
+        // Archives are searched within whether or not they're kept themselves, since their entries
+        // may still match.
+        if args.into_archives && is_zip_archive(&file) {
+            let mut files: Vec<LffFile> = Vec::new();
+            // Archives are treated as directories, so their entries have their own limit.
+            let archive_state: LffDirState = LffDirState::default();
+            match zip_entries(&file, args) {
+                Ok(entries) => {
+                    for entry in entries {
+                        let entry_rejection: Option<LffRejection> =
+                            find_rejection(&entry, args, context)?;
+                        if let Some(stats) = context.stats {
+                            stats.record_file(entry_rejection);
+                        }
+                        if entry_rejection.is_none() && archive_state.claim_match(args) {
+                            files.push(entry);
+                        }
+                    }
+                }
+                Err(err) => context.diagnose(Level::Warn, || {
+                    format!("Skipping unreadable archive {:?}: {err}", file.name)
+                }),
+            }
+            if kept {
+                files.insert(0, file);
+            }
+            return Ok((context.emit(files)?, None));
+        }
+        if kept {
+            return Ok((context.emit(vec![file])?, None));
+        }
+    } else if entry_type.is_dir() {
+        // Don't descend into directories on other file systems if we've been told not to.
+        if is_across_mount(context.start_device, &file_path)? {
+            context.diagnose(Level::Debug, || {
+                format!("Skipping directory on another file system {file_path:?}")
+            });
+            return Ok((vec![], None));
+        }
+        // If we're including directories in the output, the directory itself is subject to the
+        // same filters as files.
+        let mut dir_files: Vec<LffFile> = Vec::new();
+        if args.include_dirs {
+            let dir_entry: LffFile = LffFile {
+                depth: context.depth,
+                ..handle_entry(file_path.clone(), args, parent)?
+            };
+            if find_rejection(&dir_entry, args, context)?.is_none() && dir_state.claim_match(args) {
+                dir_files.push(dir_entry);
+            }
+        }
+        let dir_files: Vec<LffFile> = context.emit(dir_files)?;
+        if args.no_recurse {
+            context.diagnose(Level::Debug, || {
+                format!("Not descending into directory {file_path:?}")
+            });
+            return Ok((dir_files, None));
+        }
+        if args.exclude_hidden && path_is_hidden(&file_path) {
+            context.diagnose(Level::Debug, || {
+                format!("Skipping hidden directory {file_path:?}")
+            });
+            return Ok((dir_files, None));
+        }
+        // The subdirectory is only opened when it's handled, so that we don't hold a handle for
+        // every subdirectory waiting to be handled.
+        let dir_context: LffScanContext = context.descend(&file_path, args)?;
+        return Ok((dir_files, Some((file_path, dir_context))));
+    }
+    // We should never really get here, but just in case, return an empty Vec to be flattened out
+    // later.
+    Ok((vec![], None))
+}
+
+/// Opens the subdirectory at the supplied path to be handled with the supplied context, returning
+/// `None` if it can't be read. Unreadable directories are skipped, only keeping track of them if
+/// they're being reported, and are assumed not to be empty.
+fn open_directory(dir_path: &Path, dir_context: &LffScanContext) -> Option<ReadDir> {
+    match read_dir(dir_path) {
+        Ok(dir) => {
+            dir_context.diagnose(Level::Debug, || format!("Entering directory {dir_path:?}"));
+            if let Some(stats) = dir_context.stats {
+                stats.record_dir();
+            }
+            Some(dir)
+        }
+        Err(err) => {
+            dir_context.diagnose(Level::Warn, || {
+                format!("Skipping unreadable directory {dir_path:?}: {err}")
+            });
+            if let Some(unreadable) = dir_context.unreadable {
+                unreadable.lock().unwrap().push(dir_path.to_path_buf());
+            }
+            dir_context.mark_occupied();
+            None
+        }
+    }
+}
+
+/// Returns whether a limited scan can stop once the limit has been reached. This is only the case
+/// when the output is unordered, since otherwise, any file could be among those displayed, and when
+/// neither all matched files are summarised, nor are they being sampled from. Likewise, empty
+/// directories can only be found once the whole tree has been searched, a snapshot must contain,
+/// or be compared to, every matched file, and a minimum size percentage is relative to the largest
+/// of them.
+fn can_stop_at_limit(args: &LffArgs) -> bool {
+    output_sort_method(args).is_none()
+        && !args.summary_all
+        && args.sample.is_none()
+        && !args.prune_empty_dirs
+        && !args.dedupe_output
+        && args.save_snapshot.is_none()
+        && args.compare_to.is_none()
+        && args.min_size_percent.is_none()
+}
+
+/// Extract files and their details from the supplied `ReadDir` in parallel, applying the
+/// appropriate command-line arguments, and returning a `Vec` of created `LffFile`s in success
+/// cases. Subdirectories are handled recursively, meaning the traversal is depth-first.
+///
+/// # Errors
+///
+/// - If there is an issue handling a directory entry in [handle_dir_entry].
+pub fn handle_directory(
+    directory: ReadDir,
+    args: &LffArgs,
+    context: &LffScanContext,
+) -> Result<Vec<LffFile>> {
+    // It seems odd at first glance that we would be using a two-dimensional Vec here, but this is
+    // due to limitations in the rayon parallelism library with respect to flattening.
+    // Fundamentally, this is due to error handling - rayon does not let us collect Results with a
+    // single-dimensional Vec.
+    let dir_state: LffDirState = LffDirState::default();
+    let two_d_files: Result<Vec<Vec<LffFile>>> = directory
+        .into_iter()
+        // Split and handle each directory entry in parallel.
+        .par_bridge()
+        // Rayon doesn't play nice with flat_map() and then collecting with Results, so we just use
+        // map() and flatten after.
+        .map(|entry_result| {
+            // If a limit argument was supplied, the output is unordered, and enough files have been
+            // found across the whole scan - rather than enough entries handled in this directory,
+            // which could include any number of directories and filtered out files - exit early.
+            if context.reached_limit(args) {
+                // We just return empty vectors when no files are returned - these will be
+                // flattened out later.
+                return Ok(vec![]);
+            }
+            let (mut files, subdirectory) =
+                handle_dir_entry(entry_result, args, context, &dir_state)?;
+            if subdirectory.is_none() {
+                context.mark_occupied();
+            }
+            if let Some((dir_path, dir_context)) = subdirectory {
+                if let Some(dir) = open_directory(&dir_path, &dir_context) {
+                    // This actually returns a Vec with 0 or more files, which will be flattened out
+                    // later.
+                    files.extend(handle_directory(dir, args, &dir_context)?);
+                }
+            }
+            Ok(files)
+        })
+        .collect();
+    // Now we can flatten out our two-dimensional file Vec - if an error occurred during the
+    // processing of the directory, the first to occur will be returned.
+    let mut flat_files: Vec<LffFile> = two_d_files?.into_iter().flatten().collect();
+    // Only now that every entry has been handled do we know whether the directory is crowded.
+    if let Some(dir_file) = dir_state.crowded_directory(args, context)? {
+        flat_files.extend(context.emit(vec![dir_file])?);
+    }
+    Ok(flat_files)
+}
+
+/// Extract files and their details from the supplied `ReadDir` in the same way as
+/// [handle_directory], but traversing breadth-first rather than depth-first.
+///
+/// Each level of the directory tree is handled in parallel before moving on to the next, which
+/// guarantees that all files at a shallower depth appear in the returned `Vec` before any files at
+/// a deeper depth. The order of files within the same depth is not guaranteed. If a limit argument
+/// was supplied and the output is unordered, traversal stops once a level has taken the number of
+/// found files to the limit, meaning shallower files are preferred.
+///
+/// # Errors
+///
+/// - If there is an issue handling a directory entry in [handle_dir_entry].
+pub fn handle_directory_breadth_first(
+    directory: ReadDir,
+    args: &LffArgs,
+    context: &LffScanContext,
+) -> Result<Vec<LffFile>> {
+    let mut flat_files: Vec<LffFile> = Vec::new();
+    // Only the start directory is already open - the rest are opened as their level is handled, so
+    // that a wide level doesn't exhaust the limit on open files.
+    let mut level: Vec<(Option<ReadDir>, PathBuf, LffScanContext)> =
+        vec![(Some(directory), PathBuf::new(), context.clone())];
+    while !level.is_empty() {
+        // Each directory needs its own state, shared between its entries, which is kept until the
+        // level has been handled so that we know whether the directory is crowded.
+        let dir_states: Vec<(LffScanContext, Arc<LffDirState>)> = level
+            .iter()
+            .map(|(_, _, dir_context)| (dir_context.clone(), Arc::default()))
+            .collect();
+        // Handle every entry in every directory at this level in parallel, keeping the
+        // subdirectories to form the next level.
+        let level_results: Result<Vec<LffEntryResult>> = level
+            .into_par_iter()
+            .zip(dir_states.par_iter())
+            .flat_map_iter(|((opened_dir, dir_path, dir_context), (_, dir_state))| {
+                let dir: Option<ReadDir> =
+                    opened_dir.or_else(|| open_directory(&dir_path, &dir_context));
+                dir.into_iter()
+                    .flatten()
+                    .map(move |entry_result| (entry_result, dir_context.clone(), dir_state.clone()))
+            })
+            .map(|(entry_result, dir_context, dir_state)| {
+                let entry_result: LffEntryResult =
+                    handle_dir_entry(entry_result, args, &dir_context, &dir_state)?;
+                if entry_result.1.is_none() {
+                    dir_context.mark_occupied();
+                }
+                Ok(entry_result)
+            })
+            .collect();
+        level = Vec::new();
+        for (files, subdirectory) in level_results? {
+            flat_files.extend(files);
+            level.extend(subdirectory.map(|(dir_path, dir_context)| (None, dir_path, dir_context)));
+        }
+        for (dir_context, dir_state) in &dir_states {
+            if let Some(dir_file) = dir_state.crowded_directory(args, dir_context)? {
+                flat_files.extend(dir_context.emit(vec![dir_file])?);
+            }
+        }
+        if context.reached_limit(args) {
+            break;
+        }
+    }
+    Ok(flat_files)
+}
+
+/// Parses the supplied regular expression, so that it is only compiled once rather than for every
+/// file.
+///
+/// # Errors
+///
+/// - If the supplied regular expression is invalid.
+fn parse_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .wrap_err_with(|| eyre!("Invalid regex from regex pattern flag: '{pattern}'"))
+}
+
+/// Parses the supplied human-readable size, e.g. `500K`, `1.5GiB` or `10MB`, into a number of
+/// bytes. Units are case-insensitive - single-letter units and those containing an 'i' are powers
+/// of 1024, other units are powers of 1000, and sizes without a unit are in bytes.
+///
+/// # Errors
+///
+/// - If the supplied size has an invalid number or unit, or is too large.
+fn parse_human_size(size: &str) -> Result<u64> {
+    let trimmed: &str = size.trim();
+    let unit_start: usize = trimmed
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, unit): (&str, &str) = trimmed.split_at(unit_start);
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(eyre!("Invalid unit in size: '{size}'")),
+    };
+    // Whole numbers are parsed as such, so that large byte counts don't lose precision.
+    if let Ok(whole) = number.trim().parse::<u64>() {
+        return whole
+            .checked_mul(multiplier)
+            .ok_or_else(|| eyre!("Size is too large: '{size}'"));
+    }
+    match number.trim().parse::<f64>() {
+        Ok(fractional) if fractional.is_finite() && fractional >= 0.0 => {
+            let bytes: f64 = (fractional * multiplier as f64).round();
+            match bytes <= u64::MAX as f64 {
+                true => Ok(bytes as u64),
+                false => Err(eyre!("Size is too large: '{size}'")),
+            }
+        }
+        _ => Err(eyre!("Invalid number in size: '{size}'")),
+    }
+}
+
+/// Parses the supplied human-readable duration, e.g. `30s`, `5m`, `500ms` or `6mo`. Units are
+/// case-insensitive - `ms`, `s`, `m`, `h`, `d`, `w`, `mo` and `y` are supported, where months are
+/// 30 days and years are 365 days, and durations without a unit are in seconds.
+///
+/// # Errors
+///
+/// - If the supplied duration has an invalid number or unit, or is too long.
+fn parse_duration(duration: &str) -> Result<Duration> {
+    let trimmed: &str = duration.trim();
+    let unit_start: usize = trimmed
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, unit): (&str, &str) = trimmed.split_at(unit_start);
+    let unit_secs: f64 = match unit.to_ascii_lowercase().as_str() {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        "w" => 604800.0,
+        "mo" => 2592000.0,
+        "y" => 31536000.0,
+        _ => return Err(eyre!("Invalid unit in duration: '{duration}'")),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs >= 0.0 => {
+            Duration::try_from_secs_f64(secs * unit_secs)
+                .map_err(|_| eyre!("Duration is too long: '{duration}'"))
+        }
+        _ => Err(eyre!("Invalid number in duration: '{duration}'")),
+    }
+}
+
+/// Parses the supplied percentage, ensuring that it is between 0 and 100, inclusive.
+///
+/// # Errors
+///
+/// - If the supplied percentage is not a number, or is out of range.
+fn parse_percentage(percentage: &str) -> Result<f64> {
+    match percentage.parse::<f64>() {
+        Ok(parsed) if (0.0..=100.0).contains(&parsed) => Ok(parsed),
+        _ => Err(eyre!(
+            "Percentage must be a number from 0 to 100: '{percentage}'"
+        )),
+    }
+}
+
+/// Parses the supplied comma-separated extension thresholds, e.g. `mp4=500M,txt=1M`, into a map
+/// from each extension, without any leading dot, to its minimum size in bytes.
+///
+/// # Errors
+///
+/// - If a threshold isn't in the form ext=size, or its size is invalid in [parse_human_size].
+fn parse_ext_thresholds(thresholds: &str) -> Result<BTreeMap<OsString, u64>> {
+    thresholds
+        .split(',')
+        .map(|threshold| match threshold.split_once('=') {
+            Some((ext, size)) if !ext.trim_start_matches('.').is_empty() => Ok((
+                OsString::from(ext.trim_start_matches('.')),
+                parse_human_size(size)?,
+            )),
+            _ => Err(eyre!(
+                "Extension threshold must be in the form ext=size: '{threshold}'"
+            )),
+        })
+        .collect()
+}
+
+/// Parses the supplied command to execute, ensuring that it contains a program to run.
+///
+/// # Errors
+///
+/// - If the supplied command is empty or only whitespace.
+fn parse_command(command: &str) -> Result<String> {
+    match command.trim().is_empty() {
+        true => Err(eyre!("Command to execute must not be empty")),
+        false => Ok(command.to_string()),
+    }
+}
+
+/// Parses the supplied column separator, replacing any `\t` escape sequences with tab characters,
+/// since these are awkward to pass on the command line otherwise.
+///
+/// # Errors
+///
+/// - Never, but clap requires parsers to return a `Result`.
+fn parse_separator(separator: &str) -> std::result::Result<String, Infallible> {
+    Ok(separator.replace("\\t", "\t"))
+}
+
+/// Formats the supplied size as a percentage of the supplied total, to one decimal place. If the
+/// total is zero, i.e. all of the files are empty, the percentage is displayed as zero rather than
+/// being undefined.
+fn format_percent(size: u64, total_size: u64) -> String {
+    match total_size {
+        0 => String::from("0.0%"),
+        _ => format!("{:.1}%", size as f64 / total_size as f64 * 100.0),
+    }
+}
+
+/// Formats the supplied file as a single line of output, padding the file size so that all of the
+/// file names are horizontally aligned. File sizes are left-aligned unless the right align flag is
+/// passed, in which case they are aligned on their least significant digit. Directories are
+/// displayed with a trailing separator to distinguish them from files. If the percent flag is
+/// passed, the file's share of the supplied total size is displayed between the size and the name,
+/// right-aligned so that the decimal points line up. Likewise, if the show inode flag is passed,
+/// the file's inode number is displayed before any percentage, right-aligned to ten digits, and if
+/// the age flag is passed, the time since the file was modified is displayed after any inode
+/// number. If the show depth flag is passed, the file's depth is displayed before everything else.
+///
+/// If a custom column separator was supplied, no padding is applied, so that the output can be
+/// reliably split on the separator.
+fn format_file_line(
+    file: &LffFile,
+    longest_size_rep: usize,
+    total_size: u64,
+    args: &LffArgs,
+) -> String {
+    let mut display_name: OsString = file.name.clone();
+    if file.is_dir {
+        display_name.push(MAIN_SEPARATOR_STR);
+    }
+    let depth: Option<String> = args.show_depth.then(|| Column::Depth.cell(file));
+    let inode: Option<String> = args.show_inode.then(|| Column::Inode.cell(file));
+    let age: Option<String> = args.age.then(|| Column::Age.cell(file));
+    let percent: Option<String> = args.percent.then(|| format_percent(file.size, total_size));
+    if let Some(separator) = &args.separator {
+        let mut columns: Vec<String> = depth.into_iter().collect();
+        columns.push(colour_size(file.formatted_size.clone(), file.size, args));
+        columns.extend(inode);
+        columns.extend(age);
+        columns.extend(percent);
+        columns.push(format!("{:?}", display_name));
+        return columns.join(separator);
+    }
+    // Three digits covers any realistic nesting of directories.
+    let mut columns: Vec<String> = depth.map(|dep| format!("{:>3}", dep)).into_iter().collect();
+    // The padding is coloured along with the size, so that the escape sequences don't affect it.
+    let padded_size: String = match args.right_align {
+        true => format!("{:>width$}", file.formatted_size, width = longest_size_rep),
+        false => format!("{:<width$}", file.formatted_size, width = longest_size_rep),
+    };
+    columns.push(colour_size(padded_size, file.size, args));
+    // Ten digits covers the inode numbers of most file systems, and longer ones just push the
+    // remaining columns along.
+    columns.extend(inode.map(|ino| format!("{:>10}", ino)));
+    // The widest relative time is 'just now', which is eight characters.
+    columns.extend(age.map(|age| format!("{:>8}", age)));
+    // The widest percentage is 100.0%, which is six characters.
+    columns.extend(percent.map(|pct| format!("{:>6}", pct)));
+    columns.push(format!("{:?}", display_name));
+    columns.join("  ")
+}
+
+/// Wraps the supplied size column in the colour for the tier that the supplied size in bytes falls
+/// into, if size tiers are being coloured. Automatic colouring must already have been resolved.
+fn colour_size(column: String, size: u64, args: &LffArgs) -> String {
+    if !args.color_tiers || !matches!(args.color, ColorChoice::Always) {
+        return column;
+    }
+    let colour: &str = if size >= args.color_tier_red {
+        ANSI_RED
+    } else if size >= args.color_tier_yellow {
+        ANSI_YELLOW
+    } else {
+        ANSI_GREEN
+    };
+    format!("{colour}{column}{ANSI_RESET}")
+}
+
+/// Prints the supplied files to the supplied printer in groups by extension, with each group
+/// preceded by a header line containing the extension, the number of files, and their subtotal
+/// size. Groups are printed in descending order of subtotal, and files without an extension form
+/// their own group.
+fn print_extension_groups(
+    files: &[LffFile],
+    longest_size_rep: usize,
+    total_size: u64,
+    args: &LffArgs,
+    printer: &mut dyn LffPrinter,
+) {
+    // Since we're iterating over the already-sorted files, each group retains the same ordering.
+    let mut groups: BTreeMap<Option<OsString>, Vec<&LffFile>> = BTreeMap::new();
+    for file in files {
+        groups
+            .entry(comparable_extension(file, args.extension_case_fold))
+            .or_default()
+            .push(file);
+    }
+    // A hardlink is only counted in the first group it appears in, so subtotals sum to the total.
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut subtotalled_groups: Vec<(Option<OsString>, u64, Vec<&LffFile>)> = groups
+        .into_iter()
+        .map(|(ext, group)| (ext, totaller.total(group.iter().copied()), group))
+        .collect();
+    // The sort is stable, so groups with equal subtotals remain in extension order.
+    subtotalled_groups.sort_by_key(|(_, subtotal, _)| Reverse(*subtotal));
+
+    for (ext, subtotal, group) in subtotalled_groups {
+        let ext_rep: String = match ext {
+            Some(file_ext) => format!("{:?}", file_ext),
+            None => String::from("(no extension)"),
+        };
+        printer.println(format!(
+            "{} - {} file(s), {} total",
+            ext_rep,
+            group.len(),
+            format_size(subtotal, args)
+        ));
+        for file in group {
+            printer.println(format_file_line(file, longest_size_rep, total_size, args));
+        }
+    }
+}
+
+/// Prints a one-sentence summary of the supplied files, containing the number of files, their total
+/// size, and the largest file, which is referred to by its file name alone for readability. Files
+/// of equal size are ordered by path, so that the largest is deterministic.
+fn print_human_summary(files: &[LffFile], args: &LffArgs, printer: &mut dyn LffPrinter) {
+    let total: u64 = LffSizeTotaller::new(args.dedup_hardlinks).total(files);
+    let Some(largest) = files
+        .iter()
+        .max_by(|a, b| a.size.cmp(&b.size).then_with(|| b.name.cmp(&a.name)))
+    else {
+        return;
+    };
+    let largest_name: &OsStr = Path::new(&largest.name)
+        .file_name()
+        .unwrap_or(&largest.name);
+    printer.println(format!(
+        "Found {} file(s) totalling {}; the largest is {} at {}.",
+        files.len(),
+        format_size(total, args),
+        largest_name.to_string_lossy(),
+        largest.formatted_size
+    ));
+}
+
+/// Prints Prometheus text-format metrics for the supplied files - their number and total size, and
+/// the same per extension - each preceded by its HELP and TYPE lines. Extensions are in sorted
+/// order, with files without one labelled with an empty extension.
+fn print_prometheus_metrics(files: &[LffFile], args: &LffArgs, printer: &mut dyn LffPrinter) {
+    let mut print_metric = |name: &str, help: &str, samples: Vec<(String, u64)>| {
+        printer.println(format!("# HELP {name} {help}"));
+        printer.println(format!("# TYPE {name} gauge"));
+        for (labels, value) in samples {
+            printer.println(format!("{name}{labels} {value}"));
+        }
+    };
+    let mut groups: BTreeMap<Option<OsString>, Vec<&LffFile>> = BTreeMap::new();
+    for file in files {
+        groups
+            .entry(comparable_extension(file, args.extension_case_fold))
+            .or_default()
+            .push(file);
+    }
+    // As with extension groups, a hardlink is only counted in the first extension it appears in.
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut count_samples: Vec<(String, u64)> = Vec::new();
+    let mut size_samples: Vec<(String, u64)> = Vec::new();
+    for (ext, group) in groups {
+        let ext_rep: String = ext.map_or_else(String::new, |ext| {
+            ext.to_string_lossy()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        });
+        let labels: String = format!("{{extension=\"{ext_rep}\"}}");
+        count_samples.push((labels.clone(), group.len() as u64));
+        size_samples.push((labels, totaller.total(group.iter().copied())));
+    }
+
+    print_metric(
+        "lff_matched_files",
+        "The number of files found.",
+        vec![(String::new(), files.len() as u64)],
+    );
+    print_metric(
+        "lff_matched_bytes_total",
+        "The total size of the files found, in bytes.",
+        vec![(
+            String::new(),
+            LffSizeTotaller::new(args.dedup_hardlinks).total(files),
+        )],
+    );
+    print_metric(
+        "lff_extension_files",
+        "The number of files found with each extension.",
+        count_samples,
+    );
+    print_metric(
+        "lff_extension_bytes",
+        "The total size of the files found with each extension, in bytes.",
+        size_samples,
+    );
+}
+
+/// Formats the supplied time as a UTC timestamp, e.g. `2024-05-01 13:45:00`. Times before the Unix
+/// epoch are displayed as the epoch itself, since file modification times shouldn't precede it.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs: u64 = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (days, day_secs): (u64, u64) = (secs / 86400, secs % 86400);
+    // This converts days since the epoch to a civil date - see Howard Hinnant's date algorithms.
+    let shifted_days: u64 = days + 719468;
+    let era: u64 = shifted_days / 146097;
+    let day_of_era: u64 = shifted_days - era * 146097;
+    let year_of_era: u64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: u64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month: u64 = (5 * day_of_year + 2) / 153;
+    let day: u64 = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month: u64 = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year: u64 = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60
+    )
+}
+
+/// Returns the fields to display for the supplied file in the delimited formats, in a stable order:
+/// the size in bytes, the formatted size, the extension (empty if there is none), whether the file
+/// is hidden, and its name. Names and extensions that aren't valid Unicode are converted lossily.
+fn delimited_fields(file: &LffFile) -> [String; 5] {
+    [
+        file.size.to_string(),
+        file.formatted_size.clone(),
+        file.extension
+            .as_ref()
+            .map_or_else(String::new, |ext| ext.to_string_lossy().into_owned()),
+        file.hidden.to_string(),
+        file.name.to_string_lossy().into_owned(),
+    ]
+}
+
+/// Escapes any tabs and line breaks in the supplied field, so that each TSV record stays on a
+/// single line with the expected number of fields. Backslashes are escaped first, so that escaped
+/// characters can't be confused with a backslash followed by e.g. a 't'.
+fn escape_tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Formats the supplied duration as a short relative time, in the largest whole unit that fits it,
+/// e.g. '5m', '2h', '3d', or '1y'. Durations under a minute are displayed as 'just now'. Years are
+/// counted as 365 days.
+fn format_relative_time(duration: Duration) -> String {
+    let secs: u64 = duration.as_secs();
+    match secs {
+        0..60 => String::from("just now"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        86400..31536000 => format!("{}d", secs / 86400),
+        _ => format!("{}y", secs / 31536000),
+    }
+}
+
+/// Prints the supplied files as a table with the supplied columns, preceded by a header row and a
+/// separator line if the header flag is passed. Every column except the last is padded to the
+/// width of its widest value, so that the columns are aligned.
+fn print_table(rows: &[LffFile], columns: &[Column], header: bool, printer: &mut dyn LffPrinter) {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|file| columns.iter().map(|column| column.cell(file)).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            // Without a header, the columns only need to be as wide as their values.
+            let header_width: usize = if header { column.header().len() } else { 0 };
+            cells
+                .iter()
+                .map(|row| row[idx].len())
+                .fold(header_width, usize::max)
+        })
+        .collect();
+    let format_row = |values: Vec<String>| -> String {
+        let padded: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect();
+        // Trailing padding on the last column is just noise, so we trim it.
+        padded.join("  ").trim_end().to_string()
+    };
+
+    if header {
+        printer.println(format_row(
+            columns
+                .iter()
+                .map(|column| column.header().to_string())
+                .collect(),
+        ));
+        printer.println(format_row(
+            widths.iter().map(|width| "-".repeat(*width)).collect(),
+        ));
+    }
+    for row in cells {
+        printer.println(format_row(row));
+    }
+}
+
+/// Prints the supplied files as a GitHub-flavoured Markdown table with the supplied columns, with a
+/// header row and a delimiter row. Pipe characters in values are escaped, so that they aren't
+/// mistaken for the boundaries between cells.
+fn print_markdown_table(rows: &[LffFile], columns: &[Column], printer: &mut dyn LffPrinter) {
+    let format_row = |values: Vec<String>| -> String { format!("| {} |", values.join(" | ")) };
+    printer.println(format_row(
+        columns
+            .iter()
+            .map(|column| column.header().to_string())
+            .collect(),
+    ));
+    printer.println(format_row(vec![String::from("---"); columns.len()]));
+    for file in rows {
+        printer.println(format_row(
+            columns
+                .iter()
+                .map(|column| column.cell(file).replace('|', "\\|"))
+                .collect(),
+        ));
+    }
+}
+
+/// Prints the supplied files in the text format with the supplied columns, rather than the default
+/// ones. The columns are aligned as in the table format, but without a header, unless a custom
+/// column separator was supplied, in which case they are just joined by it.
+fn print_columns(
+    rows: &[LffFile],
+    columns: &[Column],
+    args: &LffArgs,
+    printer: &mut dyn LffPrinter,
+) {
+    match &args.separator {
+        Some(separator) => {
+            for file in rows {
+                let cells: Vec<String> = columns.iter().map(|column| column.cell(file)).collect();
+                printer.println(cells.join(separator));
+            }
+        }
+        None => print_table(rows, columns, false, printer),
+    }
+}
+
+/// Returns the label to display for the supplied tree node - the file or directory name, with its
+/// formatted size in brackets if it has one, i.e. for files, and directories with totals.
+fn tree_node_label(name: &OsString, node: &LffTreeNode) -> String {
+    match &node.formatted_size {
+        Some(size) => format!("{} ({})", name.to_string_lossy(), size),
+        None => name.to_string_lossy().into_owned(),
+    }
+}
+
+/// Recursively prints the children of the supplied tree node, prefixing each line with the
+/// connectors for its ancestors, followed by its own connector.
+fn print_tree_children(node: &LffTreeNode, prefix: &str, printer: &mut dyn LffPrinter) {
+    let mut children = node.children.iter().peekable();
+    while let Some((name, child)) = children.next() {
+        // The last child of each node gets a corner connector, and no vertical line continues
+        // beneath it for its own children.
+        let is_last: bool = children.peek().is_none();
+        let (connector, continuation) = match is_last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        printer.println(format!(
+            "{}{}{}",
+            prefix,
+            connector,
+            tree_node_label(name, child)
+        ));
+        print_tree_children(child, &format!("{}{}", prefix, continuation), printer);
+    }
+}
+
+/// Reconstructs the directory hierarchy containing the supplied files by splitting each file's path
+/// into its components, so only directories containing at least one of the files are included.
+/// Each file's size is added to the total of every node along its path.
+fn build_tree(files: &[LffFile], args: &LffArgs) -> LffTreeNode {
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut root: LffTreeNode = LffTreeNode::default();
+    for file in files {
+        let size: u64 = totaller.counted_size(file);
+        let mut node: &mut LffTreeNode = &mut root;
+        for component in Path::new(&file.name).components() {
+            node = node
+                .children
+                .entry(component.as_os_str().to_os_string())
+                .or_default();
+            node.total += size;
+        }
+        node.formatted_size = Some(file.formatted_size.clone());
+    }
+    root
+}
+
+/// Merges each directory below the supplied tree node that contains only a single directory with
+/// that directory, recursively, so that e.g. a chain of directories a, b and c is displayed as a
+/// single a/b/c node.
+fn collapse_tree(node: &mut LffTreeNode) {
+    for (mut name, mut child) in std::mem::take(&mut node.children) {
+        collapse_tree(&mut child);
+        // The child's own chains have already been collapsed, so at most one merge is needed.
+        if child.children.len() == 1
+            && child
+                .children
+                .values()
+                .all(|grandchild| !grandchild.children.is_empty())
+        {
+            let (grandchild_name, grandchild) = child.children.pop_first().unwrap();
+            name = Path::new(&name).join(grandchild_name).into_os_string();
+            child = grandchild;
+        }
+        node.children.insert(name, child);
+    }
+}
+
+/// Sets the formatted size of each directory below the supplied tree node to its formatted total,
+/// recursively, so that directory totals are displayed alongside file sizes.
+fn label_directory_totals(node: &mut LffTreeNode, args: &LffArgs) {
+    for child in node.children.values_mut() {
+        if !child.children.is_empty() {
+            child.formatted_size = Some(format_size(child.total, args));
+            label_directory_totals(child, args);
+        }
+    }
+}
+
+/// Prints the supplied files to the supplied printer as an indented directory hierarchy, like the
+/// `tree` command, reconstructed in [build_tree]. With directory totals, each directory is
+/// displayed with the total size of the files within it, and chains of single directories are
+/// collapsed.
+fn print_tree(
+    files: &[LffFile],
+    directory_totals: bool,
+    args: &LffArgs,
+    printer: &mut dyn LffPrinter,
+) {
+    let mut root: LffTreeNode = build_tree(files, args);
+    if directory_totals {
+        collapse_tree(&mut root);
+        label_directory_totals(&mut root, args);
+    }
+    // The top-level components are displayed without connectors, as the roots of the tree.
+    for (name, node) in &root.children {
+        printer.println(tree_node_label(name, node));
+        print_tree_children(node, "", printer);
+    }
+}
+
+/// Expands the supplied directory argument into a path, replacing a leading `~` with the user's
+/// home directory, and any `$VAR` or `${VAR}` references, as well as `%VAR%` references on Windows,
+/// with the values of the environment variables they name. A literal `$` can be supplied as `$$`.
+/// This allows directories to be supplied in contexts where the shell doesn't expand them, e.g.
+/// when quoted.
+///
+/// # Errors
+///
+/// - If the home directory cannot be determined from the environment.
+/// - If a referenced environment variable is not set, or is not valid Unicode.
+fn expand_path(input: &str) -> Result<PathBuf> {
+    // The regex is compiled only once, since every start directory is expanded.
+    static VAR_REGEX: OnceLock<Regex> = OnceLock::new();
+    let var_regex: &Regex = VAR_REGEX.get_or_init(|| {
+        let pattern: &str = if cfg!(windows) {
+            r"\$\$|\$\{(\w+)\}|\$(\w+)|%(\w+)%"
+        } else {
+            r"\$\$|\$\{(\w+)\}|\$(\w+)"
+        };
+        Regex::new(pattern).unwrap()
+    });
+    let mut expanded: String = String::with_capacity(input.len());
+    let mut last_end: usize = 0;
+    for captures in var_regex.captures_iter(input) {
+        // At most one of the variable name groups participates in each match, and none do for an
+        // escaped dollar sign.
+        let whole_match: Match = captures.get(0).unwrap();
+        let var_value: String = match captures.get(1).or(captures.get(2)).or(captures.get(3)) {
+            Some(var_name) => var(var_name.as_str()).wrap_err_with(|| {
+                format!(
+                    "Could not expand environment variable '{}' in directory '{input}'",
+                    var_name.as_str()
+                )
+            })?,
+            None => String::from("$"),
+        };
+        expanded.push_str(&input[last_end..whole_match.start()]);
+        expanded.push_str(&var_value);
+        last_end = whole_match.end();
+    }
+    expanded.push_str(&input[last_end..]);
+
+    // Only a leading tilde on its own, or followed by a separator, refers to the home directory -
+    // we don't support the ~user form.
+    let home_relative: Option<&str> = match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', MAIN_SEPARATOR]) => Some(rest),
+        _ => None,
+    };
+    match home_relative {
+        Some(rest) => {
+            let home_var: &str = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+            let home: String = var(home_var)
+                .wrap_err_with(|| format!("Could not determine home directory for '{input}'"))?;
+            Ok(PathBuf::from(home + rest))
+        }
+        None => Ok(PathBuf::from(expanded)),
+    }
+}
+
+/// Resolves the supplied start directory argument into the concrete directories to scan, after
+/// expanding it in [expand_path]. If the argument contains glob metacharacters, e.g.
+/// `projects/*/build`, it is expanded against the file system into every matching directory.
+/// Otherwise, or if it names an existing directory, e.g. `photos [2020]`, the expanded path is
+/// returned as is.
+///
+/// # Errors
+///
+/// - If the start directory cannot be expanded in [expand_path].
+/// - If the start directory contains an invalid glob pattern.
+/// - If the start directory's glob pattern doesn't match any directories.
+fn resolve_start_directories(start_directory: &str) -> Result<Vec<PathBuf>> {
+    let expanded: PathBuf = expand_path(start_directory)?;
+    // Paths without glob metacharacters take the fast path, with no file system access, as do
+    // existing directories that happen to contain them.
+    let pattern: &str = match expanded.to_str() {
+        Some(pattern) if pattern.contains(['*', '?', '[']) && !expanded.is_dir() => pattern,
+        _ => return Ok(vec![expanded]),
+    };
+    let matched_dirs: Vec<PathBuf> = glob(pattern)
+        .wrap_err_with(|| format!("Invalid glob in supplied start directory: '{start_directory}'"))?
+        // Just ignore paths we can't read, in the same way as unreadable subdirectories.
+        .filter_map(|path_result| path_result.ok())
+        .filter(|path| path.is_dir())
+        .collect();
+    if matched_dirs.is_empty() {
+        return Err(eyre!(
+            "No directories match the supplied start directory: '{start_directory}'"
+        ));
+    }
+    Ok(matched_dirs)
+}
+
+/// Scan the supplied start directory, returning a `Vec` of the `LffFile`s found within it.
+///
+/// # Errors
+///
+/// - If the supplied start directory does not exist.
+/// - If the scan is restricted to a single file system, and the device cannot be determined for
+///   the start directory in [device_id].
+/// - If there is an issue handling the directory in [handle_directory].
+pub fn scan_start_directory(
+    start_directory: &Path,
+    args: &LffArgs,
+    context: &LffScanContext,
+) -> Result<Vec<LffFile>> {
+    let directory: ReadDir = read_dir(start_directory).wrap_err_with(|| {
+        format!(
+            "Invalid supplied start directory: '{}'",
+            start_directory.display()
+        )
+    })?;
+    // When looking for empty directories, every directory searched is recorded, so that the empty
+    // ones can be found once the whole tree has been searched.
+    let searched_dirs: Option<Mutex<Vec<Arc<LffOccupancy>>>> =
+        args.prune_empty_dirs.then(Mutex::default);
+    let context: LffScanContext = LffScanContext {
+        start_device: match args.one_file_system {
+            true => Some(device_id(start_directory)?),
+            false => None,
+        },
+        ignore_rules: match args.respect_ignore_files {
+            true => read_ignore_file(start_directory, context)?,
+            false => None,
+        },
+        occupancy: searched_dirs
+            .as_ref()
+            .map(|searched| LffOccupancy::track(start_directory, 0, None, searched)),
+        searched_dirs: searched_dirs.as_ref(),
+        ..context.clone()
+    };
+    context.diagnose(Level::Debug, || {
+        format!("Entering directory {start_directory:?}")
+    });
+    if let Some(stats) = context.stats {
+        stats.record_dir();
+    }
+    let mut files: Vec<LffFile> = match args.breadth_first {
+        true => handle_directory_breadth_first(directory, args, &context)?,
+        false => handle_directory(directory, args, &context)?,
+    };
+    if let Some(searched) = &searched_dirs {
+        let empty_dirs: Vec<LffFile> = searched
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|dir| dir.is_outermost_empty())
+            .map(|dir| {
+                Ok(LffFile {
+                    depth: dir.depth,
+                    ..handle_entry(dir.path.clone(), args, None)?
+                })
+            })
+            .collect::<Result<Vec<LffFile>>>()?;
+        files.extend(context.emit(empty_dirs)?);
+    }
+    Ok(files)
+}
+
+/// Scan all of the supplied start directories in parallel, returning the merged `Vec` of the
+/// `LffFile`s found within them, in the order the directories were supplied.
+///
+/// Scanning in parallel means that idle threads can be put to work on the other directories when
+/// one directory tree is much larger than the others. When the output is unordered, the limit is
+/// global across all of the directories, since they share the count of files found, so the scan
+/// stops once that many files have been found in any of them. Which files are returned is therefore
+/// not deterministic. Otherwise, every directory is scanned in full, and the overall limit is
+/// applied once all results are merged, so the results are the same as if the directories were
+/// scanned sequentially.
+///
+/// # Errors
+///
+/// - If any of the start directories cannot be resolved in [resolve_start_directories].
+/// - If there is an issue scanning any of the start directories in [scan_start_directory].
+pub fn scan_start_directories(args: &LffArgs, context: &LffScanContext) -> Result<Vec<LffFile>> {
+    let two_d_dirs: Result<Vec<Vec<PathBuf>>> = args
+        .directories
+        .iter()
+        .map(|start_directory| resolve_start_directories(start_directory))
+        .collect();
+    let start_dirs: Vec<PathBuf> = two_d_dirs?.into_iter().flatten().collect();
+    let two_d_files: Result<Vec<Vec<LffFile>>> = start_dirs
+        .par_iter()
+        .map(|start_directory| scan_start_directory(start_directory, args, context))
+        .collect();
+    Ok(two_d_files?.into_iter().flatten().collect())
+}
+
+/// Converts the supplied bytes read from standard input into a `PathBuf`. On Unix, paths are just
+/// sequences of bytes, so any bytes are accepted.
+///
+/// # Errors
+///
+/// - If the bytes are not valid UTF-8 on non-Unix platforms.
+fn bytes_to_path(bytes: Vec<u8>) -> Result<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(PathBuf::from(OsString::from_vec(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(bytes)
+            .map(PathBuf::from)
+            .wrap_err("Paths read from standard input must be valid UTF-8")
+    }
+}
+
+/// Reads paths from the supplied reader, separated by newlines or, if `null_separated` is true, NUL
+/// characters. Empty paths are skipped.
+///
+/// # Errors
+///
+/// - If there is an issue reading from the supplied reader.
+/// - If a read path is invalid in [bytes_to_path].
+fn read_input_paths(reader: &mut dyn BufRead, null_separated: bool) -> Result<Vec<PathBuf>> {
+    let separator: u8 = if null_separated { b'\0' } else { b'\n' };
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for path_bytes in reader.split(separator) {
+        let path_bytes: Vec<u8> = path_bytes.wrap_err("Could not read paths from input")?;
+        if !path_bytes.is_empty() {
+            paths.push(bytes_to_path(path_bytes)?);
+        }
+    }
+    Ok(paths)
+}
+
+/// Reads the paths to search from standard input, as per [read_input_paths].
+///
+/// # Errors
+///
+/// - If there is an issue reading the paths in [read_input_paths].
+#[cfg(not(tarpaulin_include))]
+fn read_stdin_paths(null_separated: bool) -> Result<Vec<PathBuf>> {
+    read_input_paths(&mut stdin().lock(), null_separated)
+}
+
+/// Reads the start directories listed in the file at the supplied path, one per line, skipping
+/// blank lines and comments starting with #. Surrounding whitespace is trimmed from each line.
+///
+/// # Errors
+///
+/// - If the file cannot be read, or is not valid UTF-8.
+fn read_paths_file(paths_file: &Path) -> Result<Vec<String>> {
+    let contents: String = read_to_string(paths_file)
+        .wrap_err_with(|| format!("Could not read paths from file {paths_file:?}"))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Scan all of the supplied paths in parallel, returning the merged `Vec` of the `LffFile`s found.
+/// Directories are scanned recursively as if they were start directories, unless the stdin filter
+/// flag is passed, in which case they're ignored. Files are checked directly against the filters.
+/// Once the scan is cancelled, the remaining paths are skipped.
+///
+/// # Errors
+///
+/// - If there is an issue scanning a directory in [scan_start_directory].
+/// - If there is an issue handling a file in [handle_entry] or [find_rejection].
+fn scan_input_paths(
+    paths: &[PathBuf],
+    args: &LffArgs,
+    context: &LffScanContext,
+) -> Result<Vec<LffFile>> {
+    let two_d_files: Result<Vec<Vec<LffFile>>> = paths
+        .par_iter()
+        .map(|path| {
+            if context.is_cancelled() {
+                return Ok(vec![]);
+            }
+            if path.is_dir() {
+                if args.stdin_filter {
+                    context.diagnose(Level::Debug, || format!("Ignoring directory {path:?}"));
+                    return Ok(vec![]);
+                }
+                return scan_start_directory(path, args, context);
+            }
+            let file: LffFile = handle_entry(path.clone(), args, None)?;
+            let rejection: Option<LffRejection> = find_rejection(&file, args, context)?;
+            if let Some(stats) = context.stats {
+                stats.record_file(rejection);
+            }
+            context.diagnose(Level::Trace, || match rejection {
+                Some(reason) => format!("{:?} - dropped: {}", file.name, reason),
+                None => format!("{:?} - kept", file.name),
+            });
+            match rejection {
+                None => Ok(vec![file]),
+                Some(_) => Ok(vec![]),
+            }
+        })
+        .collect();
+    Ok(two_d_files?.into_iter().flatten().collect())
+}
+
+/// Returns the sort method to order found files by before they are displayed. If no sort method was
+/// supplied, files are ordered by path, so that the output is the same between runs despite the
+/// files being found in parallel - unless the unordered flag was passed, in which case files are
+/// left in the order they were found.
+fn output_sort_method(args: &LffArgs) -> Option<SortMethod> {
+    match (&args.sort_method, args.unordered) {
+        (Some(sort_method), _) => Some(sort_method.clone()),
+        (None, false) => Some(SortMethod::Path),
+        (None, true) => None,
+    }
+}
+
+/// Returns whether found files can be output as soon as they're found, rather than once the scan
+/// is complete. This is only the case for line-based formats when the files are output in the
+/// order they're found, and nothing else needs every file to be found before any are output.
+fn can_stream_output(args: &LffArgs) -> bool {
+    matches!(args.format, OutputFormat::Ndjson | OutputFormat::JsonStream)
+        && output_sort_method(args).is_none()
+        && !args.reverse
+        && !args.total_only
+        && args.largest_dir.is_none()
+        && args.compare_to.is_none()
+        && args.sample.is_none()
+        && !args.dedupe_output
+        && args.absolute_from.is_none()
+        && args.min_size_percent.is_none()
+        && !args.auto_units
+}
+
+/// Scans the supplied input paths in [scan_input_paths] if there are any, and otherwise the start
+/// directories in [scan_start_directories], returning the found files.
+///
+/// # Errors
+///
+/// - If there is an issue scanning the input paths or the start directories.
+fn scan_found_files(
+    args: &LffArgs,
+    input_paths: Option<&[PathBuf]>,
+    context: &LffScanContext,
+) -> Result<Vec<LffFile>> {
+    match input_paths {
+        Some(paths) => scan_input_paths(paths, args, context),
+        None => scan_start_directories(args, context),
+    }
+}
+
+/// Scans in the same way as [scan_found_files], but on another thread, passing each file to the
+/// supplied callback on this thread as soon as it is found, up to the supplied limit. Every found
+/// file is still returned, in the order it was found, so those passed to the callback come first.
+///
+/// # Errors
+///
+/// - If there is an issue scanning in [scan_found_files].
+/// - If the supplied callback returns an error, in which case the scan is stopped.
+fn scan_found_files_streaming(
+    args: &LffArgs,
+    input_paths: Option<&[PathBuf]>,
+    context: &LffScanContext,
+    limit: usize,
+    mut on_found: impl FnMut(&LffFile) -> Result<()>,
+) -> Result<Vec<LffFile>> {
+    let (sender, receiver): (SyncSender<Result<LffFile>>, Receiver<Result<LffFile>>) =
+        sync_channel(SCAN_ITER_BUFFER_SIZE);
+    scope(|scope| {
+        let scanner: ScopedJoinHandle<Result<Vec<LffFile>>> = scope.spawn(move || {
+            let sink_context: LffScanContext = LffScanContext {
+                sink: Some(&sender),
+                ..context.clone()
+            };
+            scan_found_files(args, input_paths, &sink_context)
+        });
+        let mut files: Vec<LffFile> = Vec::new();
+        // If the callback fails, the receiver is dropped on return, which stops the scan.
+        for file_result in receiver {
+            let file: LffFile = file_result?;
+            if files.len() < limit {
+                on_found(&file)?;
+            }
+            files.push(file);
+        }
+        // Files that weren't found while traversing a directory, e.g. those supplied directly, are
+        // returned rather than sent, so they're passed to the callback once the scan is complete.
+        for file in scanner.join().unwrap()? {
+            if files.len() < limit {
+                on_found(&file)?;
+            }
+            files.push(file);
+        }
+        Ok(files)
+    })
+}
+
+/// Returns the extension of the supplied file as it should be grouped and sorted by, which is
+/// lowercased when extensions are being case-folded.
+fn comparable_extension(file: &LffFile, extension_case_fold: bool) -> Option<OsString> {
+    match &file.extension {
+        Some(ext) if extension_case_fold => Some(ext.to_ascii_lowercase()),
+        ext => ext.clone(),
+    }
+}
+
+/// Sorts the supplied files using the supplied sort method. Files that are equal by the sort method
+/// are further ordered - by size and then by path, or just by path when sorting by extension - so
+/// that the order is deterministic despite the files being found in parallel. Random orders are
+/// derived from the supplied seed.
+fn sort_files(
+    files: &mut [LffFile],
+    sort_method: &SortMethod,
+    extension_case_fold: bool,
+    seed: u64,
+) {
+    match sort_method {
+        SortMethod::Size => {
+            files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        }
+        SortMethod::Name => files.sort_by(|a, b| {
+            Path::new(&a.name)
+                .file_name()
+                .cmp(&Path::new(&b.name).file_name())
+                .then_with(|| b.size.cmp(&a.size))
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        // Paths are unique, so there are no ties to break.
+        SortMethod::Path => files.sort_by(|a, b| a.name.cmp(&b.name)),
+        // Sorting on whether the extension is missing first puts those files last.
+        SortMethod::Extension => files.sort_by(|a, b| {
+            a.extension
+                .is_none()
+                .cmp(&b.extension.is_none())
+                .then_with(|| {
+                    comparable_extension(a, extension_case_fold)
+                        .cmp(&comparable_extension(b, extension_case_fold))
+                })
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        // Including the path in the key means that colliding hashes are still ordered consistently.
+        SortMethod::Random => {
+            files.sort_by_cached_key(|file| (seeded_hash(seed, &file.name), file.name.clone()));
+        }
+    }
+}
+
+/// Prints the total size of the supplied files at each depth of the directory tree, with files
+/// directly within a start directory at depth 0. Depths are displayed in ascending order.
+fn print_depth_summary(files: &[LffFile], args: &LffArgs, printer: &mut dyn LffPrinter) {
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut depth_totals: BTreeMap<usize, (usize, u64)> = BTreeMap::new();
+    for file in files {
+        let (count, total): &mut (usize, u64) = depth_totals.entry(file.depth).or_default();
+        *count += 1;
+        *total += totaller.counted_size(file);
+    }
+    for (depth, (count, total)) in depth_totals {
+        printer.println(format!(
+            "Depth {} - {} file(s), {} total",
+            depth,
+            count,
+            format_size(total, args)
+        ));
+    }
+}
+
+/// Prints the largest `count` directories containing the supplied files, in descending order of
+/// the combined size of the files within them, recursively. Each file's size counts towards every
+/// directory between it and its start directory, with directories of equal size ordered by path.
+fn print_largest_dirs(
+    files: &[LffFile],
+    count: usize,
+    args: &LffArgs,
+    printer: &mut dyn LffPrinter,
+) {
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut dir_totals: BTreeMap<&Path, u64> = BTreeMap::new();
+    for file in files {
+        let size: u64 = totaller.counted_size(file);
+        for dir in Path::new(&file.name).ancestors().skip(1).take(file.depth) {
+            *dir_totals.entry(dir).or_default() += size;
+        }
+    }
+    let mut largest_dirs: Vec<(&Path, u64)> = dir_totals.into_iter().collect();
+    largest_dirs.sort_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    for (dir, total) in largest_dirs.into_iter().take(count) {
+        printer.println(format!(
+            "{} - {}",
+            format_size(total, args),
+            dir.to_string_lossy()
+        ));
+    }
+}
+
+/// Reads the snapshot at the supplied path, returning the size of each file within it by path.
+///
+/// # Errors
+///
+/// - If the snapshot cannot be read, or isn't a snapshot or JSON array of files.
+/// - If the snapshot was saved by a newer version of `lff`, with an unsupported snapshot version.
+fn read_snapshot(snapshot_path: &Path) -> Result<BTreeMap<String, u64>> {
+    let contents: String = read_to_string(snapshot_path)
+        .wrap_err_with(|| format!("Could not read snapshot {snapshot_path:?}"))?;
+    let snapshot: LffSnapshotContents =
+        from_str(&contents).wrap_err_with(|| format!("Invalid snapshot {snapshot_path:?}"))?;
+    let files: Vec<LffSnapshotFile> = match snapshot {
+        LffSnapshotContents::Versioned { version, .. } if version > SNAPSHOT_VERSION => {
+            return Err(eyre!(
+                "Snapshot {snapshot_path:?} has version {version}, but only versions up to \
+                {SNAPSHOT_VERSION} are supported"
+            ));
+        }
+        LffSnapshotContents::Versioned { files, .. } => files,
+        LffSnapshotContents::Unversioned(files) => files,
+    };
+    Ok(files
+        .into_iter()
+        .map(|file| (file.name, file.size))
+        .collect())
+}
+
+/// Saves the supplied files to the supplied path as a versioned JSON snapshot.
+///
+/// # Errors
+///
+/// - If the files cannot be serialised, or the snapshot cannot be written.
+fn save_snapshot(snapshot_path: &Path, files: &[LffFile]) -> Result<()> {
+    let snapshot: LffSnapshot = LffSnapshot {
+        version: SNAPSHOT_VERSION,
+        files,
+    };
+    write(snapshot_path, to_string(&snapshot)?)
+        .wrap_err_with(|| format!("Could not write snapshot {snapshot_path:?}"))
+}
+
+/// Returns the differences between the supplied previous and current sizes of files by path, in
+/// order of path. Files with the same size in both are unchanged, so are omitted.
+fn diff_snapshots(
+    previous: &BTreeMap<String, u64>,
+    current: &BTreeMap<String, u64>,
+) -> Vec<LffSnapshotChange> {
+    let names: BTreeSet<&String> = previous.keys().chain(current.keys()).collect();
+    names
+        .into_iter()
+        .map(|name| LffSnapshotChange {
+            name: name.clone(),
+            previous_size: previous.get(name).copied(),
+            current_size: current.get(name).copied(),
+        })
+        .filter(|change| change.previous_size != change.current_size)
+        .collect()
+}
+
+/// Prints the differences between the supplied snapshot and files, one per line, with the marker
+/// for the kind of change, the signed change in size, and the path.
+fn print_snapshot_diff(
+    snapshot: &BTreeMap<String, u64>,
+    files: &[LffFile],
+    args: &LffArgs,
+    printer: &mut dyn LffPrinter,
+) {
+    let current: BTreeMap<String, u64> = files
+        .iter()
+        .map(|file| (file.name.to_string_lossy().into_owned(), file.size))
+        .collect();
+    for change in diff_snapshots(snapshot, &current) {
+        let delta: i128 = change.delta();
+        let sign: char = if delta < 0 { '-' } else { '+' };
+        printer.println(format!(
+            "{} {}{}  {:?}",
+            change.marker(),
+            sign,
+            format_size(delta.unsigned_abs() as u64, args),
+            change.name
+        ));
+    }
+}
+
+/// Prints a histogram of the supplied files, grouping them into power-of-two size ranges, e.g. 512
+/// to 1023 bytes, with empty files in their own range. Each range is displayed in ascending order
+/// with a bar proportional to its number of files, the longest being [HISTOGRAM_BAR_WIDTH] wide,
+/// followed by the number of files and their total size. Ranges without any files are omitted.
+fn print_histogram(files: &[LffFile], args: &LffArgs, printer: &mut dyn LffPrinter) {
+    let mut totaller: LffSizeTotaller = LffSizeTotaller::new(args.dedup_hardlinks);
+    let mut buckets: BTreeMap<Option<u32>, (usize, u64)> = BTreeMap::new();
+    for file in files {
+        // Empty files have no logarithm, so they sort before every other range.
+        let (count, total): &mut (usize, u64) =
+            buckets.entry(file.size.checked_ilog2()).or_default();
+        *count += 1;
+        *total += totaller.counted_size(file);
+    }
+    let labels: Vec<String> = buckets
+        .keys()
+        .map(|exponent| match exponent {
+            Some(exp) => format!(
+                "{} - {}",
+                format_size(1 << exp, args),
+                format_size(((1u128 << (exp + 1)) - 1) as u64, args)
+            ),
+            None => format_size(0, args),
+        })
+        .collect();
+    let longest_label: usize = labels.iter().map(String::len).max().unwrap_or(0);
+    let largest_count: usize = buckets.values().map(|(count, _)| *count).max().unwrap_or(0);
+    for (label, (count, total)) in labels.iter().zip(buckets.values()) {
+        // Every range contains at least one file, so it has at least one character of bar.
+        let bar_width: usize = (count * HISTOGRAM_BAR_WIDTH / largest_count).max(1);
+        printer.println(format!(
+            "{:<label_width$}  {:<bar_width$}  {} file(s), {} total",
+            label,
+            "#".repeat(bar_width),
+            count,
+            format_size(*total, args),
+            label_width = longest_label,
+            bar_width = HISTOGRAM_BAR_WIDTH
+        ));
+    }
+}
+
+/// Prints a warning listing the supplied directories that could not be read during the scan, if
+/// there were any, as diagnostics.
+fn print_unreadable_dirs(unreadable: Vec<PathBuf>, printer: &mut dyn LffPrinter) {
+    if unreadable.is_empty() {
+        return;
+    }
+    printer.eprintln(format!(
+        "Warning: {} director(ies) could not be read, so results may be incomplete:",
+        unreadable.len()
+    ));
+    for dir in unreadable {
+        printer.eprintln(format!("  {:?}", dir));
+    }
+}
+
+/// Prints the number of broken symlinks found during the scan, followed by each of their paths.
+fn print_broken_symlinks(broken_symlinks: Vec<PathBuf>, printer: &mut dyn LffPrinter) {
+    printer.println(format!("Broken symlinks: {}", broken_symlinks.len()));
+    for link in broken_symlinks {
+        printer.println(format!("  {:?}", link));
+    }
+}
+
+/// Prints the supplied scan statistics and elapsed time to the supplied printer as diagnostics.
+fn print_stats(stats: &LffStats, elapsed: Duration, printer: &mut dyn LffPrinter) {
+    let stat_lines: [(&str, &AtomicUsize); 10] = [
+        ("Files examined", &stats.files_examined),
+        ("Directories traversed", &stats.dirs_traversed),
+        ("Files skipped (too small)", &stats.too_small),
+        ("Files skipped (too large)", &stats.too_large),
+        ("Files skipped (wrong extension)", &stats.wrong_extension),
+        ("Files skipped (name pattern)", &stats.wrong_name),
+        ("Files skipped (hidden)", &stats.hidden),
+        ("Files skipped (not hidden)", &stats.not_hidden),
+        ("Files skipped (too old)", &stats.too_old),
+        ("Files skipped (too new)", &stats.too_new),
+    ];
+    for (label, counter) in stat_lines {
+        printer.eprintln(format!("{}: {}", label, counter.load(Ordering::Relaxed)));
+    }
+    printer.eprintln(format!("Elapsed time: {:?}", elapsed));
+}
+
+/// An iterator over the files found by a scan running in the background, as returned by
+/// [LffScanner::scan_iter]. Once the `remaining` number of files have been yielded, or the iterator
+/// is dropped, the scan is cancelled and the `receiver` is dropped, so that the scan doesn't carry
+/// on in the background.
+struct LffScanIter {
+    receiver: Option<Receiver<Result<LffFile>>>,
+    remaining: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Iterator for LffScanIter {
+    type Item = Result<LffFile>;
+
+    /// Returns the next found file, waiting for the scan to find it, or `None` once the scan is
+    /// complete or enough files have been yielded.
+    fn next(&mut self) -> Option<Self::Item> {
+        let file_result: Result<LffFile> = self.receiver.as_ref()?.recv().ok()?;
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.stop();
+        }
+        Some(file_result)
+    }
+}
+
+impl LffScanIter {
+    /// Cancels the scan, and drops the receiver so that a scan blocked on sending a file stops.
+    fn stop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.receiver = None;
+    }
+}
+
+impl Drop for LffScanIter {
+    /// Stops the scan, since no one is left to receive its files.
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A builder for programmatic scans, so that library users can configure scans in code without
+/// constructing the command-line oriented [LffArgs]. Unlike on the command line, there is no
+/// minimum size by default, so all files are found unless filters are added.
+///
+/// ```no_run
+/// use lff::{LffFile, LffScanner, SortMethod};
+///
+/// let files: Vec<LffFile> = LffScanner::new("logs")
+///     .min_size(1024 * 1024)
+///     .extension("log")
+///     .sort_method(SortMethod::Size)
+///     .scan()?;
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Default)]
+pub struct LffScanner {
+    directories: Vec<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    extension: Option<OsString>,
+    name_pattern: Option<String>,
+    regex_pattern: Option<String>,
+    exclude_hidden: bool,
+    sort_method: Option<SortMethod>,
+    unordered: bool,
+    limit: Option<usize>,
+}
+
+impl LffScanner {
+    /// Creates a scanner for the supplied start directory.
+    pub fn new(directory: impl Into<String>) -> Self {
+        LffScanner::default().directory(directory)
+    }
+
+    /// Adds another start directory to scan.
+    pub fn directory(mut self, directory: impl Into<String>) -> Self {
+        self.directories.push(directory.into());
+        self
+    }
+
+    /// Only finds files of at least this size in bytes.
+    pub fn min_size(mut self, min_bytes: u64) -> Self {
+        self.min_size = Some(min_bytes);
+        self
+    }
+
+    /// Only finds files of at most this size in bytes.
+    pub fn max_size(mut self, max_bytes: u64) -> Self {
+        self.max_size = Some(max_bytes);
+        self
+    }
+
+    /// Only finds files with this extension, without the leading dot.
+    pub fn extension(mut self, extension: impl Into<OsString>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Only finds files matching this glob pattern.
+    pub fn name_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.name_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Only finds files matching this regular expression.
+    pub fn regex_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.regex_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Excludes hidden files and directories.
+    pub fn exclude_hidden(mut self) -> Self {
+        self.exclude_hidden = true;
+        self
+    }
+
+    /// Returns found files in the order they're found when no sort method is supplied, rather than
+    /// ordering them by path. This is faster, but the order may differ between scans.
+    pub fn unordered(mut self) -> Self {
+        self.unordered = true;
+        self
+    }
+
+    /// Sorts found files using this sort method.
+    pub fn sort_method(mut self, sort_method: SortMethod) -> Self {
+        self.sort_method = Some(sort_method);
+        self
+    }
+
+    /// Returns a maximum of this many files.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Converts the configured scan into the equivalent arguments.
+    ///
+    /// # Errors
+    ///
+    /// - If the minimum size is greater than the maximum size.
+    /// - If both a glob pattern and a regular expression were supplied.
+    /// - If the supplied regular expression is invalid in [parse_regex].
+    fn to_args(&self) -> Result<LffArgs> {
+        if let Some((min, max)) = self.min_size.zip(self.max_size) {
+            if min > max {
+                return Err(eyre!(
+                    "The minimum size of {min} bytes is greater than the maximum size of \
+                    {max} bytes"
+                ));
+            }
+        }
+        if self.name_pattern.is_some() && self.regex_pattern.is_some() {
+            return Err(eyre!(
+                "Only one of a name pattern and a regex pattern can be supplied"
+            ));
+        }
+        Ok(LffArgs {
+            directories: self.directories.clone(),
+            extension: self.extension.clone(),
+            exclude_hidden: self.exclude_hidden,
+            limit: self.limit,
+            max_size_bytes: self.max_size,
+            min_size_bytes: self.min_size,
+            min_size_mib: 0.0,
+            name_pattern: self.name_pattern.clone(),
+            regex_pattern: self.regex_pattern.as_deref().map(parse_regex).transpose()?,
+            sort_method: self.sort_method.clone(),
+            unordered: self.unordered,
+            ..LffArgs::default()
+        })
+    }
+
+    /// Runs the configured scan in the background, returning an iterator over the files as they are
+    /// found. This avoids holding every found file in memory at once, and allows the caller to stop
+    /// the scan early by dropping the iterator. The scan pauses if the caller falls behind, until
+    /// more files are consumed, and stops as soon as the limit is reached.
+    ///
+    /// Since the scan is parallel, files are yielded in no particular order, and a sort method
+    /// cannot be supplied. The iterator is `Send`, so it can be consumed on any thread. If the scan
+    /// fails partway through, the error is yielded as the final item.
+    ///
+    /// # Errors
+    ///
+    /// - If the configured options conflict, or are invalid.
+    /// - If a sort method was supplied.
+    pub fn scan_iter(&self) -> Result<impl Iterator<Item = Result<LffFile>>> {
+        let args: LffArgs = LffArgs {
+            // Files are yielded as they're found, so the scan can stop as soon as the limit is
+            // reached.
+            unordered: true,
+            ..self.to_args()?
+        };
+        if args.sort_method.is_some() {
+            return Err(eyre!(
+                "Files cannot be sorted when scanning lazily, so use scan() instead"
+            ));
+        }
+        let limit: usize = args.limit.unwrap_or(usize::MAX);
+        let (sender, receiver): (SyncSender<Result<LffFile>>, Receiver<Result<LffFile>>) =
+            sync_channel(SCAN_ITER_BUFFER_SIZE);
+        let cancelled: Arc<AtomicBool> = Arc::new(AtomicBool::new(limit == 0));
+        let scan_cancelled: Arc<AtomicBool> = cancelled.clone();
+        spawn(move || {
+            let context: LffScanContext = LffScanContext {
+                sink: Some(&sender),
+                cancelled: Some(&scan_cancelled),
+                ..LffScanContext::default()
+            };
+            if let Err(err) = scan_start_directories(&args, &context) {
+                // If the receiver has been dropped, there's no one left to report the error to.
+                let _ = sender.send(Err(err));
+            }
+        });
+        Ok(LffScanIter {
+            receiver: (limit > 0).then_some(receiver),
+            remaining: limit,
+            cancelled,
+        })
+    }
+
+    /// Runs the configured scan, returning the found files.
+    ///
+    /// # Errors
+    ///
+    /// - If the configured options conflict, or are invalid.
+    /// - If there is an issue scanning the start directories in [scan_start_directories].
+    pub fn scan(&self) -> Result<Vec<LffFile>> {
+        let args: LffArgs = self.to_args()?;
+        let mut files: Vec<LffFile> = scan_start_directories(&args, &LffScanContext::default())?;
+        if let Some(sort_method) = &output_sort_method(&args) {
+            sort_files(
+                &mut files,
+                sort_method,
+                args.extension_case_fold,
+                seed_or_random(args.seed),
+            );
+        }
+        if let Some(lim) = args.limit {
+            files.truncate(lim);
+        }
+        Ok(files)
+    }
+}
+
+/// Installs a handler for Ctrl-C that cancels any running scan, so that the files found so far are
+/// displayed rather than nothing at all. Pressing Ctrl-C a second time exits immediately, in case
+/// the scan is slow to wind down.
+///
+/// # Errors
+///
+/// - If a handler cannot be installed, e.g. because one already has been.
+#[cfg(not(tarpaulin_include))]
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            exit(INTERRUPTED_EXIT_CODE);
+        }
+    })
+    .wrap_err("Could not install the Ctrl-C handler")
+}
+
+/// Reads the modification time of the supplied reference file, for filtering files by age.
+///
+/// # Errors
+/// - If there is an issue reading the reference file's metadata, or its modification time is not
+///   available on this platform.
+fn reference_time(reference_file: &Path) -> Result<SystemTime> {
+    reference_file
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .wrap_err_with(|| {
+            format!("Could not read modification time of reference file {reference_file:?}")
+        })
+}
+
+/// Parses the boot time from the supplied contents of `/proc/stat`, where it is given in seconds
+/// since the Unix epoch on the `btime` line.
+///
+/// # Errors
+///
+/// - If there is no valid `btime` line.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_boot_time(proc_stat: &str) -> Result<SystemTime> {
+    proc_stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        .ok_or_else(|| eyre!("Could not find the boot time in /proc/stat"))
+}
+
+/// Returns the time at which the system last booted, as recorded by the kernel in `/proc/stat`.
+///
+/// # Errors
+///
+/// - If `/proc/stat` cannot be read, or does not contain the boot time.
+#[cfg(target_os = "linux")]
+fn boot_time() -> Result<SystemTime> {
+    parse_boot_time(&read_to_string("/proc/stat").wrap_err("Could not read /proc/stat")?)
+}
+
+/// The boot time is only read from `/proc/stat` on Linux, so filtering by it is unsupported
+/// elsewhere.
+///
+/// # Errors
+///
+/// - Always, since this is unsupported.
+#[cfg(not(target_os = "linux"))]
+fn boot_time() -> Result<SystemTime> {
+    Err(eyre!(
+        "The since boot flag is not supported on this platform"
+    ))
+}
+
+/// Returns the minimum size in bytes for displayed files with the min size auto flag, given the
+/// supplied free space in bytes - a fraction of the free space, so that files are only displayed if
+/// deleting them would make a meaningful difference, but never less than a mebibyte.
+fn auto_min_size(free_bytes: u64) -> u64 {
+    (free_bytes / MIN_SIZE_AUTO_DIVISOR).max(MEBIBYTE)
+}
+
+/// Returns the space in bytes available to unprivileged users on the file system containing the
+/// supplied path.
+///
+/// # Errors
+///
+/// - If the file system's statistics cannot be read.
+#[cfg(unix)]
+fn free_space(path: &Path) -> Result<u64> {
+    use nix::sys::statvfs::{statvfs, Statvfs};
+
+    let stats: Statvfs = statvfs(path)
+        .wrap_err_with(|| format!("Could not read the free space for directory {path:?}"))?;
+    Ok(stats.blocks_available() as u64 * stats.fragment_size() as u64)
+}
+
+/// Free space is only read with `statvfs` on Unix, so setting the minimum size from it is
+/// unsupported elsewhere.
+///
+/// # Errors
+///
+/// - Always, since this is unsupported.
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Result<u64> {
+    Err(eyre!(
+        "The min size auto flag is not supported on this platform"
+    ))
+}
+
+/// Returns the minimum size in bytes that files must be to be displayed, from the minimum size in
+/// bytes if supplied, or the minimum size in MiB otherwise.
+fn effective_min_size(args: &LffArgs) -> u64 {
+    args.min_size_bytes
+        .unwrap_or_else(|| (args.min_size_mib * MEBIBYTE as f64).ceil() as u64)
+}
+
+/// Returns a description of how the supplied arguments will be interpreted, one line per aspect of
+/// the scan, with sizes in bytes and the supplied modification time window as timestamps.
+fn explain_args(
+    args: &LffArgs,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+) -> Vec<String> {
+    let join_or = |values: Vec<String>, fallback: &str| match values.is_empty() {
+        true => String::from(fallback),
+        false => values.join(", "),
+    };
+    let directories: String = match args.stdin {
+        true => String::from("read from standard input"),
+        false => join_or(args.directories.clone(), "none"),
+    };
+    let mut lines: Vec<String> = vec![
+        format!("Directories: {}", directories),
+        format!(
+            "Subdirectories: {}",
+            if args.no_recurse {
+                "skipped"
+            } else {
+                "searched"
+            }
+        ),
+        format!("Minimum size: {} bytes", effective_min_size(args)),
+    ];
+    if let Some(thresholds) = &args.ext_thresholds {
+        let ext_thresholds: Vec<String> = thresholds
+            .iter()
+            .map(|(ext, size)| format!("{} = {} bytes", ext.to_string_lossy(), size))
+            .collect();
+        lines.push(format!(
+            "Minimum sizes by extension: {}",
+            ext_thresholds.join(", ")
+        ));
+    }
+    lines.push(format!(
+        "Maximum size: {}",
+        args.max_size_bytes
+            .or(args.exclude_larger_than)
+            .map_or(String::from("none"), |max| format!("{max} bytes"))
+    ));
+    lines.push(format!(
+        "Extension: {}",
+        args.extension
+            .as_ref()
+            .map_or(String::from("any"), |ext| ext
+                .to_string_lossy()
+                .into_owned())
+    ));
+    let excluded_exts: Vec<String> = args
+        .exclude_extensions
+        .iter()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .collect();
+    lines.push(format!(
+        "Excluded extensions: {}",
+        join_or(excluded_exts, "none")
+    ));
+    let case: &str = match args.ignore_case && !args.case_sensitive {
+        true => ", ignoring case",
+        false => "",
+    };
+    let target: &str = match args.match_full_path {
+        true => " against full paths",
+        false => "",
+    };
+    lines.push(match (&args.name_pattern, &args.regex_pattern) {
+        (Some(pattern), _) => format!("Name: matching glob '{pattern}'{target}{case}"),
+        (None, Some(regex)) => format!("Name: matching regex '{regex}'"),
+        (None, None) => String::from("Name: any"),
+    });
+    lines.push(format!(
+        "Hidden files: {}",
+        match (args.exclude_hidden, args.hidden_only) {
+            (true, _) => "excluded",
+            (false, true) => "only",
+            (false, false) => "included",
+        }
+    ));
+    lines.push(format!(
+        "Empty files: {}",
+        if args.exclude_empty {
+            "excluded"
+        } else {
+            "included"
+        }
+    ));
+    if let Some(after) = modified_after {
+        lines.push(format!("Modified after: {} UTC", format_timestamp(after)));
+    }
+    if let Some(before) = modified_before {
+        lines.push(format!("Modified before: {} UTC", format_timestamp(before)));
+    }
+    let sort: String = match output_sort_method(args) {
+        // None of the sort methods are skipped, so they all have possible values.
+        Some(sort_method) => sort_method
+            .to_possible_value()
+            .unwrap()
+            .get_name()
+            .to_string(),
+        None => String::from("none"),
+    };
+    lines.push(format!(
+        "Sort: {}{}",
+        sort,
+        if args.reverse { ", reversed" } else { "" }
+    ));
+    lines.push(format!(
+        "Limit: {}",
+        args.limit
+            .map_or(String::from("none"), |limit| format!("{limit} files"))
+    ));
+    lines
+}
+
+/// Run `lff` with the supplied arguments, returning the number and combined size of the files
+/// found. If the scan is interrupted by Ctrl-C once the handler from [install_interrupt_handler]
+/// has been installed, the files found so far are displayed, along with a warning that they are
+/// partial.
+///
+/// # Errors
+///
+/// - If the maximum results bytes flag is supplied without sorting by size.
+/// - If the modification time of either supplied reference file cannot be read.
+/// - If the boot time cannot be determined in [boot_time].
+/// - If the free space of the start directories cannot be determined in [free_space].
+/// - If the older-than reference file is not newer than the start of the newer-than window.
+/// - If there is an issue reading paths from standard input in [read_stdin_paths].
+/// - If there is an issue reading the paths file in [read_paths_file].
+/// - If there is an issue scanning the paths from standard input in [scan_input_paths].
+/// - If there is an issue scanning the start directories in [scan_start_directories].
+/// - If files are to be moved without confirmation or a dry run.
+/// - If the found files cannot be serialised for the JSON formats.
+/// - If the number of found files is outside the supplied count bounds.
+/// - If there is an issue running any actions in [LffActionLog::run].
+/// - If the snapshot cannot be saved in [save_snapshot], or read in [read_snapshot].
+pub fn run_finder(args: LffArgs, printer: &mut dyn LffPrinter) -> Result<LffScanOutcome> {
+    // An interruption of a previous run shouldn't cancel this one.
+    INTERRUPTED.store(false, Ordering::Relaxed);
+    if args.watch {
+        return run_finder_watching(args, printer, &INTERRUPTED);
+    }
+    run_finder_cancellable(args, printer, &INTERRUPTED)
+}
+
+/// Run `lff` in the same way as [run_finder_cancellable], and then again each time the start
+/// directories change, clearing the screen first. Returns the outcome of the last scan once the
+/// supplied flag is set.
+///
+/// # Errors
+///
+/// - If the start directories cannot be resolved in [resolve_start_directories], or watched.
+/// - See [run_finder].
+#[cfg(not(tarpaulin_include))]
+fn run_finder_watching(
+    mut args: LffArgs,
+    printer: &mut dyn LffPrinter,
+    cancelled: &AtomicBool,
+) -> Result<LffScanOutcome> {
+    // The listed directories are read once up front, so that they are watched too.
+    if let Some(paths_file) = args.paths_from_file.take() {
+        args.directories.extend(read_paths_file(&paths_file)?);
+    }
+    let (sender, receiver) = channel();
+    let mut watcher: RecommendedWatcher =
+        notify::recommended_watcher(sender).wrap_err("Could not create the file system watcher")?;
+    for start_directory in &args.directories {
+        for directory in resolve_start_directories(start_directory)? {
+            watcher
+                .watch(&directory, RecursiveMode::Recursive)
+                .wrap_err_with(|| format!("Could not watch directory {directory:?}"))?;
+        }
+    }
+    loop {
+        let outcome: LffScanOutcome = run_finder_cancellable(args.clone(), printer, cancelled)?;
+        if !wait_for_changes(&receiver, WATCH_DEBOUNCE, cancelled) {
+            return Ok(outcome);
+        }
+        printer.println(String::from(ANSI_CLEAR_SCREEN));
+    }
+}
+
+/// Waits for the supplied receiver to receive a change event, and then for the supplied debounce
+/// period to pass without any more, returning `true`. Access events are ignored, since scanning
+/// the watched directories triggers them. Returns `false` if the supplied flag is set, or the
+/// watcher stops sending events, before that happens.
+fn wait_for_changes(
+    receiver: &Receiver<notify::Result<Event>>,
+    debounce: Duration,
+    cancelled: &AtomicBool,
+) -> bool {
+    let mut changed: bool = false;
+    while !cancelled.load(Ordering::Relaxed) {
+        let timeout: Duration = if changed {
+            debounce
+        } else {
+            WATCH_POLL_INTERVAL
+        };
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => changed = true,
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => debug!("Ignoring file system watcher error: {err}"),
+            Err(RecvTimeoutError::Timeout) if changed => return true,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+    false
+}
+
+/// Run `lff` in the same way as [run_finder], stopping the scan early if the supplied flag is set,
+/// or the maximum time passes. Since the results are then partial, the count bounds are not checked
+/// and no actions are run.
+///
+/// # Errors
+///
+/// - See [run_finder].
+fn run_finder_cancellable(
+    mut args: LffArgs,
+    printer: &mut dyn LffPrinter,
+    cancelled: &AtomicBool,
+) -> Result<LffScanOutcome> {
+    if let Some(paths_file) = args.paths_from_file.take() {
+        args.directories.extend(read_paths_file(&paths_file)?);
+    }
+    args.extension = args.extension.map(strip_extension_dot);
+    args.exclude_extensions = args
+        .exclude_extensions
+        .into_iter()
+        .map(strip_extension_dot)
+        .collect();
+    // Moving files is destructive, so we make sure the user really means it before scanning.
+    if args.move_to.is_some() && !args.dry_run && !args.yes {
+        return Err(eyre!(
+            "Moving files requires confirmation with --yes, or use --dry-run to preview"
+        ));
+    }
+    // The budget is filled from the largest files down, which is only meaningful in size order.
+    if args.max_results_bytes.is_some() && !matches!(args.sort_method, Some(SortMethod::Size)) {
+        return Err(eyre!(
+            "The maximum results bytes flag requires sorting by size, with --sort-method size"
+        ));
+    }
+    #[cfg(not(unix))]
+    if args.show_inode {
+        return Err(eyre!(
+            "The show inode flag is not supported on this platform"
+        ));
+    }
+    // Paths are made relative to the base by stripping it from their absolute paths, so the base
+    // needs to be absolute too.
+    let canonical_base: Option<PathBuf> = match &args.absolute_from {
+        Some(base) => Some(canonicalize(base).wrap_err_with(|| {
+            format!(
+                "Could not generate absolute path for base directory {:?}",
+                base
+            )
+        })?),
+        None => None,
+    };
+    if canonical_base.is_some() {
+        args.absolute = true;
+    }
+    if args.min_size_auto {
+        let mut least_free: u64 = u64::MAX;
+        for start_directory in &args.directories {
+            for directory in resolve_start_directories(start_directory)? {
+                least_free = least_free.min(free_space(&directory)?);
+            }
+        }
+        let min_size: u64 = auto_min_size(least_free);
+        debug!("Using a minimum size of {min_size} byte(s), from {least_free} byte(s) free");
+        args.min_size_bytes = Some(min_size);
+    }
+    if args.color_tiers && args.color_tier_yellow > args.color_tier_red {
+        return Err(eyre!(
+            "The yellow colour tier can't be larger than the red colour tier"
+        ));
+    }
+    // Whether to colour is decided once up front, rather than for every line.
+    if let ColorChoice::Auto = args.color {
+        args.color = match stdout().is_terminal() && var_os("NO_COLOR").is_none() {
+            true => ColorChoice::Always,
+            false => ColorChoice::Never,
+        };
+    }
+    let start_time: Instant = Instant::now();
+    let stats: Option<LffStats> = args.stats.then(LffStats::default);
+    // The reference files' modification times are only read once, rather than for every file, and
+    // ages are relative to when the scan started.
+    let now: SystemTime = SystemTime::now();
+    let age_cutoff = |age: Duration| now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+    let modified_after: Option<SystemTime> = match (args.since_boot, args.max_age) {
+        (true, _) => Some(boot_time()?),
+        (false, Some(max_age)) => Some(age_cutoff(max_age)),
+        (false, None) => args.newer_than.as_deref().map(reference_time).transpose()?,
+    };
+    let modified_before: Option<SystemTime> = match args.min_age {
+        Some(min_age) => Some(age_cutoff(min_age)),
+        None => args.older_than.as_deref().map(reference_time).transpose()?,
+    };
+    if let (Some(after), Some(before)) = (modified_after, modified_before) {
+        if before <= after {
+            let Some(older_than) = &args.older_than else {
+                return Err(match (&args.newer_than, args.max_age) {
+                    (_, Some(_)) => eyre!(
+                        "The --min-age must be less than the --max-age, so no files could match"
+                    ),
+                    (Some(newer_than), None) => eyre!(
+                        "The --min-age cutoff is before the --newer-than reference file {:?} was \
+                        last modified, so no files could match",
+                        newer_than
+                    ),
+                    (None, None) => eyre!(
+                        "The --min-age cutoff is before the system booted, so no files could match"
+                    ),
+                });
+            };
+            if args.max_age.is_some() {
+                return Err(eyre!(
+                    "The --older-than reference file {:?} was last modified before the --max-age \
+                    cutoff, so no files could match",
+                    older_than
+                ));
+            }
+            return Err(match &args.newer_than {
+                Some(newer_than) => eyre!(
+                    "The --older-than reference file {:?} is not newer than the --newer-than \
+                    reference file {:?}, so no files could match",
+                    older_than,
+                    newer_than
+                ),
+                None => eyre!(
+                    "The --older-than reference file {:?} was last modified before the system \
+                    booted, so no files could match",
+                    older_than
+                ),
+            });
+        }
+    }
+    // Files with an extension threshold can still match if their threshold is within the maximum,
+    // so the smallest of all the minimum sizes is what's compared.
+    let min_size: u64 = args
+        .ext_thresholds
+        .iter()
+        .flat_map(|thresholds| thresholds.values().copied())
+        .fold(effective_min_size(&args), u64::min);
+    if let Some(max_size) = args.max_size_bytes.or(args.exclude_larger_than) {
+        if min_size > max_size {
+            return Err(eyre!(
+                "The minimum size of {min_size} bytes is larger than the maximum size of \
+                {max_size} bytes, so no files could match"
+            ));
+        }
+    }
+    if args.explain {
+        for line in explain_args(&args, modified_after, modified_before) {
+            printer.println(line);
+        }
+        return Ok(LffScanOutcome {
+            count: 0,
+            total_size: 0,
+        });
+    }
+    // The snapshot is read up front, so that a missing one is reported before a long scan.
+    let snapshot: Option<BTreeMap<String, u64>> =
+        args.compare_to.as_deref().map(read_snapshot).transpose()?;
+    let unreadable: Option<Mutex<Vec<PathBuf>>> = args.warn_unreadable.then(Mutex::default);
+    let broken_symlinks: Option<Mutex<Vec<PathBuf>>> = args.dereference_count.then(Mutex::default);
+    let deadline: Option<LffDeadline> = args
+        .max_time
+        .map(|max_time| LffDeadline::new(start_time, max_time));
+    // Diagnostics are written straight to standard error as the scan runs, since the printer can't
+    // be shared between the threads handling directories.
+    let print_diagnostic = |line: String| eprintln!("{}", line);
+    let seed: u64 = seed_or_random(args.seed);
+    let sampler: Option<LffSampler> = args.sample.map(|size| LffSampler::new(size, seed));
+    let context: LffScanContext = LffScanContext {
+        stats: stats.as_ref(),
+        modified_after,
+        modified_before,
+        unreadable: unreadable.as_ref(),
+        broken_symlinks: broken_symlinks.as_ref(),
+        diagnostics: args
+            .verbose
+            .then_some(&print_diagnostic as &LffDiagnosticSink),
+        cancelled: Some(cancelled),
+        deadline: deadline.as_ref(),
+        sampler: sampler.as_ref(),
+        ..LffScanContext::default()
+    };
+    // The start event is output before scanning, so that consumers know the scan has begun.
+    if matches!(args.format, OutputFormat::JsonStream)
+        && !args.total_only
+        && args.largest_dir.is_none()
+        && snapshot.is_none()
+    {
+        printer.println(to_string(&LffEvent::Start {
+            directories: &args.directories,
+        })?);
+    }
+    let input_paths: Option<Vec<PathBuf>> = match args.stdin {
+        true => Some(read_stdin_paths(args.null)?),
+        false => None,
+    };
+    // When files can be output as they're found, they are, and only the remainder are output once
+    // the scan is complete.
+    let mut streamed_count: usize = 0;
+    let mut files_vec: Vec<LffFile> = match can_stream_output(&args) {
+        true => scan_found_files_streaming(
+            &args,
+            input_paths.as_deref(),
+            &context,
+            args.limit.unwrap_or(usize::MAX),
+            |file| {
+                printer.println(match args.format {
+                    OutputFormat::JsonStream => to_string(&LffEvent::File(file))?,
+                    _ => to_string(file)?,
+                });
+                streamed_count += 1;
+                Ok(())
+            },
+        )?,
+        false => scan_found_files(&args, input_paths.as_deref(), &context)?,
+    };
+    if let Some(sampler) = &sampler {
+        // Files that weren't found while traversing a directory, e.g. those supplied directly, are
+        // still to be sampled.
+        sampler.offer(files_vec);
+        files_vec = sampler.take();
+    }
+    if args.dedupe_output {
+        // Paths that can't be canonicalised, e.g. those of archive entries, are compared as is.
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        files_vec.retain(|file| {
+            seen.insert(canonicalize(&file.name).unwrap_or_else(|_| PathBuf::from(&file.name)))
+        });
+    }
+    debug!(
+        "Found {} file(s) in {:?}, before sorting and limiting",
+        files_vec.len(),
+        start_time.elapsed()
+    );
+    if let Some(base) = &canonical_base {
+        for file in files_vec.iter_mut() {
+            if let Ok(relative) = Path::new(&file.name).strip_prefix(base) {
+                file.name = relative.as_os_str().to_os_string();
+            }
+        }
+    }
+    // Relative thresholds can only be applied once we know the size of the largest file.
+    if let Some(percent) = args.min_size_percent {
+        let largest_size: u64 = files_vec.iter().map(|file| file.size).max().unwrap_or(0);
+        let threshold: f64 = largest_size as f64 * percent / 100.0;
+        files_vec.retain(|file| file.size as f64 >= threshold);
+    }
+    // The unit can only be picked once every file has been found, so the files are reformatted.
+    if args.auto_units {
+        let sizes: Vec<u64> = files_vec.iter().map(|file| file.size).collect();
+        let unit: usize = auto_unit(&sizes, args.base_ten);
+        for file in files_vec.iter_mut() {
+            file.formatted_size = format_size_in_unit(file.size, unit, args.base_ten);
+        }
+    }
+
+    // We need to work out the longest file size string representation in the returned files so that
+    // we can appropriately pad the output.
+    let longest_size_rep: usize = match files_vec
+        .iter()
+        .max_by(|x, y| x.formatted_size.len().cmp(&y.formatted_size.len()))
+    {
+        Some(file) => file.formatted_size.len(),
+        None => 0,
+    };
+
+    if let Some(sort_method) = &output_sort_method(&args) {
+        sort_files(&mut files_vec, sort_method, args.extension_case_fold, seed);
+    }
+    if args.reverse && !matches!(args.sort_method, Some(SortMethod::Random)) {
+        files_vec.reverse();
+    }
+    let mut displayed_count: usize = files_vec.len();
+    if let Some(lim) = args.limit {
+        displayed_count = displayed_count.min(lim);
+    }
+    if let Some(budget) = args.max_results_bytes {
+        let mut cumulative_size: u64 = 0;
+        displayed_count = files_vec[..displayed_count]
+            .iter()
+            .take_while(|file| {
+                cumulative_size = cumulative_size.saturating_add(file.size);
+                cumulative_size <= budget
+            })
+            .count();
+    }
+    // The full match set is kept so that the summaries can be based on it if requested, and
+    // otherwise, everything from here on only concerns the displayed files.
+    let matched_files: Vec<LffFile> = files_vec;
+    let displayed_files: &[LffFile] = &matched_files[..displayed_count];
+    let summarised_files: &[LffFile] = match args.summary_all {
+        true => &matched_files,
+        false => displayed_files,
+    };
+    let total_size: u64 = LffSizeTotaller::new(args.dedup_hardlinks).total(displayed_files);
+    let mut actions: Vec<Box<dyn LffAction>> = Vec::new();
+    if let Some(target_dir) = &args.move_to {
+        for file in displayed_files {
+            actions.push(Box::new(LffMoveAction {
+                source: PathBuf::from(&file.name),
+                destination: move_destination(target_dir, Path::new(&file.name)),
+            }));
+        }
+    }
+    if let Some(command) = &args.exec {
+        for file in displayed_files {
+            actions.push(Box::new(exec_action(command, &file.name, args.exec_shell)));
+        }
+    }
+    if let Some(command) = &args.batch_exec {
+        let paths: Vec<&OsStr> = displayed_files
+            .iter()
+            .map(|file| file.name.as_os_str())
+            .collect();
+        for action in batch_exec_actions(command, &paths, BATCH_EXEC_MAX_ARG_BYTES) {
+            actions.push(Box::new(action));
+        }
+    }
+
+    // JSON arrays are output even when empty, so that the output is always valid JSON. The same
+    // goes for YAML sequences, the TSV header row, the JSON stream's start and summary events, and
+    // the Prometheus metrics, which should be zero rather than missing.
+    let always_output: bool = match args.format {
+        OutputFormat::Json
+        | OutputFormat::JsonPretty
+        | OutputFormat::JsonStream
+        | OutputFormat::Tsv
+        | OutputFormat::Prometheus => true,
+        #[cfg(feature = "yaml")]
+        OutputFormat::Yaml => true,
+        _ => false,
+    };
+    if args.total_only {
+        let summarised_size: u64 =
+            LffSizeTotaller::new(args.dedup_hardlinks).total(summarised_files);
+        printer.println(format_size(summarised_size, &args));
+    } else if let Some(count) = args.largest_dir {
+        print_largest_dirs(summarised_files, count, &args, printer);
+    } else if let Some(snapshot) = &snapshot {
+        print_snapshot_diff(snapshot, &matched_files, &args, printer);
+    } else if !displayed_files.is_empty() || always_output {
+        match args.format {
+            OutputFormat::Tree => print_tree(displayed_files, false, &args, printer),
+            OutputFormat::HumanTree => print_tree(displayed_files, true, &args, printer),
+            OutputFormat::Json => printer.println(to_string(displayed_files)?),
+            OutputFormat::JsonPretty => printer.println(to_string_pretty(displayed_files)?),
+            OutputFormat::Ndjson => {
+                for file in &displayed_files[streamed_count..] {
+                    printer.println(to_string(file)?);
+                }
+            }
+            OutputFormat::JsonStream => {
+                for file in &displayed_files[streamed_count..] {
+                    printer.println(to_string(&LffEvent::File(file))?);
+                }
+                printer.println(to_string(&LffEvent::Summary {
+                    count: displayed_files.len(),
+                    total: total_size,
+                })?);
+            }
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => {
+                // The serialised YAML already ends with a newline, which the printer adds itself.
+                let yaml: String = serde_yaml::to_string(displayed_files)?;
+                printer.println(yaml.trim_end().to_string());
+            }
+            OutputFormat::Tsv => {
+                printer.println(String::from(TSV_HEADER));
+                for file in displayed_files {
+                    let fields: Vec<String> = delimited_fields(file)
+                        .iter()
+                        .map(|field| escape_tsv_field(field))
+                        .collect();
+                    printer.println(fields.join("\t"));
+                }
+            }
+            OutputFormat::Table if args.columns.is_some() => {
+                print_table(
+                    displayed_files,
+                    args.columns.as_ref().unwrap(),
+                    true,
+                    printer,
+                );
+            }
+            OutputFormat::Table => {
+                let mut columns: Vec<Column> = Vec::new();
+                if args.show_depth {
+                    columns.push(Column::Depth);
+                }
+                columns.push(Column::Size);
+                if args.show_inode {
+                    columns.push(Column::Inode);
+                }
+                columns.push(Column::Modified);
+                if args.age {
+                    columns.push(Column::Age);
+                }
+                columns.push(Column::Name);
+                print_table(displayed_files, &columns, true, printer);
+            }
+            OutputFormat::Markdown => {
+                let columns: &[Column] = match &args.columns {
+                    Some(columns) => columns,
+                    None => &[Column::Size, Column::Name],
+                };
+                print_markdown_table(displayed_files, columns, printer);
+            }
+            OutputFormat::Human => print_human_summary(summarised_files, &args, printer),
+            OutputFormat::Prometheus => print_prometheus_metrics(summarised_files, &args, printer),
+            OutputFormat::Path => {
+                for file in displayed_files {
+                    printer.println(file.name.to_string_lossy().into_owned());
+                }
+            }
+            OutputFormat::Text if args.columns.is_some() => {
+                print_columns(
+                    displayed_files,
+                    args.columns.as_ref().unwrap(),
+                    &args,
+                    printer,
+                );
+            }
+            OutputFormat::Text if args.group_by_extension => {
+                print_extension_groups(
+                    displayed_files,
+                    longest_size_rep,
+                    total_size,
+                    &args,
+                    printer,
+                );
+            }
+            OutputFormat::Text => {
+                for file in displayed_files {
+                    printer.println(format_file_line(file, longest_size_rep, total_size, &args));
+                }
+            }
+            OutputFormat::None => {}
+        }
+    }
+    if displayed_files.is_empty()
+        && !args.quiet
+        && !args.total_only
+        && !matches!(args.format, OutputFormat::None)
+    {
+        // This goes to standard error so that it can't be mistaken for a found file in scripts.
+        printer.eprintln(String::from(NO_FILES_FOUND_STR));
+    }
+    if args.depth_summary {
+        print_depth_summary(summarised_files, &args, printer);
+    }
+    if args.histogram {
+        print_histogram(summarised_files, &args, printer);
+    }
+    if let Some(broken_symlinks) = broken_symlinks {
+        let mut broken_links: Vec<PathBuf> = broken_symlinks.into_inner().unwrap();
+        // Like unreadable directories, broken symlinks are recorded in parallel.
+        broken_links.sort();
+        print_broken_symlinks(broken_links, printer);
+    }
+    if let Some(unreadable) = unreadable {
+        let mut unreadable_dirs: Vec<PathBuf> = unreadable.into_inner().unwrap();
+        // Directories are recorded in parallel, so we sort them for consistent output.
+        unreadable_dirs.sort();
+        print_unreadable_dirs(unreadable_dirs, printer);
+    }
+    let partial_reason: Option<&str> = if cancelled.load(Ordering::Relaxed) {
+        Some("was interrupted")
+    } else if deadline.is_some_and(|deadline| deadline.reached.into_inner()) {
+        Some("timed out")
+    } else {
+        None
+    };
+    let partial: bool = partial_reason.is_some();
+    if let Some(reason) = partial_reason {
+        printer.eprintln(format!(
+            "Warning: the scan {}, so results are partial and any actions were skipped",
+            reason
+        ));
+        // Partial results can't be relied upon to act upon.
+        actions.clear();
+    }
+    if let Some(snapshot_path) = args.save_snapshot.as_deref().filter(|_| !partial) {
+        save_snapshot(snapshot_path, &matched_files)?;
+    }
+    // The counts are checked after the files are displayed so that they can still be inspected, but
+    // before any actions are run, since something is evidently amiss. Partial results aren't
+    // checked, since they can't be expected to be within the bounds.
+    if let Some(min) = args
+        .min_count
+        .filter(|min| !partial && displayed_files.len() < *min)
+    {
+        return Err(LffCountError::TooFew {
+            found: displayed_files.len(),
+            min,
+        }
+        .into());
+    }
+    if let Some(max) = args
+        .max_count
+        .filter(|max| !partial && displayed_files.len() > *max)
+    {
+        return Err(LffCountError::TooMany {
+            found: displayed_files.len(),
+            max,
+        }
+        .into());
+    }
+
+    // Any actions which modify the file system are run through the action log, so that they
+    // respect the dry run flag.
+    let action_log: LffActionLog = LffActionLog {
+        dry_run: args.dry_run,
+    };
+    action_log.run(&actions, printer)?;
+    if let Some(command) = args.after_scan_command.as_deref().filter(|_| !partial) {
+        let action: LffExecAction = after_scan_action(command, displayed_files.len(), total_size);
+        action_log.run(&[Box::new(action)], printer)?;
+    }
+    if args.report_to_syslog && !partial {
+        let largest: Option<&LffFile> = displayed_files.iter().max_by_key(|file| file.size);
+        report_to_syslog(&syslog_message(displayed_files.len(), total_size, largest))?;
+    }
+
+    if let Some(stats) = &stats {
+        print_stats(stats, start_time.elapsed(), printer);
+    }
+
+    Ok(LffScanOutcome {
+        count: displayed_files.len(),
+        total_size,
+    })
+}
+
+/// Runs the [run_finder] function with the supplied `LffArgs` and an optionally-supplied
+/// `LffPrinter`. If one is not supplied, an `LffStdoutPrinter` is used - in effect providing a
+/// default argument for the [run_finder] function.
+#[macro_export]
+macro_rules! run_finder {
+    ($args: expr, $printer: expr) => {
+        $crate::run_finder($args, $printer)
+    };
+    ($args: expr) => {
+        $crate::run_finder($args, &mut $crate::LffStdoutPrinter)
+    };
+}
+
+/// A few functions are excluded from coverage collection:
+/// - [LffEyreHandler::debug]: This is actually tested in [test_lff_eyre_handler], but is excluded
+///   due to the fact that the test must run in isolation. This is because if other tests run before
+///   it, eyre installs its standard handler, not our custom one, resulting in an error when the
+///   test runs.
+/// - [LffStdoutPrinter::println]: We cannot test values being printed to standard out, so this
+///   function is excluded.
+/// - [install_interrupt_handler]: Only one handler can be installed per process, and Ctrl-C can't
+///   be pressed in tests, so this function is excluded. The cancellation it triggers is tested with
+///   [run_finder_cancellable] and the scan context instead.
+/// - [run_finder_watching]: Watching relies on real file system events and runs until interrupted,
+///   so this function is excluded. The debouncing it relies on is tested with [wait_for_changes]
+///   and synthetic events instead.
+/// - [report_to_syslog]: On Unix, this logs to the system's syslog daemon, which isn't available in
+///   every test environment and can't be read back, so this function is excluded. The message it
+///   logs is tested with [syslog_message] instead.
+/// - `main`: Since the main function in the binary only consists of setting up eyre - which is
+///   tested elsewhere - and parsing command-line arguments before running the finder, there is no
+///   need to test this. Indeed, running the main function in a test results in errors because clap
+///   attempts to parse the command-line arguments that are passed to `cargo test`.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        auto_min_size, auto_unit, batch_exec_actions, can_stream_output, config_path_in,
+        diff_snapshots, escape_tsv_field, exec_action, expand_path, format_file_line,
+        format_relative_time, format_size_in_unit, format_timestamp, handle_directory,
+        handle_directory_breadth_first, handle_entry, is_across_mount, is_same_device,
+        move_destination, parse_args_with_config, parse_args_with_config_from, parse_boot_time,
+        parse_command, parse_duration, parse_ext_thresholds, parse_human_size, parse_percentage,
+        parse_regex, parse_separator, path_is_hidden, print_human_summary, read_config,
+        read_input_paths, resolve_start_directories, run_finder_cancellable,
+        scan_found_files_streaming, scan_input_paths, scan_start_directories, scan_start_directory,
+        seeded_hash, strip_extension_dot, syslog_message, wait_for_changes, ColorChoice, Column,
+        LffAction, LffActionLog, LffArgs, LffCountError, LffDeadline, LffExecAction,
+        LffEyreHandler, LffFile, LffMoveAction, LffPrinter, LffRejection, LffScanContext,
+        LffScanIter, LffScanOutcome, LffScanner, LffSnapshotChange, LffStats, OutputFormat,
+        SortMethod, DEFAULT_MIN_SIZE_MIB, MEBIBYTE, NO_FILES_FOUND_STR, TSV_HEADER,
+    };
+    use clap::Parser;
+    use eyre::{eyre, Report};
+    use log::{
+        set_logger, set_max_level, Level, LevelFilter, Log, Metadata as LogMetadata, Record,
+    };
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use notify::{Error as NotifyError, Event, EventKind};
+    use serde_json::{from_str, json, Value};
+    use std::cmp::Reverse;
+    use std::collections::BTreeMap;
+    use std::env::{set_var, var};
+    use std::ffi::{OsStr, OsString};
+    use std::fs::{
+        canonicalize, create_dir, create_dir_all, read_dir, read_to_string, write, File, Metadata,
+        ReadDir,
+    };
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+    use std::str::from_utf8_unchecked;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+    use std::sync::{Arc, Mutex};
+    use std::thread::sleep;
+    use std::time::{Duration, Instant, SystemTime};
+    use tempfile::{tempdir, TempDir};
+
+    const BASE_ARGS: LffArgs = LffArgs {
+        directories: Vec::new(),
+        absolute: false,
+        absolute_from: None,
+        after_scan_command: None,
+        age: false,
+        auto_units: false,
+        base_ten: false,
+        batch_exec: None,
+        case_sensitive: false,
+        color: ColorChoice::Auto,
+        color_tier_red: 1024 * MEBIBYTE,
+        color_tier_yellow: 100 * MEBIBYTE,
+        color_tiers: false,
+        compare_to: None,
+        config: None,
+        breadth_first: false,
+        columns: None,
+        dedup_hardlinks: false,
+        dedupe_output: false,
+        depth_summary: false,
+        dereference_count: false,
+        dry_run: false,
+        exclude_empty: false,
+        exclude_extensions: Vec::new(),
+        exclude_hidden: false,
+        exclude_larger_than: None,
+        exec: None,
+        exec_shell: false,
+        explain: false,
+        ext_thresholds: None,
+        extension: None,
+        extension_case_fold: false,
+        follow_only_top_level_symlinks: false,
+        format: OutputFormat::Text,
+        group_by_extension: false,
+        histogram: false,
+        hidden_only: false,
+        ignore_case: false,
+        include_dirs: false,
+        into_archives: false,
+        largest_dir: None,
+        limit: None,
+        limit_per_dir: None,
+        match_full_path: false,
+        max_age: None,
+        max_count: None,
+        max_results_bytes: None,
+        max_time: None,
+        max_size_bytes: None,
+        min_age: None,
+        min_count: None,
+        min_files_in_dir: None,
+        min_size_bytes: None,
+        min_size_auto: false,
+        min_size_mib: 0.0,
+        min_size_percent: None,
+        move_to: None,
+        name_pattern: None,
+        newer_than: None,
+        no_recurse: false,
+        older_than: None,
+        null: false,
+        one_file_system: false,
+        paths_from_file: None,
+        percent: false,
+        pretty: false,
+        prune_empty_dirs: false,
+        quiet: false,
+        regex_pattern: None,
+        report_to_syslog: false,
+        respect_ignore_files: false,
+        reverse: false,
+        right_align: false,
+        sample: None,
+        save_snapshot: None,
+        seed: None,
+        separator: None,
+        show_depth: false,
+        show_inode: false,
+        since_boot: false,
+        sort_method: None,
+        stats: false,
+        stdin: false,
+        stdin_filter: false,
+        summary_all: false,
+        total_only: false,
+        unordered: false,
+        verbose: false,
+        warn_unreadable: false,
+        watch: false,
+        yes: false,
+    };
+
+    /// A test printer that records 'printed' output in one `Vec`, and diagnostics in another.
+    /// Derives `Default` for convenience's sake when instantiating test instances.
+    #[derive(Default)]
+    struct LffTestPrinter(Vec<String>, Vec<String>);
+
+    /// The implementation of our printer trait for the test printer.
+    impl LffPrinter for LffTestPrinter {
+        /// Record the value in the printer's first `Vec`, rather than printing it, so we can assert
+        /// on it later.
+        fn println(&mut self, value: String) {
+            self.0.push(value);
+        }
+
+        /// Record the value in the printer's second `Vec`, rather than printing it, so we can
+        /// assert on it later.
+        fn eprintln(&mut self, value: String) {
+            self.1.push(value);
+        }
+    }
+
+    /// The messages logged during tests, along with their levels.
+    static LOGGED_MESSAGES: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+    /// A test logger that records logged messages in [LOGGED_MESSAGES], so we can assert on them.
+    struct LffTestLogger;
+
+    /// The implementation of the log trait for the test logger.
+    impl Log for LffTestLogger {
+        fn enabled(&self, _metadata: &LogMetadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            LOGGED_MESSAGES
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Installs the test logger, if it hasn't been already. Only warnings and errors are recorded,
+    /// so that tests running in parallel don't flood the log with traversal messages.
+    fn install_test_logger() {
+        static TEST_LOGGER: LffTestLogger = LffTestLogger;
+        // This fails if the logger has already been installed by another test, which is fine.
+        let _ = set_logger(&TEST_LOGGER);
+        set_max_level(LevelFilter::Warn);
+    }
+
+    /// A stub action that creates a file at the given path when performed.
+    struct LffTestAction(PathBuf);
+
+    /// The implementation of the action trait for the stub action.
+    impl LffAction for LffTestAction {
+        /// Describe the file creation.
+        fn describe(&self) -> String {
+            format!("create {:?}", self.0)
+        }
+
+        /// Create the file.
+        fn perform(&self) -> eyre::Result<()> {
+            write(&self.0, "")?;
+            Ok(())
+        }
+    }
+
+    /// Ensure that the default arguments match those parsed from the command line when only a
+    /// directory is supplied.
+    #[test]
+    fn test_lff_args_default() {
+        let parsed_args: LffArgs = LffArgs::try_parse_from(["lff", "test_resources"]).unwrap();
+        let default_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            ..LffArgs::default()
+        };
+        assert_eq!(parsed_args.directories, default_args.directories);
+        assert_eq!(parsed_args.min_size_mib, default_args.min_size_mib);
+        assert!(matches!(default_args.format, OutputFormat::Text));
+        assert!(default_args.sort_method.is_none());
+        assert!(default_args.limit.is_none());
+        assert!(!default_args.absolute);
+
+        // None of the test resources are large enough to be found with the default minimum size.
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(default_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert!(test_printer.0.is_empty());
+    }
+
+    /// Ensure that the values in a config file are applied to flags that weren't supplied on the
+    /// command line, while those that were supplied take precedence.
+    #[test]
+    fn test_parse_args_with_config() {
+        let config_dir: TempDir = tempdir().unwrap();
+        let config_path: PathBuf = config_dir.path().join("config.toml");
+        write(
+            &config_path,
+            "absolute = true\nbase_ten = true\nexclude_hidden = true\nformat = \"json-pretty\"\n\
+             limit = 3\nmin_size_mib = 10.0\npretty = true\nquiet = true\nright_align = true\n\
+             sort_method = \"size\"\n",
+        )
+        .unwrap();
+        let config_string: String = config_path.to_string_lossy().into_owned();
+
+        let config_args: LffArgs =
+            parse_args_with_config(["lff", "--config", &config_string, "test_resources"]).unwrap();
+        assert!(config_args.absolute);
+        assert!(config_args.base_ten);
+        assert!(config_args.exclude_hidden);
+        assert!(matches!(config_args.format, OutputFormat::JsonPretty));
+        assert_eq!(Some(3), config_args.limit);
+        assert_eq!(10.0, config_args.min_size_mib);
+        assert!(config_args.pretty);
+        assert!(config_args.quiet);
+        assert!(config_args.right_align);
+        assert!(matches!(config_args.sort_method, Some(SortMethod::Size)));
+
+        let overridden_args: LffArgs = parse_args_with_config([
+            "lff",
+            "--config",
+            &config_string,
+            "-m",
+            "5",
+            "-f",
+            "text",
+            "-l",
+            "1",
+            "-s",
+            "name",
+            "test_resources",
+        ])
+        .unwrap();
+        assert_eq!(5.0, overridden_args.min_size_mib);
+        assert!(matches!(overridden_args.format, OutputFormat::Text));
+        assert_eq!(Some(1), overridden_args.limit);
+        assert!(matches!(
+            overridden_args.sort_method,
+            Some(SortMethod::Name)
+        ));
+        // Flags not supplied on the command line still come from the config.
+        assert!(overridden_args.pretty);
+    }
+
+    /// Ensure that the config file is read from the default location when none is supplied, and
+    /// that a missing default config file is not an error.
+    #[test]
+    fn test_parse_args_with_default_config() {
+        let config_home: TempDir = tempdir().unwrap();
+        let default_path: PathBuf = config_home.path().join("lff").join("config.toml");
+        assert_eq!(
+            Some(default_path.clone()),
+            config_path_in(Some(config_home.path().into()), Some("/home/lff".into()))
+        );
+        assert_eq!(
+            Some(PathBuf::from("/home/lff/.config/lff/config.toml")),
+            config_path_in(None, Some("/home/lff".into()))
+        );
+        assert!(config_path_in(None, None).is_none());
+
+        let parsed_args: LffArgs =
+            parse_args_with_config_from(["lff", "test_resources"], Some(default_path.clone()))
+                .unwrap();
+        assert!(!parsed_args.pretty);
+
+        create_dir(config_home.path().join("lff")).unwrap();
+        write(&default_path, "pretty = true").unwrap();
+        let parsed_args: LffArgs =
+            parse_args_with_config_from(["lff", "test_resources"], Some(default_path)).unwrap();
+        assert!(parsed_args.pretty);
+    }
+
+    /// Ensure that values in a config file that conflict with flags supplied on the command line
+    /// are dropped, so that the flags on the command line take precedence.
+    #[test]
+    fn test_parse_args_with_conflicting_config() {
+        let config_dir: TempDir = tempdir().unwrap();
+        let config_path: PathBuf = config_dir.path().join("config.toml");
+        write(&config_path, "exclude_hidden = true\nmin_size_mib = 5").unwrap();
+        let config_string: String = config_path.to_string_lossy().into_owned();
+
+        let parsed_args: LffArgs =
+            parse_args_with_config(["lff", "--config", &config_string, "."]).unwrap();
+        assert!(parsed_args.exclude_hidden);
+        assert_eq!(5.0, parsed_args.min_size_mib);
+
+        let parsed_args: LffArgs =
+            parse_args_with_config(["lff", "--config", &config_string, "--hidden-only", "."])
+                .unwrap();
+        assert!(parsed_args.hidden_only);
+        assert!(!parsed_args.exclude_hidden);
+        assert_eq!(5.0, parsed_args.min_size_mib);
+
+        let parsed_args: LffArgs =
+            parse_args_with_config(["lff", "--config", &config_string, "--min-size-auto", "."])
+                .unwrap();
+        assert!(parsed_args.min_size_auto);
+        assert!(parsed_args.exclude_hidden);
+        assert_eq!(DEFAULT_MIN_SIZE_MIB, parsed_args.min_size_mib);
+    }
+
+    /// Ensure that the correct error messages are generated when a supplied config file doesn't
+    /// exist, or is invalid.
+    #[test]
+    fn test_read_config_invalid() {
+        let config_dir: TempDir = tempdir().unwrap();
+        let config_path: PathBuf = config_dir.path().join("config.toml");
+        assert!(read_config(&config_path, false).unwrap().pretty.is_none());
+        assert_eq!(
+            format!("Could not read config file {:?}", config_path),
+            read_config(&config_path, true).err().unwrap().to_string()
+        );
+
+        for contents in ["pretty = ", "prety = true", "sort_method = \"colour\""] {
+            write(&config_path, contents).unwrap();
+            assert_eq!(
+                format!("Invalid config file {:?}", config_path),
+                read_config(&config_path, true).err().unwrap().to_string()
+            );
+        }
+    }
+
+    /// Ensure that our custom eyre handler correctly formats returned errors.
+    ///
+    /// This test is ignored by default because it needs to run in isolation - in cases where it is
+    /// run after other tests, eyre will have already installed its default handler, resulting in an
+    /// error when this test attempts to install our custom one.
+    #[test]
+    #[ignore]
+    fn test_lff_eyre_handler() {
+        // Install our custom handler in the same way as the main function.
+        eyre::set_hook(Box::new(|_| Box::new(LffEyreHandler))).unwrap();
+
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        // We pass an invalid glob as an argument so that we can get a consistent error that will
+        // not vary based on operating system - unlike a file not found error, for example.
+        let test_args: &LffArgs = &LffArgs {
+            name_pattern: Some(String::from("[")),
+            ..BASE_ARGS
+        };
+
+        let test_error: Report =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap_err();
+        // By formatting the Report like this, we directly call the debug function of our handler.
+        let formatted_error: String = format!("{:?}", test_error);
+        assert_eq!(
+            "Invalid glob from name pattern flag: '['\n\n\
+            Caused by:\n    error parsing glob '[': unclosed character class; missing ']'",
+            formatted_error
+        );
+    }
+
+    /// Ensure that when running actions through the action log, they are performed.
+    #[test]
+    fn test_action_log_run() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let test_file: PathBuf = test_dir.path().join("created");
+        let actions: Vec<Box<dyn LffAction>> = vec![Box::new(LffTestAction(test_file.clone()))];
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        LffActionLog { dry_run: false }
+            .run(&actions, &mut test_printer)
+            .unwrap();
+        assert!(test_file.exists());
+        assert!(test_printer.0.is_empty());
+    }
+
+    /// Ensure that when running actions through the action log in a dry run, they are described
+    /// rather than performed.
+    #[test]
+    fn test_action_log_run_dry_run() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let test_file: PathBuf = test_dir.path().join("created");
+        let actions: Vec<Box<dyn LffAction>> = vec![Box::new(LffTestAction(test_file.clone()))];
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        LffActionLog { dry_run: true }
+            .run(&actions, &mut test_printer)
+            .unwrap();
+        // Nothing should have been created.
+        assert!(!test_file.exists());
+        assert_eq!(
+            vec![format!("Would create {:?}", test_file)],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when an action fails, the error is output and the remaining actions are still
+    /// run.
+    #[test]
+    fn test_action_log_run_failure() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let bad_file: PathBuf = test_dir.path().join("missing").join("created");
+        let good_file: PathBuf = test_dir.path().join("created");
+        let actions: Vec<Box<dyn LffAction>> = vec![
+            Box::new(LffTestAction(bad_file.clone())),
+            Box::new(LffTestAction(good_file.clone())),
+        ];
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let run_error: Report = LffActionLog { dry_run: false }
+            .run(&actions, &mut test_printer)
+            .unwrap_err();
+        assert_eq!("1 of 2 actions failed", run_error.to_string());
+        assert!(good_file.exists());
+        assert_eq!(1, test_printer.1.len());
+        assert!(test_printer.1[0].starts_with(&format!("Could not create {:?}: ", bad_file)));
+    }
+
+    /// Ensure that move destinations preserve the relative structure of files within the target
+    /// directory, even for absolute paths or those containing parent directory components.
+    #[test]
+    fn test_move_destination() {
+        let target: &Path = Path::new("target_dir");
+        assert_eq!(
+            PathBuf::from("target_dir/test_resources/visible/mud.md"),
+            move_destination(target, Path::new("test_resources/visible/mud.md"))
+        );
+        assert_eq!(
+            PathBuf::from("target_dir/abs/snow.txt"),
+            move_destination(target, Path::new("/abs/snow.txt"))
+        );
+        assert_eq!(
+            PathBuf::from("target_dir/snow.txt"),
+            move_destination(target, Path::new("../snow.txt"))
+        );
+    }
+
+    /// Ensure that a leading tilde is expanded to the home directory, but only when it is on its
+    /// own or followed by a separator.
+    #[test]
+    fn test_expand_path_home() {
+        let home: String = var("HOME").unwrap();
+        assert_eq!(PathBuf::from(&home), expand_path("~").unwrap());
+        assert_eq!(
+            PathBuf::from(format!("{home}/Downloads")),
+            expand_path("~/Downloads").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("~user/Downloads"),
+            expand_path("~user/Downloads").unwrap()
+        );
+        assert_eq!(PathBuf::from("a/~"), expand_path("a/~").unwrap());
+    }
+
+    /// Ensure that environment variables are expanded in each of the supported forms.
+    #[test]
+    fn test_expand_path_env_var() {
+        set_var("LFF_TEST_EXPAND_DIR", "test_resources");
+        for input in [
+            "$LFF_TEST_EXPAND_DIR/visible",
+            "${LFF_TEST_EXPAND_DIR}/visible",
+        ] {
+            assert_eq!(
+                PathBuf::from("test_resources/visible"),
+                expand_path(input).unwrap()
+            );
+        }
+        // The percent form is only expanded on Windows, where it is native.
+        let percent_expanded: PathBuf = if cfg!(windows) {
+            PathBuf::from("test_resources/visible")
+        } else {
+            PathBuf::from("%LFF_TEST_EXPAND_DIR%/visible")
+        };
+        assert_eq!(
+            percent_expanded,
+            expand_path("%LFF_TEST_EXPAND_DIR%/visible").unwrap()
+        );
+        // Dollar and percent signs that aren't part of a variable reference are left alone, and a
+        // doubled dollar sign is a literal one.
+        assert_eq!(PathBuf::from("a$/50%"), expand_path("a$/50%").unwrap());
+        assert_eq!(
+            PathBuf::from("$LFF_TEST_EXPAND_DIR/$$"),
+            expand_path("$$LFF_TEST_EXPAND_DIR/$$$$").unwrap()
+        );
+    }
+
+    /// Ensure that the correct error message is generated when expanding a directory that
+    /// references an environment variable that isn't set.
+    #[test]
+    fn test_expand_path_unknown_var() {
+        let expand_error: Report = expand_path("$LFF_TEST_UNDEFINED/visible").unwrap_err();
+        assert_eq!(
+            "Could not expand environment variable 'LFF_TEST_UNDEFINED' in directory \
+            '$LFF_TEST_UNDEFINED/visible'",
+            expand_error.to_string()
+        );
+    }
+
+    /// Ensure that the hidden status of paths is correctly determined.
+    #[test]
+    fn test_hidden_paths() {
+        let visible_file: &Path = Path::new("test_resources/snow.txt");
+        let visible_dir: &Path = Path::new("test_resources/visible");
+        assert!(!path_is_hidden(visible_file));
+        assert!(!path_is_hidden(visible_dir));
+
+        let hidden_file: &Path = Path::new("test_resources/.hidden");
+        let hidden_dir: &Path = Path::new("test_resources/.hidden_dir");
+        assert!(path_is_hidden(hidden_file));
+        assert!(path_is_hidden(hidden_dir));
+
+        // In order to create a situation in which the to_str() call on the file name fails the
+        // UTF-8 validity check, we need to enter unsafe mode and create a Path from an invalid
+        // sequence of bytes. These bytes are taken directly from the documentation of the
+        // from_utf8() function, in the part documenting incorrect bytes.
+        unsafe {
+            let invalid_bytes: Vec<u8> = vec![0, 159, 145, 160];
+            let non_utf8_path: &Path = Path::new(from_utf8_unchecked(&invalid_bytes));
+            assert!(!path_is_hidden(non_utf8_path));
+        }
+        // Since this is an invalid file name altogether, we expect this to not be hidden.
+        let invalid_path: &Path = Path::new("test_resources/..");
+        assert!(!path_is_hidden(invalid_path));
+    }
+
+    /// Ensure that a file has the correct details extracted.
+    #[test]
+    fn test_handle_entry() {
+        let test_file: PathBuf = Path::new("test_resources/snow.txt").to_path_buf();
+        let file: LffFile = handle_entry(test_file, &BASE_ARGS, None).unwrap();
+        assert_eq!("test_resources/snow.txt", file.name);
+        assert_eq!(Some(OsString::from("txt")), file.extension);
+        assert_eq!(544, file.size);
+        assert_eq!("544", file.formatted_size);
+        assert!(!file.hidden);
+        assert!(!file.is_dir);
+    }
+
+    /// Ensure that when handling an entry with the absolute flag, the correct file name is
+    /// extracted.
+    #[test]
+    fn test_handle_entry_absolute() {
+        let test_file: PathBuf = Path::new("test_resources/snow.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            absolute: true,
+            ..BASE_ARGS
+        };
+
+        let file: LffFile = handle_entry(test_file, test_args, None).unwrap();
+        assert!(file
+            .name
+            .to_str()
+            .unwrap()
+            // Obviously the full absolute path will differ on different machines, but as long as
+            // the 'lff/' part of this path is there, we at least know that the path extends further
+            // back than the root directory of this repository.
+            .ends_with("lff/test_resources/snow.txt"));
+    }
+
+    /// Ensure that when handling an entry with the absolute flag and a canonical parent directory,
+    /// the file name is joined onto the parent rather than being canonicalised.
+    #[test]
+    fn test_handle_entry_absolute_canonical_parent() {
+        let test_file: PathBuf = Path::new("test_resources/snow.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            absolute: true,
+            ..BASE_ARGS
+        };
+
+        let file: LffFile = handle_entry(test_file, test_args, Some(Path::new("/parent"))).unwrap();
+        assert_eq!("/parent/snow.txt", file.name);
+    }
+
+    /// Ensure that the correct error message is generated when an entry with an invalid path is
+    /// supplied, and the absolute flag is on.
+    #[test]
+    fn test_handle_entry_absolute_invalid_path() {
+        let test_file: PathBuf = Path::new("test_resources/snow2.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            absolute: true,
+            ..BASE_ARGS
+        };
+        let canonicalize_error: Report = handle_entry(test_file, test_args, None).unwrap_err();
+        assert_eq!(
+            "Could not generate absolute path for \"test_resources/snow2.txt\"",
+            canonicalize_error.to_string()
+        );
+    }
+
+    /// Ensure that files with no extension and hidden files are both correctly determined to have
+    /// no extension.
+    #[test]
+    fn test_handle_entry_none_extension() {
+        let test_file_no_ext: PathBuf = Path::new("test_resources/LICENCE").to_path_buf();
+        let no_ext_file: LffFile = handle_entry(test_file_no_ext, &BASE_ARGS, None).unwrap();
+        assert_eq!(None, no_ext_file.extension);
+
+        let test_file_hidden: PathBuf = Path::new("test_resources/.hidden").to_path_buf();
+        let hidden_file: LffFile = handle_entry(test_file_hidden, &BASE_ARGS, None).unwrap();
+        assert_eq!(None, hidden_file.extension);
+    }
+
+    /// Ensure that the correct error message is generated when an entry with an invalid path is
+    /// supplied.
+    #[test]
+    fn test_handle_entry_metadata_invalid_path() {
+        let test_file: PathBuf = Path::new("test_resources/snow2.txt").to_path_buf();
+        let metadata_error: Report = handle_entry(test_file, &BASE_ARGS, None).unwrap_err();
+        assert_eq!(
+            "Could not retrieve metadata for \"test_resources/snow2.txt\"",
+            metadata_error.to_string()
+        );
+    }
+
+    /// Ensure that an entry's file size is of base 2 by default when the pretty flag is passed.
+    #[test]
+    fn test_handle_entry_pretty() {
+        let test_file: PathBuf = Path::new("test_resources/.hidden_dir/spider.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            pretty: true,
+            ..BASE_ARGS
+        };
+
+        let file: LffFile = handle_entry(test_file, test_args, None).unwrap();
+        assert_eq!("1.16 KiB", file.formatted_size);
+    }
+
+    /// Ensure that an entry's file size is of base 10 when both the pretty and base ten flags are
+    /// passed.
+    #[test]
+    fn test_handle_entry_pretty_base_ten() {
+        let test_file: PathBuf = Path::new("test_resources/.hidden_dir/spider.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            pretty: true,
+            base_ten: true,
+            ..BASE_ARGS
+        };
+
+        let file: LffFile = handle_entry(test_file, test_args, None).unwrap();
+        assert_eq!("1.18 KB", file.formatted_size);
+    }
+
+    /// Ensure that an entry's file size is of the abbreviated style when the pretty flag is passed.
+    #[test]
+    fn test_handle_entry_pretty_under_kilo() {
+        let test_file: PathBuf = Path::new("test_resources/snow.txt").to_path_buf();
+        let test_args: &LffArgs = &LffArgs {
+            pretty: true,
+            ..BASE_ARGS
+        };
+
+        let file: LffFile = handle_entry(test_file, test_args, None).unwrap();
+        assert_eq!("544 B", file.formatted_size);
+    }
+
+    /// Ensure that hidden entries are correctly identified as such.
+    #[test]
+    fn test_handle_entry_hidden() {
+        let test_file: PathBuf = Path::new("test_resources/.hidden").to_path_buf();
+        let file: LffFile = handle_entry(test_file, &BASE_ARGS, None).unwrap();
+        assert!(file.hidden);
+    }
+
+    /// Ensure that all of the files in the test directory have their details correctly extracted.
+    #[test]
+    fn test_handle_directory() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let mut files: Vec<LffFile> =
+            handle_directory(test_dir, &BASE_ARGS, &LffScanContext::default()).unwrap();
+        // Since handle_directory() does no sorting in of itself, we need to manually sort the
+        // returned files in order for the test to be repeatable - the files are read in parallel,
+        // after all.
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(5, files.len());
+
+        let hidden_file: &LffFile = &files[0];
+        assert_eq!("test_resources/.hidden", hidden_file.name);
+        assert_eq!(None, hidden_file.extension);
+        assert_eq!(0, hidden_file.size);
+        assert_eq!("0", hidden_file.formatted_size);
+        assert!(hidden_file.hidden);
+
+        let spider_file: &LffFile = &files[1];
+        assert_eq!("test_resources/.hidden_dir/spider.txt", spider_file.name);
+        assert_eq!(Some(OsString::from("txt")), spider_file.extension);
+        assert_eq!(1183, spider_file.size);
+        assert_eq!("1183", spider_file.formatted_size);
+        assert!(!spider_file.hidden);
+
+        let licence_file: &LffFile = &files[2];
+        assert_eq!("test_resources/LICENCE", licence_file.name);
+        assert_eq!(None, licence_file.extension);
+        assert_eq!(27, licence_file.size);
+        assert_eq!("27", licence_file.formatted_size);
+        assert!(!licence_file.hidden);
+
+        let snow_file: &LffFile = &files[3];
+        assert_eq!("test_resources/snow.txt", snow_file.name);
+        assert_eq!(Some(OsString::from("txt")), snow_file.extension);
+        assert_eq!(544, snow_file.size);
+        assert_eq!("544", snow_file.formatted_size);
+        assert!(!snow_file.hidden);
+
+        let mud_file: &LffFile = &files[4];
+        assert_eq!("test_resources/visible/mud.md", mud_file.name);
+        assert_eq!(Some(OsString::from("md")), mud_file.extension);
+        assert_eq!(329, mud_file.size);
+        assert_eq!("329", mud_file.formatted_size);
+        assert!(!mud_file.hidden);
+    }
+
+    /// Ensure that 'smart limiting' (early exit) is applied when handling a directory and the
+    /// limit flag is passed and the output is unordered.
+    #[test]
+    fn test_handle_directory_limit_no_sort() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            limit: Some(1),
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+    }
+
+    /// Ensure that when a diagnostics sink is supplied, a line is written to it for each directory
+    /// entered or skipped, and for each file considered, with the reason it was dropped if it was.
+    #[test]
+    fn test_handle_directory_diagnostics() {
+        for (limit_per_dir, snow_line) in [
+            (None, "\"test_resources/snow.txt\" - kept"),
+            (
+                Some(0),
+                "\"test_resources/snow.txt\" - dropped: directory limit reached",
+            ),
+        ] {
+            let test_args: &LffArgs = &LffArgs {
+                exclude_hidden: true,
+                limit_per_dir,
+                min_size_bytes: Some(100),
+                ..BASE_ARGS
+            };
+            let lines: Mutex<Vec<String>> = Mutex::default();
+            let record_line = |line: String| lines.lock().unwrap().push(line);
+            let test_context: LffScanContext = LffScanContext {
+                diagnostics: Some(&record_line),
+                ..LffScanContext::default()
+            };
+
+            scan_start_directory(Path::new("test_resources"), test_args, &test_context).unwrap();
+            let lines: Vec<String> = lines.into_inner().unwrap();
+            for expected_line in [
+                "Entering directory \"test_resources\"",
+                "Entering directory \"test_resources/visible\"",
+                "Skipping hidden directory \"test_resources/.hidden_dir\"",
+                "\"test_resources/LICENCE\" - dropped: too small",
+                snow_line,
+            ] {
+                assert!(lines.iter().any(|line| line == expected_line));
+            }
+        }
+    }
+
+    /// Ensure that once the cancelled flag is set mid-scan, no further entries are handled, so the
+    /// scan terminates early with the files found so far.
+    #[test]
+    fn test_handle_directory_cancelled() {
+        let test_tree: TempDir = create_test_tree(4, 50);
+        let test_args: &LffArgs = &LffArgs {
+            min_size_bytes: Some(0),
+            ..BASE_ARGS
+        };
+        let cancelled: AtomicBool = AtomicBool::new(false);
+        let handled_count: AtomicUsize = AtomicUsize::new(0);
+        // The diagnostics sink acts as a hook into the scan, cancelling it after ten files.
+        let cancel_after_ten = |line: String| {
+            if line.ends_with(" - kept") && handled_count.fetch_add(1, Ordering::Relaxed) == 9 {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        };
+        let test_context: LffScanContext = LffScanContext {
+            diagnostics: Some(&cancel_after_ten),
+            cancelled: Some(&cancelled),
+            ..LffScanContext::default()
+        };
+
+        let files: Vec<LffFile> =
+            scan_start_directory(test_tree.path(), test_args, &test_context).unwrap();
+        assert!(cancelled.load(Ordering::Relaxed));
+        // Entries already being handled in parallel when the flag was set may still be found.
+        assert!(files.len() >= 10 && files.len() < 200);
+        assert_eq!(files.len(), handled_count.load(Ordering::Relaxed));
+    }
+
+    /// Ensure that once the deadline is reached mid-scan, no further entries are handled, so the
+    /// scan terminates early with the files found so far, and the deadline records that it was
+    /// reached.
+    #[test]
+    fn test_handle_directory_deadline() {
+        let test_tree: TempDir = create_test_tree(4, 50);
+        let test_args: &LffArgs = &LffArgs {
+            min_size_bytes: Some(0),
+            ..BASE_ARGS
+        };
+        let deadline: LffDeadline = LffDeadline::new(Instant::now(), Duration::from_millis(50));
+        // The diagnostics sink acts as a hook into the scan, slowing down the handling of each file
+        // so that the whole scan would take several seconds.
+        let slow_down = |line: String| {
+            if line.ends_with(" - kept") {
+                sleep(Duration::from_millis(100));
+            }
+        };
+        let test_context: LffScanContext = LffScanContext {
+            diagnostics: Some(&slow_down),
+            deadline: Some(&deadline),
+            ..LffScanContext::default()
+        };
+
+        let files: Vec<LffFile> =
+            scan_start_directory(test_tree.path(), test_args, &test_context).unwrap();
+        assert!(deadline.reached.into_inner());
+        assert!(!files.is_empty() && files.len() < 200);
+    }
+
+    /// Ensure that when the finder's maximum time passes, a warning that the results are partial is
+    /// output.
+    #[test]
+    fn test_run_finder_max_time() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            max_time: Some(Duration::ZERO),
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert_eq!(
+            vec![
+                "Warning: the scan timed out, so results are partial and any actions were skipped"
+            ],
+            test_printer.1
+        );
+    }
+
+    /// Ensure that when the finder is cancelled, the files found so far are output along with a
+    /// warning that they are partial, and that neither the count bounds nor any actions are
+    /// applied.
+    #[test]
+    fn test_run_finder_cancelled() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            min_count: Some(1),
+            move_to: Some(PathBuf::from("quarantine")),
+            dry_run: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        // The flag is set before the scan starts, so nothing is found.
+        let outcome: LffScanOutcome =
+            run_finder_cancellable(test_args, &mut test_printer, &AtomicBool::new(true)).unwrap();
+        assert_eq!(0, outcome.count);
+        assert!(test_printer.0.is_empty());
+        assert_eq!(
+            vec![
+                NO_FILES_FOUND_STR,
+                "Warning: the scan was interrupted, so results are partial and any actions were \
+                skipped",
+            ],
+            test_printer.1
+        );
+    }
+
+    /// Ensure that when waiting for changes to watched directories, a burst of change events only
+    /// results in a single change once it settles, with access events and watcher errors ignored.
+    #[test]
+    fn test_wait_for_changes() {
+        let (sender, receiver) = channel();
+        sender
+            .send(Ok(Event::new(EventKind::Access(AccessKind::Any))))
+            .unwrap();
+        sender
+            .send(Err(NotifyError::generic("test error")))
+            .unwrap();
+        for _ in 0..3 {
+            sender
+                .send(Ok(Event::new(EventKind::Create(CreateKind::File))))
+                .unwrap();
+        }
+
+        let not_cancelled: AtomicBool = AtomicBool::new(false);
+        assert!(wait_for_changes(
+            &receiver,
+            Duration::from_millis(10),
+            &not_cancelled
+        ));
+        // The whole burst should have been consumed by the single change.
+        assert!(receiver.try_recv().is_err());
+
+        // Access events alone shouldn't count as a change, so the wait only ends when the watcher
+        // stops sending events.
+        sender
+            .send(Ok(Event::new(EventKind::Access(AccessKind::Any))))
+            .unwrap();
+        drop(sender);
+        assert!(!wait_for_changes(
+            &receiver,
+            Duration::from_millis(10),
+            &not_cancelled
+        ));
+    }
+
+    /// Ensure that when waiting for changes to watched directories is cancelled, no change is
+    /// reported, even if change events have been received.
+    #[test]
+    fn test_wait_for_changes_cancelled() {
+        let (sender, receiver) = channel();
+        sender
+            .send(Ok(Event::new(EventKind::Modify(ModifyKind::Any))))
+            .unwrap();
+
+        assert!(!wait_for_changes(
+            &receiver,
+            Duration::from_millis(10),
+            &AtomicBool::new(true)
+        ));
+    }
+
+    /// Ensure that the limit flag is ignored when handling a directory and the sort flag is also
+    /// passed.
+    #[test]
+    fn test_handle_directory_limit_with_sort() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            limit: Some(1),
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        // Despite passing a limit of 1, we still get 5 files.
+        assert_eq!(5, files.len());
+    }
+
+    /// Ensure that the absolute paths of files found when handling a directory are identical to
+    /// those generated by canonicalising each file's path individually, whether traversing
+    /// depth-first or breadth-first.
+    #[test]
+    fn test_handle_directory_absolute() {
+        let test_args: &LffArgs = &LffArgs {
+            absolute: true,
+            ..BASE_ARGS
+        };
+        let relative_files: Vec<LffFile> = handle_directory(
+            read_dir("test_resources").unwrap(),
+            &BASE_ARGS,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        let mut expected_names: Vec<OsString> = relative_files
+            .iter()
+            .map(|file| canonicalize(&file.name).unwrap().into_os_string())
+            .collect();
+        expected_names.sort();
+
+        for traversal in [handle_directory, handle_directory_breadth_first] {
+            let test_dir: ReadDir = read_dir("test_resources").unwrap();
+            let files: Vec<LffFile> =
+                traversal(test_dir, test_args, &LffScanContext::default()).unwrap();
+            let mut names: Vec<OsString> = files.into_iter().map(|file| file.name).collect();
+            names.sort();
+            assert_eq!(expected_names, names);
+        }
+    }
+
+    /// Ensure that when traversing breadth-first, all of the top-level files are found before the
+    /// files within subdirectories.
+    #[test]
+    fn test_handle_directory_breadth_first() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let files: Vec<LffFile> =
+            handle_directory_breadth_first(test_dir, &BASE_ARGS, &LffScanContext::default())
+                .unwrap();
+        assert_eq!(5, files.len());
+
+        // The order within each level isn't guaranteed, so we sort each level for comparison.
+        let mut top_level_names: Vec<&OsString> = files[..3].iter().map(|f| &f.name).collect();
+        top_level_names.sort();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt"
+            ],
+            top_level_names
+        );
+        let mut nested_names: Vec<&OsString> = files[3..].iter().map(|f| &f.name).collect();
+        nested_names.sort();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/visible/mud.md"
+            ],
+            nested_names
+        );
+    }
+
+    /// Ensure that when the finder is run with a limit and no sort, the limit is filled with
+    /// matching files even when many entries that don't match precede them, for both traversal
+    /// orders.
+    #[test]
+    fn test_run_finder_unordered_limit_filters() {
+        let test_dir: TempDir = tempdir().unwrap();
+        for i in 0..100 {
+            write(test_dir.path().join(format!("skipped{i}.log")), "lff").unwrap();
+            create_dir(test_dir.path().join(format!("empty{i}"))).unwrap();
+        }
+        create_dir_all(test_dir.path().join("nested")).unwrap();
+        write(test_dir.path().join("nested/first.txt"), "lff").unwrap();
+        write(test_dir.path().join("nested/second.txt"), "lff").unwrap();
+        write(test_dir.path().join("third.txt"), "lff").unwrap();
+
+        for breadth_first in [false, true] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                extension: Some(OsString::from("txt")),
+                breadth_first,
+                limit: Some(2),
+                unordered: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(2, outcome.count);
+            assert!(test_printer.0.iter().all(|line| line.ends_with(".txt\"")));
+        }
+    }
+
+    /// Ensure that when traversing breadth-first with a limit and no sort, traversal stops once
+    /// the limit is reached, preferring shallower files.
+    #[test]
+    fn test_scan_start_directory_breadth_first_limit() {
+        let test_args: &LffArgs = &LffArgs {
+            breadth_first: true,
+            limit: Some(2),
+            min_size_mib: 1.0 / MEBIBYTE as f64,
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let files: Vec<LffFile> = scan_start_directory(
+            Path::new("test_resources"),
+            test_args,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        // Only LICENCE and snow.txt are non-empty at the top level, so we never get to the
+        // larger files within the subdirectories.
+        let mut names: Vec<&OsString> = files.iter().map(|f| &f.name).collect();
+        names.sort();
+        assert_eq!(
+            vec!["test_resources/LICENCE", "test_resources/snow.txt"],
+            names
+        );
+    }
+
+    /// Ensure that the minimum size flag functions as expected.
+    #[test]
+    fn test_handle_directory_min_size() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            // 1 MiB / 1024 = 1 KiB.
+            min_size_mib: 1.0 / 1024.0,
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let spider_file: &LffFile = &files[0];
+        assert_eq!("test_resources/.hidden_dir/spider.txt", spider_file.name);
+        // We expect the one file returned to reach the size threshold.
+        assert_eq!(1183, spider_file.size);
+    }
+
+    /// Ensure that the minimum size in bytes flag is inclusive, and takes precedence over the
+    /// minimum size in MiB flag.
+    #[test]
+    fn test_handle_directory_min_size_bytes() {
+        let inclusive_args: &LffArgs = &LffArgs {
+            min_size_bytes: Some(1183),
+            // This would exclude every file, if it weren't overridden.
+            min_size_mib: 100.0,
+            ..BASE_ARGS
+        };
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, inclusive_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!("test_resources/.hidden_dir/spider.txt", files[0].name);
+
+        let exclusive_args: &LffArgs = &LffArgs {
+            min_size_bytes: Some(1184),
+            ..BASE_ARGS
+        };
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, exclusive_args, &LffScanContext::default()).unwrap();
+        assert!(files.is_empty());
+    }
+
+    /// Ensure that the maximum size in bytes flag is inclusive.
+    #[test]
+    fn test_handle_directory_max_size_bytes() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            max_size_bytes: Some(27),
+            ..BASE_ARGS
+        };
+        let mut files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(2, files.len());
+        assert_eq!("test_resources/.hidden", files[0].name);
+        assert_eq!("test_resources/LICENCE", files[1].name);
+    }
+
+    /// Ensure that the exclude larger than flag excludes the largest file, while keeping the
+    /// smaller ones.
+    #[test]
+    fn test_handle_directory_exclude_larger_than() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            exclude_larger_than: Some(parse_human_size("1K").unwrap()),
+            ..BASE_ARGS
+        };
+        let mut files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&OsString> = files.iter().map(|file| &file.name).collect();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md",
+            ],
+            names
+        );
+    }
+
+    /// Ensure that human-readable sizes are parsed into the correct number of bytes, and that
+    /// invalid sizes are rejected with the correct error messages.
+    #[test]
+    fn test_parse_human_size() {
+        assert_eq!(1183, parse_human_size("1183").unwrap());
+        assert_eq!(512, parse_human_size("512b").unwrap());
+        assert_eq!(500 * 1024, parse_human_size("500K").unwrap());
+        assert_eq!(1536 * 1024 * 1024, parse_human_size("1.5GiB").unwrap());
+        assert_eq!(10_000_000, parse_human_size(" 10 MB ").unwrap());
+        assert_eq!(2 * 1024 * 1024, parse_human_size("2mib").unwrap());
+        assert_eq!(1 << 40, parse_human_size("1T").unwrap());
+        assert_eq!(2_500, parse_human_size("2.5kb").unwrap());
+        assert_eq!(3_000_000_000, parse_human_size("3GB").unwrap());
+        assert_eq!(1_000_000_000_000, parse_human_size("1tb").unwrap());
+        assert_eq!(
+            "Invalid unit in size: '10 parsecs'",
+            parse_human_size("10 parsecs").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Invalid number in size: '-1K'",
+            parse_human_size("-1K").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Size is too large: '20000000T'",
+            parse_human_size("20000000T").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Size is too large: '99999999999999999999.5'",
+            parse_human_size("99999999999999999999.5")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    /// Ensure that human-readable durations are parsed in the supported units, and that the correct
+    /// error messages are generated for invalid durations.
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Duration::from_secs(30), parse_duration("30").unwrap());
+        assert_eq!(Duration::from_secs(30), parse_duration("30s").unwrap());
+        assert_eq!(Duration::from_millis(500), parse_duration("500ms").unwrap());
+        assert_eq!(Duration::from_secs(90), parse_duration(" 1.5M ").unwrap());
+        assert_eq!(Duration::from_secs(7200), parse_duration("2h").unwrap());
+        assert_eq!(Duration::from_secs(172800), parse_duration("48h").unwrap());
+        assert_eq!(Duration::from_secs(2592000), parse_duration("30d").unwrap());
+        assert_eq!(Duration::from_secs(1209600), parse_duration("2w").unwrap());
+        assert_eq!(
+            Duration::from_secs(15552000),
+            parse_duration("6MO").unwrap()
+        );
+        assert_eq!(Duration::from_secs(63072000), parse_duration("2y").unwrap());
+        assert_eq!(
+            "Invalid unit in duration: '5x'",
+            parse_duration("5x").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Invalid unit in duration: '3 days'",
+            parse_duration("3 days").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Invalid number in duration: '-5s'",
+            parse_duration("-5s").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Duration is too long: '99999999999999999999h'",
+            parse_duration("99999999999999999999h")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    /// Ensure that when recursion is disabled, only the files directly within the start directory
+    /// are found, for both traversal orders.
+    #[test]
+    fn test_handle_directory_no_recurse() {
+        for traversal in [handle_directory, handle_directory_breadth_first] {
+            let test_dir: ReadDir = read_dir("test_resources").unwrap();
+            let test_args: &LffArgs = &LffArgs {
+                no_recurse: true,
+                ..BASE_ARGS
+            };
+            let mut files: Vec<LffFile> =
+                traversal(test_dir, test_args, &LffScanContext::default()).unwrap();
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+            let names: Vec<&OsString> = files.iter().map(|file| &file.name).collect();
+            assert_eq!(
+                vec![
+                    "test_resources/.hidden",
+                    "test_resources/LICENCE",
+                    "test_resources/snow.txt",
+                ],
+                names
+            );
+        }
+    }
+
+    /// Ensure that the extension filter flag functions as expected.
+    #[test]
+    fn test_handle_directory_extension() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            extension: Some(OsString::from("md")),
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let mud_file: &LffFile = &files[0];
+        assert_eq!("test_resources/visible/mud.md", mud_file.name);
+        // We expect the one file returned to have the md extension.
+        assert_eq!(Some(OsString::from("md")), mud_file.extension);
+    }
+
+    /// Ensure that the exclude empty flag excludes empty files, even with a minimum size of zero.
+    #[test]
+    fn test_handle_directory_exclude_empty() {
+        let test_args: &LffArgs = &LffArgs {
+            exclude_empty: true,
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> = handle_directory(
+            read_dir("test_resources").unwrap(),
+            test_args,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        let mut names: Vec<&OsStr> = files.iter().map(|file| file.name.as_os_str()).collect();
+        names.sort();
+        // Only the empty .hidden file should be missing.
+        assert_eq!(
+            vec![
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md"
+            ],
+            names
+        );
+    }
+
+    /// Ensure that the exclude extension flag functions as expected, taking precedence over the
+    /// extension filter.
+    #[test]
+    fn test_handle_directory_exclude_extension() {
+        let test_args: &LffArgs = &LffArgs {
+            exclude_extensions: vec![OsString::from("txt")],
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> = handle_directory(
+            read_dir("test_resources").unwrap(),
+            test_args,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        let mut names: Vec<&OsStr> = files.iter().map(|file| file.name.as_os_str()).collect();
+        names.sort();
+        // Neither of the txt files should be present, but mud.md should be.
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/LICENCE",
+                "test_resources/visible/mud.md"
+            ],
+            names
+        );
+
+        let conflicting_args: &LffArgs = &LffArgs {
+            extension: Some(OsString::from("txt")),
+            exclude_extensions: vec![OsString::from("txt")],
+            ..BASE_ARGS
+        };
+        let conflicting_files: Vec<LffFile> = handle_directory(
+            read_dir("test_resources").unwrap(),
+            conflicting_args,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        assert!(conflicting_files.is_empty());
+    }
+
+    /// Ensure that when the finder is run with extensions supplied with or without a leading dot,
+    /// they match the same files.
+    #[test]
+    fn test_run_finder_extension_dot() {
+        for extension in ["txt", ".txt"] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                extension: Some(OsString::from(extension)),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(2, outcome.count);
+            assert!(test_printer.0.iter().all(|line| line.ends_with(".txt\"")));
+
+            let exclude_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                exclude_extensions: vec![OsString::from(extension)],
+                ..BASE_ARGS
+            };
+            let mut exclude_printer: LffTestPrinter = LffTestPrinter::default();
+            assert_eq!(
+                3,
+                run_finder!(exclude_args, &mut exclude_printer)
+                    .unwrap()
+                    .count
+            );
+        }
+        assert_eq!(
+            OsString::from(".txt"),
+            strip_extension_dot(OsString::from("..txt"))
+        );
+        assert_eq!(
+            OsString::from("txt"),
+            strip_extension_dot(OsString::from("txt"))
+        );
+    }
+
+    /// Ensure that the name pattern filter flag functions as expected.
+    #[test]
+    fn test_handle_directory_name_pattern() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            name_pattern: Some(String::from("*no*")),
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let snow_file: &LffFile = &files[0];
+        // We expect the one file returned to match the *no* glob.
+        assert_eq!("test_resources/snow.txt", snow_file.name);
+    }
+
+    /// Ensure that name patterns are matched against file names alone by default, and against whole
+    /// paths with the match full path flag.
+    #[test]
+    fn test_handle_directory_name_pattern_full_path() {
+        for (pattern, match_full_path, expected_names) in [
+            ("snow*", false, vec!["test_resources/snow.txt"]),
+            ("snow*", true, vec![]),
+            ("*visible*", false, vec![]),
+            ("*visible*", true, vec!["test_resources/visible/mud.md"]),
+            ("test_resources/s*", true, vec!["test_resources/snow.txt"]),
+        ] {
+            let test_args: &LffArgs = &LffArgs {
+                name_pattern: Some(String::from(pattern)),
+                match_full_path,
+                ..BASE_ARGS
+            };
+            let files: Vec<LffFile> = handle_directory(
+                read_dir("test_resources").unwrap(),
+                test_args,
+                &LffScanContext::default(),
+            )
+            .unwrap();
+            let names: Vec<&OsStr> = files.iter().map(|file| file.name.as_os_str()).collect();
+            assert_eq!(expected_names, names);
+        }
+
+        let parse_error: String =
+            LffArgs::try_parse_from(["lff", "--match-full-path", "test_resources"])
+                .err()
+                .unwrap()
+                .to_string();
+        assert!(parse_error.contains("--name-pattern <NAME_PATTERN>"));
+    }
+
+    /// Ensure that the name pattern filter flag matches case-insensitively when the ignore case
+    /// flag is passed.
+    #[test]
+    fn test_handle_directory_name_pattern_ignore_case() {
+        let case_sensitive_args: &LffArgs = &LffArgs {
+            name_pattern: Some(String::from("*licence")),
+            ..BASE_ARGS
+        };
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, case_sensitive_args, &LffScanContext::default()).unwrap();
+        assert!(files.is_empty());
+
+        let case_insensitive_args: &LffArgs = &LffArgs {
+            ignore_case: true,
+            name_pattern: Some(String::from("*licence")),
+            ..BASE_ARGS
+        };
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, case_insensitive_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!("test_resources/LICENCE", files[0].name);
+    }
+
+    /// Ensure that the case sensitive flag matches extensions and name patterns exactly, taking
+    /// precedence over the ignore case flag, and that the two flags can't be combined on the
+    /// command line.
+    #[test]
+    fn test_handle_directory_case_sensitive() {
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("loud.TXT"), "lff").unwrap();
+        write(test_dir.path().join("quiet.txt"), "lff").unwrap();
+        for test_args in [
+            LffArgs {
+                case_sensitive: true,
+                extension: Some(OsString::from("txt")),
+                ..BASE_ARGS
+            },
+            LffArgs {
+                case_sensitive: true,
+                ignore_case: true,
+                name_pattern: Some(String::from("*.txt")),
+                ..BASE_ARGS
+            },
+        ] {
+            let files: Vec<LffFile> = handle_directory(
+                read_dir(test_dir.path()).unwrap(),
+                &test_args,
+                &LffScanContext::default(),
+            )
+            .unwrap();
+            assert_eq!(1, files.len());
+            assert_eq!(test_dir.path().join("quiet.txt"), files[0].name);
+        }
+
+        let parse_error: String =
+            LffArgs::try_parse_from(["lff", "--case-sensitive", "--ignore-case", "test_resources"])
+                .err()
+                .unwrap()
+                .to_string();
+        assert!(parse_error
+            .contains("the argument '--case-sensitive' cannot be used with '--ignore-case'"));
+    }
+
+    /// Ensure that the regex pattern filter flag functions as expected.
+    #[test]
+    fn test_handle_directory_regex_pattern() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            regex_pattern: Some(parse_regex(r"snow\.txt$").unwrap()),
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!("test_resources/snow.txt", files[0].name);
+    }
+
+    /// Ensure that the correct error message is generated when an invalid regex pattern is
+    /// supplied.
+    #[test]
+    fn test_parse_regex_invalid() {
+        let regex_error: Report = parse_regex("[").unwrap_err();
+        assert_eq!(
+            "Invalid regex from regex pattern flag: '['",
+            regex_error.to_string()
+        );
+    }
+
+    /// Ensure that the correct error message is generated when an invalid glob pattern is supplied
+    /// as the name pattern filter flag.
+    #[test]
+    fn test_handle_directory_invalid_name_pattern() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            name_pattern: Some(String::from("[")),
+            ..BASE_ARGS
+        };
+        let new_glob_error: Report =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap_err();
+        assert_eq!(
+            "Invalid glob from name pattern flag: '['",
+            new_glob_error.to_string()
+        );
+    }
+
+    /// Ensure that directories matching the filters are included in the output when the include
+    /// directories flag is passed.
+    #[test]
+    fn test_handle_directory_include_dirs() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            include_dirs: true,
+            name_pattern: Some(String::from("*visible")),
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let visible_dir: &LffFile = &files[0];
+        assert_eq!("test_resources/visible", visible_dir.name);
+        assert!(visible_dir.is_dir);
+        // Directories are displayed with a trailing separator.
+        assert_eq!(
+            format!("{}  \"test_resources/visible/\"", visible_dir.size),
+            format_file_line(visible_dir, 0, 0, test_args)
+        );
+    }
+
+    /// Ensure that the exclude hidden flag functions as expected, excluding both hidden files and
+    /// hidden directories.
+    #[test]
+    fn test_handle_directory_exclude_hidden() {
+        let test_dir: ReadDir = read_dir("test_resources").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            exclude_hidden: true,
+            // This pattern would match mud.md and .hidden, but since we're excluding hidden files
+            // and directories, we only expect mud.md to be yielded.
+            name_pattern: Some(String::from("*d*")),
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            handle_directory(test_dir, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let mud_file: &LffFile = &files[0];
+        // We expect the one file returned to not be hidden.
+        assert_eq!("test_resources/visible/mud.md", mud_file.name);
+        assert!(!mud_file.hidden);
+    }
+
+    /// Ensure that when the finder is run with the dereference count flag, broken symlinks are
+    /// listed with their count after the found files, while working symlinks are not.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_dereference_count() {
+        use std::os::unix::fs::symlink;
+
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("target.txt"), "lff").unwrap();
+        symlink(
+            test_dir.path().join("target.txt"),
+            test_dir.path().join("working_link"),
+        )
+        .unwrap();
+        symlink(
+            test_dir.path().join("missing.txt"),
+            test_dir.path().join("broken_link"),
+        )
+        .unwrap();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            dereference_count: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(
+            vec![
+                format!("3  {:?}", test_dir.path().join("target.txt")),
+                String::from("Broken symlinks: 1"),
+                format!("  {:?}", test_dir.path().join("broken_link")),
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with the warn unreadable flag, directories that can't be
+    /// read are listed in a warning, and the rest of the scan is unaffected.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_warn_unreadable() {
+        use std::fs::{set_permissions, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_dir: TempDir = create_test_tree(2, 2);
+        let locked_dir: PathBuf = test_dir.path().join("subdir0");
+        set_permissions(&locked_dir, Permissions::from_mode(0o000)).unwrap();
+        // Permissions aren't enforced for privileged users, so there's nothing to test.
+        if read_dir(&locked_dir).is_ok() {
+            return;
+        }
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            warn_unreadable: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let finder_result: eyre::Result<LffScanOutcome> = run_finder!(test_args, &mut test_printer);
+        set_permissions(&locked_dir, Permissions::from_mode(0o755)).unwrap();
+        finder_result.unwrap();
+        assert_eq!(2, test_printer.0.len());
+        assert_eq!(
+            vec![
+                String::from(
+                    "Warning: 1 director(ies) could not be read, so results may be incomplete:"
+                ),
+                format!("  {:?}", locked_dir),
+            ],
+            test_printer.1
+        );
+    }
+
+    /// Ensure that a warning is logged when a directory can't be read, even though the scan
+    /// continues.
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_start_directory_logs_unreadable() {
+        use std::fs::{set_permissions, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_dir: TempDir = create_test_tree(2, 2);
+        let locked_dir: PathBuf = test_dir.path().join("subdir1");
+        set_permissions(&locked_dir, Permissions::from_mode(0o000)).unwrap();
+        // Permissions aren't enforced for privileged users, so there's nothing to test.
+        if read_dir(&locked_dir).is_ok() {
+            return;
+        }
+        install_test_logger();
+
+        let scan_result: eyre::Result<Vec<LffFile>> =
+            scan_start_directory(test_dir.path(), &BASE_ARGS, &LffScanContext::default());
+        set_permissions(&locked_dir, Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(2, scan_result.unwrap().len());
+        let expected_prefix: String = format!("Skipping unreadable directory {:?}: ", locked_dir);
+        assert!(
+            LOGGED_MESSAGES
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(level, message)| *level == Level::Warn
+                    && message.starts_with(&expected_prefix))
+        );
+    }
+
+    /// Ensure that when the finder is run with the into archives flag, the entries within zip
+    /// archives are output with their uncompressed sizes alongside the archives themselves, that
+    /// directory entries are skipped, and that corrupt archives are skipped with a warning.
+    #[test]
+    fn test_run_finder_into_archives() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+        use zip::{CompressionMethod, ZipWriter};
+
+        let test_dir: TempDir = tempdir().unwrap();
+        let archive_path: PathBuf = test_dir.path().join("assets.zip");
+        let mut writer: ZipWriter<File> = ZipWriter::new(File::create(&archive_path).unwrap());
+        let options: SimpleFileOptions =
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("large.bin", options).unwrap();
+        writer.write_all(&[0; 10000]).unwrap();
+        writer.add_directory("docs/", options).unwrap();
+        writer.start_file("docs/readme.txt", options).unwrap();
+        writer.write_all(b"lfflfflfflff").unwrap();
+        writer.finish().unwrap();
+        let corrupt_path: PathBuf = test_dir.path().join("corrupt.zip");
+        write(&corrupt_path, "lff").unwrap();
+        install_test_logger();
+
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(
+            LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                format: OutputFormat::Path,
+                into_archives: true,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(4, outcome.count);
+        assert_eq!(
+            vec![
+                archive_path.to_string_lossy().into_owned(),
+                format!("{}!large.bin", archive_path.to_string_lossy()),
+                format!("{}!docs/readme.txt", archive_path.to_string_lossy()),
+                corrupt_path.to_string_lossy().into_owned(),
+            ],
+            test_printer.0
+        );
+        let expected_prefix: String = format!("Skipping unreadable archive {:?}: ", corrupt_path);
+        assert!(
+            LOGGED_MESSAGES
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(level, message)| *level == Level::Warn
+                    && message.starts_with(&expected_prefix))
+        );
+
+        // Entries are subject to the same filters as files, and archives are still searched within
+        // when they don't match themselves.
+        let mut filtered_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                extension: Some(OsString::from("bin")),
+                format: OutputFormat::Text,
+                into_archives: true,
+                ..BASE_ARGS
+            },
+            &mut filtered_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![format!(
+                "10000  \"{}!large.bin\"",
+                archive_path.to_string_lossy()
+            )],
+            filtered_printer.0
+        );
+
+        // Each archive has its own per-directory limit, like a directory.
+        let mut limited_printer: LffTestPrinter = LffTestPrinter::default();
+        let limited_outcome: LffScanOutcome = run_finder!(
+            LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                format: OutputFormat::Path,
+                into_archives: true,
+                limit_per_dir: Some(1),
+                ..BASE_ARGS
+            },
+            &mut limited_printer
+        )
+        .unwrap();
+        assert_eq!(2, limited_outcome.count);
+        assert!(limited_printer
+            .0
+            .contains(&format!("{}!large.bin", archive_path.to_string_lossy())));
+
+        // Archive entries can't be acted upon.
+        assert!(
+            LffArgs::try_parse_from(["lff", "--into-archives", "--exec", "ls {}", "."]).is_err()
+        );
+    }
+
+    /// Ensure that when the finder is run with a minimum number of files per directory, only the
+    /// directories directly containing at least that many files are output, for both traversal
+    /// orders.
+    #[test]
+    fn test_run_finder_min_files_in_dir() {
+        let test_dir: TempDir = create_test_tree(2, 2);
+        let crowded_dir: PathBuf = test_dir.path().join("subdir1");
+        for file_idx in 2..5 {
+            write(crowded_dir.join(format!("extra{}.txt", file_idx)), "").unwrap();
+        }
+        for breadth_first in [false, true] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                breadth_first,
+                format: OutputFormat::Path,
+                min_files_in_dir: Some(3),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(1, outcome.count);
+            assert_eq!(vec![crowded_dir.to_string_lossy()], test_printer.0);
+        }
+    }
+
+    /// Ensure that when the finder is run with the prune empty directories flag, only the outermost
+    /// directories without any files within them are output, for both traversal orders, and that
+    /// directories which aren't searched are assumed not to be empty.
+    #[test]
+    fn test_run_finder_prune_empty_dirs() {
+        let test_dir: TempDir = tempdir().unwrap();
+        create_dir_all(test_dir.path().join("full/empty/deeper")).unwrap();
+        create_dir_all(test_dir.path().join("guarded/.hidden")).unwrap();
+        write(test_dir.path().join("full/file.txt"), "lff").unwrap();
+        create_dir_all(test_dir.path().join("hollow/deeper")).unwrap();
+        for breadth_first in [false, true] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                breadth_first,
+                exclude_hidden: true,
+                format: OutputFormat::Path,
+                prune_empty_dirs: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(2, outcome.count);
+            assert_eq!(
+                vec![
+                    test_dir.path().join("full/empty").to_string_lossy(),
+                    test_dir.path().join("hollow").to_string_lossy(),
+                ],
+                test_printer.0
+            );
+        }
+
+        // A start directory without any files is itself empty.
+        let empty_dir: TempDir = tempdir().unwrap();
+        let empty_args: LffArgs = LffArgs {
+            directories: vec![empty_dir.path().to_string_lossy().into_owned()],
+            format: OutputFormat::Path,
+            prune_empty_dirs: true,
+            ..BASE_ARGS
+        };
+        let mut empty_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(empty_args, &mut empty_printer).unwrap();
+        assert_eq!(vec![empty_dir.path().to_string_lossy()], empty_printer.0);
+    }
+
+    /// Ensure that the per-directory limit yields exactly one file from each directory with
+    /// matching files, for both traversal orders.
+    #[test]
+    fn test_handle_directory_limit_per_dir() {
+        for traversal in [handle_directory, handle_directory_breadth_first] {
+            let test_dir: ReadDir = read_dir("test_resources").unwrap();
+            let test_args: &LffArgs = &LffArgs {
+                limit_per_dir: Some(1),
+                ..BASE_ARGS
+            };
+
+            let files: Vec<LffFile> =
+                traversal(test_dir, test_args, &LffScanContext::default()).unwrap();
+            let mut parents: Vec<&Path> = files
+                .iter()
+                .map(|file| Path::new(&file.name).parent().unwrap())
+                .collect();
+            parents.sort();
+            assert_eq!(
+                vec![
+                    Path::new("test_resources"),
+                    Path::new("test_resources/.hidden_dir"),
+                    Path::new("test_resources/visible"),
+                ],
+                parents
+            );
+        }
+    }
+
+    /// Ensure that the hidden only flag functions as expected, yielding both hidden files and files
+    /// within hidden directories, for both traversal orders.
+    #[test]
+    fn test_handle_directory_hidden_only() {
+        for traversal in [handle_directory, handle_directory_breadth_first] {
+            let test_dir: ReadDir = read_dir("test_resources").unwrap();
+            let test_args: &LffArgs = &LffArgs {
+                hidden_only: true,
+                ..BASE_ARGS
+            };
+
+            let mut files: Vec<LffFile> =
+                traversal(test_dir, test_args, &LffScanContext::default()).unwrap();
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+            assert_eq!(2, files.len());
+            assert_eq!("test_resources/.hidden", files[0].name);
+            assert_eq!("test_resources/.hidden_dir/spider.txt", files[1].name);
+        }
+    }
+
+    /// Ensure that directories are only considered to be on the same file system as the start
+    /// directory when their device IDs match.
+    #[test]
+    fn test_is_same_device() {
+        assert!(is_same_device(42, 42));
+        assert!(!is_same_device(42, 43));
+    }
+
+    /// Ensure that directories are only considered to be across a mount point when the scan is
+    /// restricted to a single file system and they're on another device, and that symlinked
+    /// directories are judged by their targets.
+    #[test]
+    #[cfg(unix)]
+    fn test_is_across_mount() {
+        use std::fs::metadata;
+        use std::os::unix::fs::MetadataExt;
+
+        let test_dir: TempDir = tempdir().unwrap();
+        let link_path: PathBuf = test_dir.path().join("link");
+        std::os::unix::fs::symlink(
+            Path::new("test_resources/visible").canonicalize().unwrap(),
+            &link_path,
+        )
+        .unwrap();
+        let resources_device: u64 = metadata("test_resources").unwrap().dev();
+
+        assert!(!is_across_mount(None, Path::new("test_resources/missing")).unwrap());
+        assert!(
+            !is_across_mount(Some(resources_device), Path::new("test_resources/visible")).unwrap()
+        );
+        assert!(is_across_mount(
+            Some(resources_device + 1),
+            Path::new("test_resources/visible")
+        )
+        .unwrap());
+        assert!(!is_across_mount(Some(resources_device), &link_path).unwrap());
+        assert!(
+            is_across_mount(Some(resources_device), Path::new("test_resources/missing")).is_err()
+        );
+    }
+
+    /// Ensure that --no-cross-mounts is accepted as an alias for --one-file-system.
+    #[test]
+    fn test_no_cross_mounts_alias() {
+        let parsed_args: LffArgs =
+            LffArgs::try_parse_from(["lff", "--no-cross-mounts", "test_resources"]).unwrap();
+        assert!(parsed_args.one_file_system);
+    }
+
+    /// Ensure that when the scan is restricted to a single file system, all files on that file
+    /// system are still found.
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_start_directory_one_file_system() {
+        let test_args: &LffArgs = &LffArgs {
+            one_file_system: true,
+            ..BASE_ARGS
+        };
+        let files: Vec<LffFile> = scan_start_directory(
+            Path::new("test_resources"),
+            test_args,
+            &LffScanContext::default(),
+        )
+        .unwrap();
+        assert_eq!(5, files.len());
+    }
+
+    /// Ensure that newline-separated paths are correctly read from input, skipping empty lines.
+    #[test]
+    fn test_read_input_paths() {
+        let mut test_input: Cursor<&str> =
+            Cursor::new("test_resources/snow.txt\n\ntest_resources/visible\n");
+        let paths: Vec<PathBuf> = read_input_paths(&mut test_input, false).unwrap();
+        assert_eq!(
+            vec![
+                PathBuf::from("test_resources/snow.txt"),
+                PathBuf::from("test_resources/visible")
+            ],
+            paths
+        );
+    }
+
+    /// Ensure that when the finder is run with a paths file, the directories listed within it are
+    /// searched, skipping blank lines and comments, and that the correct error messages are
+    /// generated when the file or a listed directory is missing.
+    #[test]
+    fn test_run_finder_paths_from_file() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let paths_file: PathBuf = test_dir.path().join("dirs.txt");
+        write(
+            &paths_file,
+            "# Directories to search\ntest_resources/.hidden_dir\n\n  test_resources/visible  \n",
+        )
+        .unwrap();
+        let test_args: LffArgs = LffArgs {
+            paths_from_file: Some(paths_file.clone()),
+            format: OutputFormat::Path,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert_eq!(
+            vec![
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/visible/mud.md",
+            ],
+            test_printer.0
+        );
+
+        let missing_file: PathBuf = test_dir.path().join("missing.txt");
+        let missing_file_args: LffArgs = LffArgs {
+            paths_from_file: Some(missing_file.clone()),
+            ..BASE_ARGS
+        };
+        let missing_file_error: Report =
+            run_finder!(missing_file_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            format!("Could not read paths from file {:?}", missing_file),
+            missing_file_error.to_string()
+        );
+
+        write(&paths_file, "does_not_exist\n").unwrap();
+        let missing_dir_args: LffArgs = LffArgs {
+            paths_from_file: Some(paths_file),
+            ..BASE_ARGS
+        };
+        let missing_dir_error: Report =
+            run_finder!(missing_dir_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            "Invalid supplied start directory: 'does_not_exist'",
+            missing_dir_error.to_string()
+        );
+    }
+
+    /// Ensure that NUL-separated paths are correctly read from input, with newlines treated as
+    /// part of the paths.
+    #[test]
+    fn test_read_input_paths_null_separated() {
+        let mut test_input: Cursor<&str> = Cursor::new("snow\n.txt\0LICENCE\0");
+        let paths: Vec<PathBuf> = read_input_paths(&mut test_input, true).unwrap();
+        assert_eq!(
+            vec![PathBuf::from("snow\n.txt"), PathBuf::from("LICENCE")],
+            paths
+        );
+    }
+
+    /// Ensure that when scanning input paths, files are checked directly and directories are
+    /// scanned recursively, with both subject to the filters.
+    #[test]
+    fn test_scan_input_paths() {
+        let test_paths: Vec<PathBuf> = vec![
+            PathBuf::from("test_resources/snow.txt"),
+            PathBuf::from("test_resources/LICENCE"),
+            PathBuf::from("test_resources/visible"),
+        ];
+        let test_args: &LffArgs = &LffArgs {
+            // Only snow.txt and mud.md are large enough.
+            min_size_mib: 100.0 / MEBIBYTE as f64,
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            scan_input_paths(&test_paths, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(2, files.len());
+        assert_eq!("test_resources/snow.txt", files[0].name);
+        assert_eq!("test_resources/visible/mud.md", files[1].name);
+
+        // Once the scan is cancelled, no more paths are scanned.
+        let cancelled: AtomicBool = AtomicBool::new(true);
+        let cancelled_context: LffScanContext = LffScanContext {
+            cancelled: Some(&cancelled),
+            ..LffScanContext::default()
+        };
+        assert!(scan_input_paths(&test_paths, test_args, &cancelled_context)
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Ensure that when scanning input paths with the stdin filter flag, only the files are checked
+    /// against the filters, and directories are ignored.
+    #[test]
+    fn test_scan_input_paths_stdin_filter() {
+        let test_paths: Vec<PathBuf> = vec![
+            PathBuf::from("test_resources/.hidden_dir/spider.txt"),
+            PathBuf::from("test_resources/snow.txt"),
+            PathBuf::from("test_resources/LICENCE"),
+            PathBuf::from("test_resources/visible"),
+        ];
+        let test_args: &LffArgs = &LffArgs {
+            // Only spider.txt, snow.txt, and mud.md are large enough, and mud.md isn't listed.
+            min_size_mib: 100.0 / MEBIBYTE as f64,
+            stdin_filter: true,
+            ..BASE_ARGS
+        };
+
+        let files: Vec<LffFile> =
+            scan_input_paths(&test_paths, test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(2, files.len());
+        assert_eq!("test_resources/.hidden_dir/spider.txt", files[0].name);
+        assert_eq!("test_resources/snow.txt", files[1].name);
+    }
+
+    /// Ensure that the correct statistics are recorded when scanning a start directory.
+    #[test]
+    fn test_scan_start_directory_stats() {
+        let test_args: &LffArgs = &LffArgs {
+            exclude_hidden: true,
+            // 1 MiB / 1024 = 1 KiB.
+            min_size_mib: 1.0 / 1024.0,
+            ..BASE_ARGS
+        };
+        let stats: LffStats = LffStats::default();
+        let files: Vec<LffFile> = scan_start_directory(
+            Path::new("test_resources"),
+            test_args,
+            &LffScanContext {
+                stats: Some(&stats),
+                ..LffScanContext::default()
+            },
+        )
+        .unwrap();
+        assert!(files.is_empty());
+
+        // The hidden directory is never traversed, so spider.txt is never examined. The .hidden
+        // file is too small, and too small takes precedence over hidden.
+        assert_eq!(4, stats.files_examined.load(Ordering::Relaxed));
+        assert_eq!(2, stats.dirs_traversed.load(Ordering::Relaxed));
+        assert_eq!(4, stats.too_small.load(Ordering::Relaxed));
+        assert_eq!(0, stats.too_large.load(Ordering::Relaxed));
+        assert_eq!(0, stats.wrong_extension.load(Ordering::Relaxed));
+        assert_eq!(0, stats.wrong_name.load(Ordering::Relaxed));
+        assert_eq!(0, stats.hidden.load(Ordering::Relaxed));
+    }
+
+    /// Ensure that each rejection reason is recorded against the correct counter.
+    #[test]
+    fn test_stats_record_file() {
+        let stats: LffStats = LffStats::default();
+        stats.record_file(None);
+        stats.record_file(Some(LffRejection::TooSmall));
+        stats.record_file(Some(LffRejection::TooLarge));
+        stats.record_file(Some(LffRejection::WrongExtension));
+        stats.record_file(Some(LffRejection::WrongName));
+        stats.record_file(Some(LffRejection::Hidden));
+        stats.record_file(Some(LffRejection::NotHidden));
+        stats.record_file(Some(LffRejection::TooOld));
+        stats.record_file(Some(LffRejection::TooNew));
+        assert_eq!(9, stats.files_examined.load(Ordering::Relaxed));
+        assert_eq!(1, stats.too_small.load(Ordering::Relaxed));
+        assert_eq!(1, stats.too_large.load(Ordering::Relaxed));
+        assert_eq!(1, stats.wrong_extension.load(Ordering::Relaxed));
+        assert_eq!(1, stats.wrong_name.load(Ordering::Relaxed));
+        assert_eq!(1, stats.hidden.load(Ordering::Relaxed));
+        assert_eq!(1, stats.not_hidden.load(Ordering::Relaxed));
+        assert_eq!(1, stats.too_old.load(Ordering::Relaxed));
+        assert_eq!(1, stats.too_new.load(Ordering::Relaxed));
+    }
+
+    /// Ensure that each rejection reason is described correctly for diagnostic output.
+    #[test]
+    fn test_rejection_display() {
+        let reasons: Vec<String> = [
+            LffRejection::TooSmall,
+            LffRejection::TooLarge,
+            LffRejection::WrongExtension,
+            LffRejection::WrongName,
+            LffRejection::Hidden,
+            LffRejection::NotHidden,
+            LffRejection::TooOld,
+            LffRejection::TooNew,
+        ]
+        .iter()
+        .map(|rejection| rejection.to_string())
+        .collect();
+        assert_eq!(
+            vec![
+                "too small",
+                "too large",
+                "wrong extension",
+                "wrong name",
+                "hidden",
+                "not hidden",
+                "too old",
+                "too new",
+            ],
+            reasons
+        );
+    }
+
+    /// Ensure that when the finder is run with the stats flag, the statistics are output as
+    /// diagnostics after the found files.
+    #[test]
+    fn test_run_finder_stats() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            extension: Some(OsString::from("md")),
+            stats: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(1, test_printer.0.len());
+        assert_eq!(11, test_printer.1.len());
+        assert_eq!("Files examined: 5", test_printer.1[0]);
+        assert_eq!("Directories traversed: 3", test_printer.1[1]);
+        assert_eq!("Files skipped (wrong extension): 4", test_printer.1[4]);
+        assert!(test_printer.1[10].starts_with("Elapsed time: "));
+    }
+
+    /// Ensure that when the finder is run with no output format, nothing is output other than any
+    /// statistics, even when no files are found, but the files are still counted.
+    #[test]
+    fn test_run_finder_format_none() {
+        for (min_size_mib, expected_count) in [(0.0, 5), (1.0, 0)] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format: OutputFormat::None,
+                min_size_mib,
+                stats: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(expected_count, outcome.count);
+            assert!(test_printer.0.is_empty());
+            assert_eq!("Files examined: 5", test_printer.1[0]);
+            assert!(test_printer.1.last().unwrap().starts_with("Elapsed time: "));
+        }
+    }
+
+    /// Create a temporary directory tree containing the given number of subdirectories, each
+    /// containing the given number of files of varying sizes.
+    fn create_test_tree(subdirs: usize, files_per_subdir: usize) -> TempDir {
+        let tree: TempDir = tempdir().unwrap();
+        for subdir_idx in 0..subdirs {
+            let subdir: PathBuf = tree.path().join(format!("subdir{}", subdir_idx));
+            create_dir(&subdir).unwrap();
+            for file_idx in 0..files_per_subdir {
+                let contents: Vec<u8> = vec![0; subdir_idx * files_per_subdir + file_idx];
+                write(subdir.join(format!("file{}.bin", file_idx)), contents).unwrap();
+            }
+        }
+        tree
+    }
+
+    /// Ensure that when following only top-level symlinks, a symlinked directory directly within
+    /// the start directory is followed, but a nested one is not, for both traversal orders.
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_start_directory_follow_only_top_level_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let target_tree: TempDir = create_test_tree(2, 1);
+        let start_dir: TempDir = tempdir().unwrap();
+        symlink(
+            target_tree.path().join("subdir0"),
+            start_dir.path().join("top_link"),
+        )
+        .unwrap();
+        create_dir(start_dir.path().join("nested")).unwrap();
+        symlink(
+            target_tree.path().join("subdir1"),
+            start_dir.path().join("nested").join("deep_link"),
+        )
+        .unwrap();
+
+        for (follow_only_top_level_symlinks, breadth_first, expected_files) in [
+            (false, false, vec![]),
+            (
+                true,
+                false,
+                vec![start_dir.path().join("top_link/file0.bin")],
+            ),
+            (
+                true,
+                true,
+                vec![start_dir.path().join("top_link/file0.bin")],
+            ),
+        ] {
+            let test_args: &LffArgs = &LffArgs {
+                breadth_first,
+                follow_only_top_level_symlinks,
+                ..BASE_ARGS
+            };
+            let files: Vec<LffFile> =
+                scan_start_directory(start_dir.path(), test_args, &LffScanContext::default())
+                    .unwrap();
+            let paths: Vec<PathBuf> = files.iter().map(|file| PathBuf::from(&file.name)).collect();
+            assert_eq!(expected_files, paths);
+        }
+    }
+
+    /// Ensure that when respecting ignore files, files and directories matching the patterns in
+    /// the ignore files of their directory or its parents are skipped, for both traversal orders.
+    #[test]
+    fn test_scan_start_directory_respect_ignore_files() {
+        let test_dir: TempDir = tempdir().unwrap();
+        for dir_name in ["sub", "other", "skipped_dir"] {
+            create_dir(test_dir.path().join(dir_name)).unwrap();
+        }
+        for (file_name, contents) in [
+            (
+                ".lffignore",
+                "# Comments and blank lines are skipped.\n\n*.md\nskipped_dir\n",
+            ),
+            ("a.md", "lff"),
+            ("a.txt", "lff"),
+            ("sub/.lffignore", "c.txt"),
+            ("sub/b.md", "lff"),
+            ("sub/c.txt", "lff"),
+            ("other/c.txt", "lff"),
+            ("skipped_dir/d.txt", "lff"),
+        ] {
+            write(test_dir.path().join(file_name), contents).unwrap();
+        }
+
+        for (respect_ignore_files, breadth_first, expected_count) in
+            [(false, false, 8), (true, false, 4), (true, true, 4)]
+        {
+            let test_args: &LffArgs = &LffArgs {
+                breadth_first,
+                respect_ignore_files,
+                ..BASE_ARGS
+            };
+            let files: Vec<LffFile> =
+                scan_start_directory(test_dir.path(), test_args, &LffScanContext::default())
+                    .unwrap();
+            let mut relative_paths: Vec<&Path> = files
+                .iter()
+                .map(|file| Path::new(&file.name).strip_prefix(test_dir.path()).unwrap())
+                .collect();
+            relative_paths.sort();
+            assert_eq!(expected_count, relative_paths.len());
+            if respect_ignore_files {
+                assert_eq!(
+                    vec![
+                        Path::new(".lffignore"),
+                        Path::new("a.txt"),
+                        Path::new("other/c.txt"),
+                        Path::new("sub/.lffignore"),
+                    ],
+                    relative_paths
+                );
+            }
+        }
+    }
+
+    /// Ensure that the correct error message is generated when an ignore file contains an invalid
+    /// glob pattern, and that an ignore file that can't be read is skipped with a warning.
+    #[test]
+    fn test_scan_start_directory_invalid_ignore_file() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let ignore_path: PathBuf = test_dir.path().join(".lffignore");
+        write(&ignore_path, "[").unwrap();
+        let test_args: &LffArgs = &LffArgs {
+            respect_ignore_files: true,
+            ..BASE_ARGS
+        };
+
+        let ignore_error: Report =
+            scan_start_directory(test_dir.path(), test_args, &LffScanContext::default())
+                .unwrap_err();
+        assert_eq!(
+            format!("Invalid glob in ignore file {:?}: '['", ignore_path),
+            ignore_error.to_string()
+        );
+
+        // An ignore file that can't be read as such is skipped, like an unreadable directory.
+        let test_dir: TempDir = tempdir().unwrap();
+        let ignore_path: PathBuf = test_dir.path().join(".lffignore");
+        create_dir(&ignore_path).unwrap();
+        write(test_dir.path().join("found.txt"), "lff").unwrap();
+        install_test_logger();
+        let files: Vec<LffFile> =
+            scan_start_directory(test_dir.path(), test_args, &LffScanContext::default()).unwrap();
+        assert_eq!(1, files.len());
+        let expected_prefix: String =
+            format!("Skipping unreadable ignore file {:?}: ", ignore_path);
+        assert!(
+            LOGGED_MESSAGES
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(level, message)| *level == Level::Warn
+                    && message.starts_with(&expected_prefix))
+        );
+    }
+
+    /// Ensure that scanning multiple start directories in parallel yields the same files as
+    /// scanning each of them sequentially.
+    #[test]
+    fn test_scan_start_directories() {
+        let first_tree: TempDir = create_test_tree(8, 50);
+        let second_tree: TempDir = create_test_tree(4, 100);
+        let test_args: &LffArgs = &LffArgs {
+            directories: vec![
+                first_tree.path().to_string_lossy().into_owned(),
+                second_tree.path().to_string_lossy().into_owned(),
+            ],
+            ..BASE_ARGS
+        };
+
+        let mut parallel_files: Vec<LffFile> =
+            scan_start_directories(test_args, &LffScanContext::default()).unwrap();
+        let mut sequential_files: Vec<LffFile> = test_args
+            .directories
+            .iter()
+            .flat_map(|dir| {
+                scan_start_directory(Path::new(dir), test_args, &LffScanContext::default()).unwrap()
+            })
+            .collect();
+        assert_eq!(800, parallel_files.len());
+        // Neither set of files is sorted, so we sort them both for comparison.
+        parallel_files.sort_by(|a, b| a.name.cmp(&b.name));
+        sequential_files.sort_by(|a, b| a.name.cmp(&b.name));
+        for (parallel_file, sequential_file) in parallel_files.iter().zip(&sequential_files) {
+            assert_eq!(sequential_file.name, parallel_file.name);
+            assert_eq!(sequential_file.size, parallel_file.size);
+        }
+    }
+
+    /// Ensure that when multiple start directories are supplied, the correct error message is
+    /// generated for the one that does not exist.
+    #[test]
+    fn test_scan_start_directories_invalid_dir() {
+        let test_args: &LffArgs = &LffArgs {
+            directories: vec![
+                String::from("test_resources"),
+                String::from("this is not real"),
+            ],
+            ..BASE_ARGS
+        };
+        let dir_err: Report =
+            scan_start_directories(test_args, &LffScanContext::default()).unwrap_err();
+        assert_eq!(
+            "Invalid supplied start directory: 'this is not real'",
+            dir_err.to_string()
+        );
+    }
+
+    /// Ensure that when a start directory contains a glob pattern, every matching directory is
+    /// scanned, and files matching the pattern are ignored.
+    #[test]
+    fn test_scan_start_directories_glob() {
+        let test_args: &LffArgs = &LffArgs {
+            // This matches .hidden_dir and visible, as well as the top-level files.
+            directories: vec![String::from("test_resources/*")],
+            ..BASE_ARGS
+        };
+        let mut files: Vec<LffFile> =
+            scan_start_directories(test_args, &LffScanContext::default()).unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(2, files.len());
+        assert_eq!("test_resources/.hidden_dir/spider.txt", files[0].name);
+        assert_eq!("test_resources/visible/mud.md", files[1].name);
+    }
+
+    /// Ensure that the correct error messages are generated when a start directory contains an
+    /// invalid glob pattern, or a glob pattern that doesn't match any directories.
+    #[test]
+    fn test_resolve_start_directories_glob_errors() {
+        let invalid_error: Report = resolve_start_directories("test_resources/[").unwrap_err();
+        assert_eq!(
+            "Invalid glob in supplied start directory: 'test_resources/['",
+            invalid_error.to_string()
+        );
+        // Only files match this pattern, so there are no directories to scan.
+        let no_match_error: Report = resolve_start_directories("test_resources/*.txt").unwrap_err();
+        assert_eq!(
+            "No directories match the supplied start directory: 'test_resources/*.txt'",
+            no_match_error.to_string()
+        );
+    }
+
+    /// Ensure that an existing directory is resolved as is, even if its name contains glob
+    /// metacharacters.
+    #[test]
+    fn test_resolve_start_directories_existing_dir() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let photos_dir: PathBuf = test_dir.path().join("photos [2020]");
+        create_dir(&photos_dir).unwrap();
+        assert_eq!(
+            vec![photos_dir.clone()],
+            resolve_start_directories(&photos_dir.to_string_lossy()).unwrap()
+        );
+    }
+
+    /// Ensure that a scan configured with the builder finds the expected files end to end.
+    #[test]
+    fn test_scanner_scan() {
+        let files: Vec<LffFile> = LffScanner::new("test_resources")
+            .min_size(100)
+            .max_size(1000)
+            .sort_method(SortMethod::Size)
+            .scan()
+            .unwrap();
+        let names: Vec<&OsString> = files.iter().map(|file| &file.name).collect();
+        assert_eq!(
+            vec!["test_resources/snow.txt", "test_resources/visible/mud.md"],
+            names
+        );
+
+        let files: Vec<LffFile> = LffScanner::new("test_resources/visible")
+            .directory("test_resources/.hidden_dir")
+            .extension("txt")
+            .regex_pattern("spider")
+            .exclude_hidden()
+            .limit(1)
+            .scan()
+            .unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!("test_resources/.hidden_dir/spider.txt", files[0].name);
+
+        let files: Vec<LffFile> = LffScanner::new("test_resources")
+            .name_pattern("*.md")
+            .scan()
+            .unwrap();
+        assert_eq!(1, files.len());
+    }
+
+    /// Ensure that the files yielded by a lazy scan are the same as those returned by an eager
+    /// scan.
+    #[test]
+    fn test_scanner_scan_iter() {
+        let test_tree: TempDir = create_test_tree(4, 100);
+        let scanner: LffScanner = LffScanner::new(test_tree.path().to_string_lossy()).min_size(50);
+
+        let mut lazy_names: Vec<OsString> = scanner
+            .scan_iter()
+            .unwrap()
+            .map(|file_result| file_result.unwrap().name)
+            .collect();
+        let mut eager_names: Vec<OsString> = scanner
+            .scan()
+            .unwrap()
+            .into_iter()
+            .map(|file| file.name)
+            .collect();
+        assert_eq!(350, lazy_names.len());
+        // No order is guaranteed for either scan, so we sort them both for comparison.
+        lazy_names.sort();
+        eager_names.sort();
+        assert_eq!(eager_names, lazy_names);
+    }
+
+    /// Ensure that a lazy scan can be stopped early, and that a limit is respected.
+    #[test]
+    fn test_scanner_scan_iter_early_stop() {
+        let test_tree: TempDir = create_test_tree(8, 500);
+        let scanner: LffScanner = LffScanner::new(test_tree.path().to_string_lossy());
+
+        let mut lazy_files = scanner.scan_iter().unwrap();
+        assert!(lazy_files.next().unwrap().is_ok());
+        // Dropping the iterator stops the scan, rather than it running to completion.
+        drop(lazy_files);
+        assert_eq!(3, scanner.limit(3).scan_iter().unwrap().count());
+        let zero_limit: LffScanner = LffScanner::new(test_tree.path().to_string_lossy()).limit(0);
+        assert_eq!(0, zero_limit.scan_iter().unwrap().count());
+    }
+
+    /// Ensure that once a lazy scan's limit is reached, the scan is cancelled, and it can no longer
+    /// send files, so that it doesn't carry on in the background.
+    #[test]
+    fn test_scan_iter_limit_stops_scan() {
+        let (sender, receiver): (
+            SyncSender<eyre::Result<LffFile>>,
+            Receiver<eyre::Result<LffFile>>,
+        ) = sync_channel(2);
+        let cancelled: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let mut scan_iter: LffScanIter = LffScanIter {
+            receiver: Some(receiver),
+            remaining: 1,
+            cancelled: cancelled.clone(),
+        };
+        let found_file =
+            || handle_entry(PathBuf::from("test_resources/snow.txt"), &BASE_ARGS, None);
+        sender.send(found_file()).unwrap();
+        sender.send(found_file()).unwrap();
+
+        assert!(scan_iter.next().unwrap().is_ok());
+        assert!(cancelled.load(Ordering::Relaxed));
+        assert!(sender.send(found_file()).is_err());
+        assert!(scan_iter.next().is_none());
+    }
+
+    /// Ensure that the correct errors are generated when a lazy scan is configured with a sort
+    /// method, or fails partway through.
+    #[test]
+    fn test_scanner_scan_iter_errors() {
+        let sort_error: Report = LffScanner::new("test_resources")
+            .sort_method(SortMethod::Size)
+            .scan_iter()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "Files cannot be sorted when scanning lazily, so use scan() instead",
+            sort_error.to_string()
+        );
+        let scan_results: Vec<eyre::Result<LffFile>> = LffScanner::new("this is not real")
+            .scan_iter()
+            .unwrap()
+            .collect();
+        assert_eq!(1, scan_results.len());
+        assert_eq!(
+            "Invalid supplied start directory: 'this is not real'",
+            scan_results[0].as_ref().unwrap_err().to_string()
+        );
+    }
+
+    /// Ensure that the correct error messages are generated when a scan is configured with
+    /// conflicting or invalid options.
+    #[test]
+    fn test_scanner_scan_invalid() {
+        let size_error: Report = LffScanner::new("test_resources")
+            .min_size(10)
+            .max_size(5)
+            .scan()
+            .unwrap_err();
+        assert_eq!(
+            "The minimum size of 10 bytes is greater than the maximum size of 5 bytes",
+            size_error.to_string()
+        );
+        let pattern_error: Report = LffScanner::new("test_resources")
+            .name_pattern("*.md")
+            .regex_pattern("md$")
+            .scan()
+            .unwrap_err();
+        assert_eq!(
+            "Only one of a name pattern and a regex pattern can be supplied",
+            pattern_error.to_string()
+        );
+        let regex_error: Report = LffScanner::new("test_resources")
+            .regex_pattern("[")
+            .scan()
+            .unwrap_err();
+        assert_eq!(
+            "Invalid regex from regex pattern flag: '['",
+            regex_error.to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run, the expected formatted text is output.
+    #[test]
+    fn test_run_finder() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            // Sort by size for a repeatable test.
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(
+            LffScanOutcome {
+                count: 5,
+                total_size: 1183 + 544 + 329 + 27
+            },
+            outcome
+        );
+        // Check that the correct output has been 'printed'.
+        assert_eq!(5, test_printer.0.len());
+        assert_eq!(
+            "1183  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[0]
+        );
+        assert_eq!("544   \"test_resources/snow.txt\"", test_printer.0[1]);
+        assert_eq!("329   \"test_resources/visible/mud.md\"", test_printer.0[2]);
+        assert_eq!("27    \"test_resources/LICENCE\"", test_printer.0[3]);
+        assert_eq!("0     \"test_resources/.hidden\"", test_printer.0[4]);
+    }
+
+    /// Ensure that when the finder is run with the reverse flag, the sort order is flipped before
+    /// the limit is applied.
+    #[test]
+    fn test_run_finder_reverse() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            reverse: true,
+            limit: Some(4),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(4, outcome.count);
+        assert_eq!(
+            vec![
+                "0     \"test_resources/.hidden\"",
+                "27    \"test_resources/LICENCE\"",
+                "329   \"test_resources/visible/mud.md\"",
+                "544   \"test_resources/snow.txt\"",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with the total only flag, only the combined size of the
+    /// found files is output, in both raw and pretty-printed forms, including when none are found.
+    #[test]
+    fn test_run_finder_total_only() {
+        for (pretty, min_size_mib, expected_total) in [
+            (false, 0.0, "2083"),
+            (true, 0.0, "2.03 KiB"),
+            (false, 100.0, "0"),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                pretty,
+                min_size_mib,
+                total_only: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(vec![expected_total], test_printer.0);
+            assert!(test_printer.1.is_empty());
+        }
+    }
+
+    /// Ensure that when the finder is run with a limit smaller than the number of matched files,
+    /// the summaries are based on the displayed files by default, and on all matched files with the
+    /// summary all flag, even when the output is unordered.
+    #[test]
+    fn test_run_finder_summary_all() {
+        for (summary_all, sort_method, expected_total) in [
+            (false, Some(SortMethod::Size), "1727"),
+            (true, Some(SortMethod::Size), "2083"),
+            (true, None, "2083"),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                limit: Some(2),
+                unordered: sort_method.is_none(),
+                sort_method,
+                summary_all,
+                total_only: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(2, outcome.count);
+            assert_eq!(vec![expected_total], test_printer.0);
+        }
+
+        for (summary_all, expected_summary) in [
+            (false, "Found 2 file(s) totalling 1727;"),
+            (true, "Found 5 file(s) totalling 2083;"),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format: OutputFormat::Human,
+                limit: Some(2),
+                sort_method: Some(SortMethod::Size),
+                summary_all,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            run_finder!(test_args, &mut test_printer).unwrap();
+            assert!(test_printer.0[0].starts_with(expected_summary));
+        }
+    }
+
+    /// Ensure that when the finder is run with the right align flag, the file sizes are aligned on
+    /// their least significant digit.
+    #[test]
+    fn test_run_finder_right_align() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            right_align: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                " 544  \"test_resources/snow.txt\"",
+                " 329  \"test_resources/visible/mud.md\"",
+                "  27  \"test_resources/LICENCE\"",
+                "   0  \"test_resources/.hidden\"",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with coloured size tiers, each size is coloured by the
+    /// tier it falls into, and that nothing is coloured when colour is disabled.
+    #[test]
+    fn test_run_finder_color_tiers() {
+        for (color, expected_lines) in [
+            (
+                ColorChoice::Always,
+                vec![
+                    "\x1b[31m1183\x1b[0m  \"test_resources/.hidden_dir/spider.txt\"",
+                    "\x1b[33m544 \x1b[0m  \"test_resources/snow.txt\"",
+                    "\x1b[32m329 \x1b[0m  \"test_resources/visible/mud.md\"",
+                ],
+            ),
+            (
+                ColorChoice::Never,
+                vec![
+                    "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                    "544   \"test_resources/snow.txt\"",
+                    "329   \"test_resources/visible/mud.md\"",
+                ],
+            ),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                sort_method: Some(SortMethod::Size),
+                limit: Some(3),
+                color,
+                color_tiers: true,
+                color_tier_red: 1000,
+                color_tier_yellow: 500,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(3, outcome.count);
+            assert_eq!(expected_lines, test_printer.0);
+        }
+
+        let invalid_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            color_tiers: true,
+            color_tier_red: 500,
+            color_tier_yellow: 1000,
+            ..BASE_ARGS
+        };
+        let error: Report = run_finder!(invalid_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            "The yellow colour tier can't be larger than the red colour tier",
+            error.to_string()
+        );
+    }
+
+    /// Ensure that tab escape sequences in supplied separators are replaced with tab characters.
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!("\t", parse_separator("\\t").unwrap());
+        assert_eq!(" | ", parse_separator(" | ").unwrap());
+    }
+
+    /// Ensure that when the finder is run with a custom separator, the columns are separated by it
+    /// without any padding.
+    #[test]
+    fn test_run_finder_separator() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            separator: Some(parse_separator("\\t").unwrap()),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(5, test_printer.0.len());
+        // Each line should split cleanly into exactly two columns.
+        let columns: Vec<Vec<&str>> = test_printer
+            .0
+            .iter()
+            .map(|line| line.split('\t').collect())
+            .collect();
+        assert_eq!(
+            vec!["1183", "\"test_resources/.hidden_dir/spider.txt\""],
+            columns[0]
+        );
+        assert_eq!(vec!["544", "\"test_resources/snow.txt\""], columns[1]);
+        assert_eq!(vec!["329", "\"test_resources/visible/mud.md\""], columns[2]);
+        assert_eq!(vec!["27", "\"test_resources/LICENCE\""], columns[3]);
+        assert_eq!(vec!["0", "\"test_resources/.hidden\""], columns[4]);
+    }
+
+    /// Ensure that when the finder is run and sorted by name, the expected formatted text is
+    /// output, ordered by file name regardless of directory.
+    #[test]
+    fn test_run_finder_sort_by_name() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Name),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        // Check that the correct output has been 'printed'.
+        assert_eq!(5, test_printer.0.len());
+        assert_eq!("0     \"test_resources/.hidden\"", test_printer.0[0]);
+        assert_eq!("27    \"test_resources/LICENCE\"", test_printer.0[1]);
+        assert_eq!("329   \"test_resources/visible/mud.md\"", test_printer.0[2]);
+        assert_eq!("544   \"test_resources/snow.txt\"", test_printer.0[3]);
+        assert_eq!(
+            "1183  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[4]
+        );
+    }
+
+    /// Ensure that when the finder is run and sorted by path, the expected formatted text is
+    /// output, with files in the same directory grouped together.
+    #[test]
+    fn test_run_finder_sort_by_path() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Path),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        // Check that the correct output has been 'printed'.
+        assert_eq!(5, test_printer.0.len());
+        assert_eq!("0     \"test_resources/.hidden\"", test_printer.0[0]);
+        assert_eq!(
+            "1183  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[1]
+        );
+        assert_eq!("27    \"test_resources/LICENCE\"", test_printer.0[2]);
+        assert_eq!("544   \"test_resources/snow.txt\"", test_printer.0[3]);
+        assert_eq!("329   \"test_resources/visible/mud.md\"", test_printer.0[4]);
+    }
+
+    /// Ensure that when the finder is run and sorted by extension, files are grouped by extension,
+    /// with those without an extension displayed last.
+    #[test]
+    fn test_run_finder_sort_by_extension() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Extension),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "329   \"test_resources/visible/mud.md\"",
+                "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "544   \"test_resources/snow.txt\"",
+                "0     \"test_resources/.hidden\"",
+                "27    \"test_resources/LICENCE\"",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when extensions are case-folded, files with mixed-case extensions are grouped
+    /// and sorted as one type, with their displayed names unchanged.
+    #[test]
+    fn test_run_finder_extension_case_fold() {
+        let test_dir: TempDir = tempdir().unwrap();
+        for (file_name, size) in [("a.JPG", 30), ("b.png", 20), ("c.jpg", 10)] {
+            write(test_dir.path().join(file_name), "x".repeat(size)).unwrap();
+        }
+        let dir_string: String = test_dir.path().to_string_lossy().into_owned();
+
+        for (extension_case_fold, expected_headers) in [
+            (
+                false,
+                vec![
+                    "\"JPG\" - 1 file(s), 30 total",
+                    "\"png\" - 1 file(s), 20 total",
+                    "\"jpg\" - 1 file(s), 10 total",
+                ],
+            ),
+            (
+                true,
+                vec![
+                    "\"jpg\" - 2 file(s), 40 total",
+                    "\"png\" - 1 file(s), 20 total",
+                ],
+            ),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![dir_string.clone()],
+                extension_case_fold,
+                group_by_extension: true,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(3, outcome.count);
+            let headers: Vec<&String> = test_printer
+                .0
+                .iter()
+                .filter(|line| line.contains(" total"))
+                .collect();
+            assert_eq!(expected_headers, headers);
+            assert!(test_printer.0[1].ends_with("a.JPG\""));
+        }
+
+        let test_args: LffArgs = LffArgs {
+            directories: vec![dir_string],
+            extension_case_fold: true,
+            sort_method: Some(SortMethod::Extension),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(3, outcome.count);
+        let sizes: Vec<&str> = test_printer
+            .0
+            .iter()
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(vec!["30", "10", "20"], sizes);
+    }
+
+    /// Ensure that files which are equal by the sort method are consistently ordered by size and
+    /// then by path.
+    #[test]
+    fn test_run_finder_sort_tie_break() {
+        let test_dir: TempDir = tempdir().unwrap();
+        create_dir(test_dir.path().join("sub")).unwrap();
+        for (file_name, size) in [
+            ("b.txt", 10),
+            ("a.txt", 10),
+            ("sub/a.txt", 20),
+            ("c.txt", 5),
+        ] {
+            write(test_dir.path().join(file_name), vec![0; size]).unwrap();
+        }
+        for (sort_method, expected_order) in [
+            (SortMethod::Size, ["sub/a.txt", "a.txt", "b.txt", "c.txt"]),
+            (SortMethod::Name, ["sub/a.txt", "a.txt", "b.txt", "c.txt"]),
+            (SortMethod::Path, ["a.txt", "b.txt", "c.txt", "sub/a.txt"]),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                sort_method: Some(sort_method),
+                separator: Some(String::from(" ")),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(4, outcome.count);
+            let expected_lines: Vec<String> = expected_order
+                .iter()
+                .map(|file_name| {
+                    let file_path: PathBuf = test_dir.path().join(file_name);
+                    format!("{} {:?}", file_path.metadata().unwrap().len(), file_path)
+                })
+                .collect();
+            assert_eq!(expected_lines, test_printer.0);
+        }
+    }
+
+    /// Ensure that the limit flag functions correctly when running the finder in combination with
+    /// the sort flag.
+    #[test]
+    fn test_run_finder_limit() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            limit: Some(3),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(3, outcome.count);
+        // We expect only the three largest of the test files to have been output.
+        assert_eq!(3, test_printer.0.len());
+        assert_eq!(
+            "1183  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[0]
+        );
+        assert_eq!("544   \"test_resources/snow.txt\"", test_printer.0[1]);
+        assert_eq!("329   \"test_resources/visible/mud.md\"", test_printer.0[2]);
+    }
+
+    /// Ensure that without a sort method, files are ordered by path so that consecutive runs
+    /// produce identical output, even with a limit, unless the unordered flag is passed.
+    #[test]
+    fn test_run_finder_ordered_by_default() {
+        let test_tree: TempDir = create_test_tree(8, 50);
+        let run = |limit: Option<usize>, unordered: bool| -> Vec<String> {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_tree.path().to_string_lossy().into_owned()],
+                limit,
+                unordered,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(test_printer.0.len(), outcome.count);
+            test_printer.0
+        };
+
+        let first_output: Vec<String> = run(None, false);
+        assert_eq!(400, first_output.len());
+        assert_eq!(first_output, run(None, false));
+        let mut sorted_output: Vec<String> = first_output.clone();
+        sorted_output.sort_by_key(|line| line.split_once('"').unwrap().1.to_string());
+        assert_eq!(sorted_output, first_output);
+        assert_eq!(first_output[..10], run(Some(10), false));
+
+        // Unordered output contains the same files, in whatever order they were found.
+        let mut unordered_output: Vec<String> = run(None, true);
+        unordered_output.sort_by_key(|line| line.split_once('"').unwrap().1.to_string());
+        assert_eq!(first_output, unordered_output);
+    }
+
+    /// Ensure that when the finder is run with the group by extension flag, files are grouped
+    /// under headers containing each extension's file count and subtotal.
+    #[test]
+    fn test_run_finder_group_by_extension() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            group_by_extension: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        // We expect three groups - txt, md, and files without an extension - each with a header.
+        assert_eq!(8, test_printer.0.len());
+        assert_eq!("\"txt\" - 2 file(s), 1727 total", test_printer.0[0]);
+        assert_eq!(
+            "1183  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[1]
+        );
+        assert_eq!("544   \"test_resources/snow.txt\"", test_printer.0[2]);
+        assert_eq!("\"md\" - 1 file(s), 329 total", test_printer.0[3]);
+        assert_eq!("329   \"test_resources/visible/mud.md\"", test_printer.0[4]);
+        assert_eq!("(no extension) - 2 file(s), 27 total", test_printer.0[5]);
+        assert_eq!("27    \"test_resources/LICENCE\"", test_printer.0[6]);
+        assert_eq!("0     \"test_resources/.hidden\"", test_printer.0[7]);
+    }
+
+    /// Ensure that when the finder is run with the tree format, the found files are displayed in
+    /// an indented directory hierarchy.
+    #[test]
+    fn test_run_finder_tree_format() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Tree,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        // Directories are displayed in alphabetical order, with sizes only shown for files.
+        assert_eq!(
+            vec![
+                "test_resources",
+                "├── .hidden (0)",
+                "├── .hidden_dir",
+                "│   └── spider.txt (1183)",
+                "├── LICENCE (27)",
+                "├── snow.txt (544)",
+                "└── visible",
+                "    └── mud.md (329)",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with the human tree format, each directory is displayed
+    /// with the total size of the files within it, and chains of directories containing only a
+    /// single directory are collapsed onto one line.
+    #[test]
+    fn test_run_finder_human_tree_format() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::HumanTree,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "test_resources (2083)",
+                "├── .hidden (0)",
+                "├── .hidden_dir (1183)",
+                "│   └── spider.txt (1183)",
+                "├── LICENCE (27)",
+                "├── snow.txt (544)",
+                "└── visible (329)",
+                "    └── mud.md (329)",
+            ],
+            test_printer.0
+        );
+
+        let test_dir: TempDir = tempdir().unwrap();
+        create_dir_all(test_dir.path().join("a/b/c")).unwrap();
+        write(test_dir.path().join("a/b/c/x.bin"), vec![0; 100]).unwrap();
+        write(test_dir.path().join("a/y.bin"), vec![0; 50]).unwrap();
+        let collapse_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            format: OutputFormat::HumanTree,
+            ..BASE_ARGS
+        };
+        let mut collapse_printer: LffTestPrinter = LffTestPrinter::default();
+
+        run_finder!(collapse_args, &mut collapse_printer).unwrap();
+        // The directories above the temporary directory only contain a single directory too.
+        assert_eq!(
+            vec![
+                format!("{} (150)", test_dir.path().join("a").display()),
+                format!("├── {} (100)", Path::new("b").join("c").display()),
+                String::from("│   └── x.bin (100)"),
+                String::from("└── y.bin (50)"),
+            ],
+            collapse_printer.0
+        );
+    }
+
+    /// Ensure that times are formatted as the correct UTC timestamps, including around leap days
+    /// and for times before the Unix epoch.
+    #[test]
+    fn test_format_timestamp() {
+        for (secs, expected) in [
+            (0, "1970-01-01 00:00:00"),
+            (951782400, "2000-02-29 00:00:00"),
+            (1714571100, "2024-05-01 13:45:00"),
+            (4107542399, "2100-02-28 23:59:59"),
+        ] {
+            assert_eq!(
+                expected,
+                format_timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            );
+        }
+        assert_eq!(
+            "1970-01-01 00:00:00",
+            format_timestamp(SystemTime::UNIX_EPOCH - Duration::from_secs(1))
+        );
+    }
+
+    /// Ensure that durations are formatted as relative times in the largest whole unit that fits
+    /// them.
+    #[test]
+    fn test_format_relative_time() {
+        for (secs, expected) in [
+            (0, "just now"),
+            (59, "just now"),
+            (60, "1m"),
+            (300, "5m"),
+            (3599, "59m"),
+            (3600, "1h"),
+            (7200, "2h"),
+            (86399, "23h"),
+            (86400, "1d"),
+            (259200, "3d"),
+            (31535999, "364d"),
+            (31536000, "1y"),
+            (94608000, "3y"),
+        ] {
+            assert_eq!(expected, format_relative_time(Duration::from_secs(secs)));
+        }
+    }
+
+    /// Ensure that when the finder is run with the age flag, each file's relative age is displayed
+    /// right-aligned between its size and name, so that the names remain aligned, and that it is
+    /// also displayed in the table format.
+    #[test]
+    fn test_run_finder_age() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let now: SystemTime = SystemTime::now();
+        for (file_name, contents, age_secs) in [
+            ("fresh.txt", "lff", 0),
+            ("stale.txt", "lfflff", 3 * 86400 + 100),
+        ] {
+            let file_path: PathBuf = test_dir.path().join(file_name);
+            write(&file_path, contents).unwrap();
+            File::options()
+                .write(true)
+                .open(&file_path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                age: true,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                format!("6        3d  {:?}", test_dir.path().join("stale.txt")),
+                format!("3  just now  {:?}", test_dir.path().join("fresh.txt")),
+            ],
+            test_printer.0
+        );
+
+        let mut table_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                age: true,
+                format: OutputFormat::Table,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            },
+            &mut table_printer
+        )
+        .unwrap();
+        assert!(table_printer.0[0].starts_with("Size  Modified (UTC)       Age       Name"));
+        assert!(table_printer.0[2].contains(" 3d  "));
+    }
+
+    /// Ensure that a placeholder is displayed in the inode and modified columns for files without a
+    /// known inode number or modification time.
+    #[test]
+    fn test_column_cell_no_modified() {
+        let test_file: PathBuf = PathBuf::from("test_resources/snow.txt");
+        let file: LffFile = LffFile {
+            modified: None,
+            inode: None,
+            ..handle_entry(test_file, &BASE_ARGS, None).unwrap()
+        };
+        assert_eq!("0", Column::Depth.cell(&file));
+        assert_eq!("544", Column::Size.cell(&file));
+        assert_eq!("-", Column::Inode.cell(&file));
+        assert_eq!("-", Column::Modified.cell(&file));
+        assert_eq!("-", Column::Age.cell(&file));
+        assert_eq!("txt", Column::Extension.cell(&file));
+        assert_eq!("\"test_resources/snow.txt\"", Column::Name.cell(&file));
+    }
+
+    /// Ensure that when the finder is run with the show inode flag, each file's actual inode number
+    /// is displayed between its size and name, in both the text and table formats.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_show_inode() {
+        use std::os::unix::fs::MetadataExt;
+
+        let inode: u64 = Path::new("test_resources/snow.txt")
+            .metadata()
+            .unwrap()
+            .ino();
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                name_pattern: Some(String::from("*snow.txt")),
+                show_inode: true,
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![format!("544  {:>10}  \"test_resources/snow.txt\"", inode)],
+            test_printer.0
+        );
+
+        let mut table_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                name_pattern: Some(String::from("*snow.txt")),
+                format: OutputFormat::Table,
+                show_inode: true,
+                ..BASE_ARGS
+            },
+            &mut table_printer
+        )
+        .unwrap();
+        assert!(table_printer.0[0].starts_with("Size  Inode"));
+        assert!(table_printer.0[2].starts_with(&format!("544   {}", inode)));
+    }
+
+    /// Ensure that when the finder is run with a sample size and a fixed seed, the same number of
+    /// files are sampled each time, and the same files are sampled on every run, despite the
+    /// parallel traversal. A sample larger than the number of matched files contains all of them.
+    #[test]
+    fn test_run_finder_sample() {
+        let test_dir: TempDir = create_test_tree(4, 5);
+        let run_sample = |sample: usize, seed: u64| -> Vec<String> {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                sample: Some(sample),
+                seed: Some(seed),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(test_printer.0.len(), outcome.count);
+            test_printer.0
+        };
+
+        let first_sample: Vec<String> = run_sample(5, 42);
+        assert_eq!(5, first_sample.len());
+        for _ in 0..5 {
+            assert_eq!(first_sample, run_sample(5, 42));
+        }
+        assert_eq!(20, run_sample(50, 42).len());
+        assert!(run_sample(0, 42).is_empty());
+    }
+
+    /// Ensure that seeded hashes are stable, so that a seed selects the same files on every
+    /// platform and Rust version.
+    #[test]
+    fn test_seeded_hash() {
+        assert_eq!(12161962213042174405, seeded_hash(0, OsStr::new("")));
+        assert_eq!(
+            10737539248120050251,
+            seeded_hash(42, OsStr::new("test_resources/snow.txt"))
+        );
+        assert_eq!(
+            12242038684844211517,
+            seeded_hash(42, OsStr::new("test_resources/LICENCE"))
+        );
+    }
+
+    /// Ensure that when the finder is run with the random sort method and a fixed seed, all of the
+    /// files are output in the same order on every run, even when reversed, and that a seed can't
+    /// be supplied without anything random.
+    #[test]
+    fn test_run_finder_sort_random() {
+        let run_sort = |seed: u64, reverse: bool| -> Vec<String> {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format: OutputFormat::Path,
+                sort_method: Some(SortMethod::Random),
+                seed: Some(seed),
+                reverse,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+            run_finder!(test_args, &mut test_printer).unwrap();
+            test_printer.0
+        };
+
+        let first_order: Vec<String> = run_sort(42, false);
+        for _ in 0..5 {
+            assert_eq!(first_order, run_sort(42, false));
+        }
+        assert_eq!(first_order, run_sort(42, true));
+        let mut sorted_order: Vec<String> = first_order.clone();
+        sorted_order.sort();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md",
+            ],
+            sorted_order
+        );
+
+        assert!(LffArgs::try_parse_from(["lff", "--seed", "42", "."]).is_err());
+        assert!(LffArgs::try_parse_from(["lff", "--seed", "42", "--sample", "3", "."]).is_ok());
+        assert!(LffArgs::try_parse_from(["lff", "--seed", "42", "-s", "random", "."]).is_ok());
+        assert!(LffArgs::try_parse_from([
+            "lff", "--seed", "42", "--sample", "3", "-s", "random", "."
+        ])
+        .is_ok());
+    }
+
+    /// Ensure that when the finder is run with a base directory to display paths relative to, the
+    /// paths of files within it are relative to it, and the paths of those that aren't are
+    /// absolute.
+    #[test]
+    fn test_run_finder_absolute_from() {
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                absolute_from: Some(PathBuf::from(".")),
+                format: OutputFormat::Path,
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md",
+            ],
+            test_printer.0
+        );
+
+        let mut nested_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                absolute_from: Some(PathBuf::from("test_resources/visible")),
+                format: OutputFormat::Path,
+                ..BASE_ARGS
+            },
+            &mut nested_printer
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize("test_resources/snow.txt")
+                .unwrap()
+                .to_string_lossy(),
+            nested_printer.0[3]
+        );
+        assert_eq!("mud.md", nested_printer.0[4]);
+    }
+
+    /// Ensure that an error is returned when the finder is run with a base directory to display
+    /// paths relative to that doesn't exist.
+    #[test]
+    fn test_run_finder_absolute_from_missing() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            absolute_from: Some(PathBuf::from("test_resources/missing")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let finder_result: eyre::Result<LffScanOutcome> = run_finder!(test_args, &mut test_printer);
+        assert_eq!(
+            "Could not generate absolute path for base directory \"test_resources/missing\"",
+            finder_result.unwrap_err().to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run with the show depth flag, each file's depth relative to
+    /// the start directory is displayed as the first column, in both the text and table formats.
+    #[test]
+    fn test_run_finder_show_depth() {
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                name_pattern: Some(String::from("*.txt")),
+                show_depth: true,
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "  1  1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "  0  544   \"test_resources/snow.txt\"",
+            ],
+            test_printer.0
+        );
+
+        let mut separated_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                name_pattern: Some(String::from("*snow.txt")),
+                separator: Some(String::from(",")),
+                show_depth: true,
+                ..BASE_ARGS
+            },
+            &mut separated_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["0,544,\"test_resources/snow.txt\""],
+            separated_printer.0
+        );
+
+        let mut table_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                name_pattern: Some(String::from("*.txt")),
+                format: OutputFormat::Table,
+                show_depth: true,
+                ..BASE_ARGS
+            },
+            &mut table_printer
+        )
+        .unwrap();
+        assert!(table_printer.0[0].starts_with("Depth  Size  Modified (UTC)"));
+        assert!(table_printer.0[2].starts_with("1      1183  "));
+        assert!(table_printer.0[3].starts_with("0      544   "));
+    }
+
+    /// Ensure that when the finder is run with the path format, only the bare paths of the found
+    /// files are output.
+    #[test]
+    fn test_run_finder_path_format() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Path,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "test_resources/.hidden",
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md",
+            ],
+            test_printer.0
+        );
+        assert!(test_printer.1.is_empty());
+    }
+
+    /// Ensure that when the finder is run with the TSV format, a header row is output, followed by
+    /// a row of tab-separated fields for each file, and that just the header is output when no
+    /// files are found.
+    #[test]
+    fn test_run_finder_tsv() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Tsv,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "size\tformatted_size\textension\thidden\tname",
+                "0\t0\t\ttrue\ttest_resources/.hidden",
+                "1183\t1183\ttxt\tfalse\ttest_resources/.hidden_dir/spider.txt",
+                "27\t27\t\tfalse\ttest_resources/LICENCE",
+                "544\t544\ttxt\tfalse\ttest_resources/snow.txt",
+                "329\t329\tmd\tfalse\ttest_resources/visible/mud.md",
+            ],
+            test_printer.0
+        );
+
+        let empty_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Tsv,
+            min_size_mib: 1.0,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut empty_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(empty_args, &mut empty_printer).unwrap();
+        assert_eq!(vec![TSV_HEADER], empty_printer.0);
+    }
+
+    /// Ensure that tabs and line breaks in names are escaped in the TSV format, so that each record
+    /// remains a single line with five fields.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_tsv_escaped_name() {
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("tab\tand\nnewline.txt"), "lff").unwrap();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            format: OutputFormat::Tsv,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(
+            vec![
+                String::from(TSV_HEADER),
+                format!(
+                    "3\t3\ttxt\tfalse\t{}/tab\\tand\\nnewline.txt",
+                    test_dir.path().to_string_lossy()
+                ),
+            ],
+            test_printer.0
+        );
+        assert_eq!(5, test_printer.0[1].split('\t').count());
+    }
+
+    /// Ensure that backslashes are escaped in TSV fields, so that they can't be confused with
+    /// escaped tabs and line breaks.
+    #[test]
+    fn test_escape_tsv_field() {
+        assert_eq!("plain.txt", escape_tsv_field("plain.txt"));
+        assert_eq!("a\\tb\\nc", escape_tsv_field("a\tb\nc"));
+        assert_eq!("a\\\\tb", escape_tsv_field("a\\tb"));
+        assert_eq!("a\\\\\\tb", escape_tsv_field("a\\\tb"));
+    }
+
+    /// Ensure that when the finder is run with the table format, a header and separator line are
+    /// output, followed by a row for each file with aligned columns.
+    #[test]
+    fn test_run_finder_table() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Table,
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(7, test_printer.0.len());
+        assert_eq!("Size  Modified (UTC)       Name", test_printer.0[0]);
+        assert_eq!(
+            format!("----  -------------------  {}", "-".repeat(39)),
+            test_printer.0[1]
+        );
+        for (row, file_name) in test_printer.0[2..].iter().zip([
+            "test_resources/.hidden_dir/spider.txt",
+            "test_resources/snow.txt",
+            "test_resources/visible/mud.md",
+            "test_resources/LICENCE",
+            "test_resources/.hidden",
+        ]) {
+            let metadata: Metadata = Path::new(file_name).metadata().unwrap();
+            assert_eq!(
+                format!(
+                    "{:<4}  {}  {:?}",
+                    metadata.len(),
+                    format_timestamp(metadata.modified().unwrap()),
+                    file_name
+                ),
+                *row
+            );
+        }
+    }
+
+    /// Ensure that when the finder is run with custom columns, only those columns are displayed, in
+    /// the supplied order, in both the text and table formats.
+    #[test]
+    fn test_run_finder_columns() {
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                columns: Some(vec![Column::Extension, Column::Name, Column::Size]),
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "txt  \"test_resources/.hidden_dir/spider.txt\"  1183",
+                "txt  \"test_resources/snow.txt\"                544",
+                "md   \"test_resources/visible/mud.md\"          329",
+                "-    \"test_resources/LICENCE\"                 27",
+                "-    \"test_resources/.hidden\"                 0",
+            ],
+            test_printer.0
+        );
+
+        let mut separated_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                columns: Some(vec![Column::Size, Column::Extension]),
+                name_pattern: Some(String::from("*.txt")),
+                separator: Some(String::from(",")),
+                ..BASE_ARGS
+            },
+            &mut separated_printer
+        )
+        .unwrap();
+        assert_eq!(vec!["1183,txt", "544,txt"], separated_printer.0);
+
+        let mut table_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                columns: Some(vec![Column::Name, Column::Size]),
+                format: OutputFormat::Table,
+                name_pattern: Some(String::from("*snow.txt")),
+                ..BASE_ARGS
+            },
+            &mut table_printer
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "Name                       Size",
+                "-------------------------  ----",
+                "\"test_resources/snow.txt\"  544",
+            ],
+            table_printer.0
+        );
+    }
+
+    /// Ensure that the columns are parsed from a comma-separated list, and that an unknown column
+    /// results in an error listing the valid columns.
+    #[test]
+    fn test_parse_columns() {
+        let parsed_args: LffArgs = LffArgs::try_parse_from([
+            "lff",
+            "--columns",
+            "size,modified,ext,name",
+            "test_resources",
+        ])
+        .unwrap();
+        assert!(matches!(
+            parsed_args.columns.as_deref(),
+            Some([
+                Column::Size,
+                Column::Modified,
+                Column::Extension,
+                Column::Name
+            ])
+        ));
+
+        let parse_error: String =
+            LffArgs::try_parse_from(["lff", "--columns", "size,colour", "test_resources"])
+                .err()
+                .unwrap()
+                .to_string();
+        assert!(parse_error.contains("invalid value 'colour' for '--columns <COLUMNS>'"));
+        assert!(
+            parse_error.contains("[possible values: depth, size, inode, modified, age, ext, name]")
+        );
+    }
+
+    /// Ensure that when the finder is run with the Markdown format, a table with the size and name
+    /// of each file is output, or with the supplied columns, and that pipes in names are escaped.
+    #[test]
+    fn test_run_finder_markdown() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Markdown,
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "| Size | Name |",
+                "| --- | --- |",
+                "| 1183 | \"test_resources/.hidden_dir/spider.txt\" |",
+                "| 544 | \"test_resources/snow.txt\" |",
+                "| 329 | \"test_resources/visible/mud.md\" |",
+                "| 27 | \"test_resources/LICENCE\" |",
+                "| 0 | \"test_resources/.hidden\" |",
+            ],
+            test_printer.0
+        );
+
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("pipe|name.txt"), "lff").unwrap();
+        let pipe_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            columns: Some(vec![Column::Extension, Column::Name]),
+            format: OutputFormat::Markdown,
+            ..BASE_ARGS
+        };
+        let mut pipe_printer: LffTestPrinter = LffTestPrinter::default();
+
+        run_finder!(pipe_args, &mut pipe_printer).unwrap();
+        assert_eq!(
+            vec![
+                String::from("| Ext | Name |"),
+                String::from("| --- | --- |"),
+                format!(
+                    "| txt | \"{}/pipe\\|name.txt\" |",
+                    test_dir.path().to_string_lossy()
+                ),
+            ],
+            pipe_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with the NDJSON format, each output line is a standalone
+    /// JSON object, and that together they cover all of the expected files.
+    #[test]
+    fn test_run_finder_ndjson() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Ndjson,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        let mut objects: Vec<Value> = test_printer
+            .0
+            .iter()
+            .map(|line| from_str(line).unwrap())
+            .collect();
+        objects.sort_by_key(|object| Reverse(object["size"].as_u64()));
+        assert_eq!(5, objects.len());
+        assert_eq!(
+            json!({
+                "name": "test_resources/.hidden_dir/spider.txt",
+                "extension": "txt",
+                "size": 1183,
+                "formatted_size": "1183",
+                "hidden": false,
+                "is_dir": false,
+            }),
+            objects[0]
+        );
+        let names: Vec<&str> = objects
+            .iter()
+            .map(|object| object["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/snow.txt",
+                "test_resources/visible/mud.md",
+                "test_resources/LICENCE",
+                "test_resources/.hidden",
+            ],
+            names
+        );
+        assert_eq!(Value::Null, objects[3]["extension"]);
+    }
+
+    /// Ensure that when found files are streamed, as with the NDJSON format and the unordered flag,
+    /// each file is passed on as soon as it's found, up to the limit, while every found file is
+    /// still returned.
+    #[test]
+    fn test_scan_found_files_streaming() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Ndjson,
+            unordered: true,
+            ..BASE_ARGS
+        };
+        assert!(can_stream_output(&test_args));
+        let mut streamed: Vec<OsString> = Vec::new();
+        let files: Vec<LffFile> =
+            scan_found_files_streaming(&test_args, None, &LffScanContext::default(), 2, |file| {
+                streamed.push(file.name.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(5, files.len());
+        assert_eq!(
+            streamed,
+            files[..2]
+                .iter()
+                .map(|file| file.name.clone())
+                .collect::<Vec<OsString>>()
+        );
+
+        // Files supplied directly are only returned once the scan is complete, but are passed on.
+        let input_paths: Vec<PathBuf> = vec![PathBuf::from("test_resources/snow.txt")];
+        let mut streamed_count: usize = 0;
+        let files: Vec<LffFile> = scan_found_files_streaming(
+            &test_args,
+            Some(&input_paths),
+            &LffScanContext::default(),
+            usize::MAX,
+            |_| {
+                streamed_count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!(1, streamed_count);
+
+        // An error from the callback stops the scan.
+        let stream_error: Report = scan_found_files_streaming(
+            &test_args,
+            None,
+            &LffScanContext::default(),
+            usize::MAX,
+            |_| Err(eyre!("Could not print")),
+        )
+        .unwrap_err();
+        assert_eq!("Could not print", stream_error.to_string());
+
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(
+            LffArgs {
+                limit: Some(3),
+                ..test_args.clone()
+            },
+            &mut test_printer
+        )
+        .unwrap();
+        assert_eq!(3, outcome.count);
+        assert_eq!(3, test_printer.0.len());
+
+        // JSON stream file events are streamed in the same way, between the start and summary.
+        let mut stream_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(
+            LffArgs {
+                format: OutputFormat::JsonStream,
+                limit: Some(3),
+                ..test_args
+            },
+            &mut stream_printer
+        )
+        .unwrap();
+        let event_types: Vec<String> = stream_printer
+            .0
+            .iter()
+            .map(|line| from_str::<Value>(line).unwrap()["type"].to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "\"start\"",
+                "\"file\"",
+                "\"file\"",
+                "\"file\"",
+                "\"summary\""
+            ],
+            event_types
+        );
+    }
+
+    /// Ensure that when the finder is run with the JSON stream format, a start event is output,
+    /// followed by an event for each file and a summary event whose totals match them, including
+    /// when no files are found.
+    #[test]
+    fn test_run_finder_json_stream() {
+        for (min_size_mib, expected_count, expected_total) in [(0.0, 5, 2083), (1.0, 0, 0)] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format: OutputFormat::JsonStream,
+                min_size_mib,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(expected_count, outcome.count);
+            let events: Vec<Value> = test_printer
+                .0
+                .iter()
+                .map(|line| from_str(line).unwrap())
+                .collect();
+            assert_eq!(expected_count + 2, events.len());
+            assert_eq!(
+                json!({"type": "start", "directories": ["test_resources"]}),
+                events[0]
+            );
+            let file_events: &[Value] = &events[1..events.len() - 1];
+            assert!(file_events.iter().all(|event| event["type"] == "file"));
+            let file_total: u64 = file_events
+                .iter()
+                .map(|event| event["size"].as_u64().unwrap())
+                .sum();
+            assert_eq!(expected_total, file_total);
+            assert_eq!(
+                json!({"type": "summary", "count": expected_count, "total": expected_total}),
+                events[events.len() - 1]
+            );
+        }
+    }
+
+    /// Ensure that when the finder is run with the NDJSON format and no files are found, no lines
+    /// are output.
+    #[test]
+    fn test_run_finder_ndjson_no_files() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Ndjson,
+            min_size_mib: 1.0,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert!(test_printer.0.is_empty());
+    }
+
+    /// Ensure that when the finder is run with the depth summary flag, the total size of found
+    /// files at each depth is output after the files, for both traversal orders.
+    #[test]
+    fn test_run_finder_depth_summary() {
+        for breadth_first in [false, true] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                breadth_first,
+                depth_summary: true,
+                sort_method: Some(SortMethod::Name),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(5, outcome.count);
+            assert_eq!(7, test_printer.0.len());
+            assert_eq!("Depth 0 - 3 file(s), 571 total", test_printer.0[5]);
+            assert_eq!("Depth 1 - 2 file(s), 1512 total", test_printer.0[6]);
+        }
+    }
+
+    /// Ensure that the largest directory option only takes a count with an equals sign, so that a
+    /// start directory after it isn't mistaken for one.
+    #[test]
+    fn test_largest_dir_parse() {
+        let flag_args: LffArgs =
+            LffArgs::try_parse_from(["lff", "--largest-dir", "test_resources"]).unwrap();
+        assert_eq!(Some(1), flag_args.largest_dir);
+        assert_eq!(vec!["test_resources"], flag_args.directories);
+
+        let count_args: LffArgs =
+            LffArgs::try_parse_from(["lff", "--largest-dir=3", "test_resources"]).unwrap();
+        assert_eq!(Some(3), count_args.largest_dir);
+        assert_eq!(vec!["test_resources"], count_args.directories);
+    }
+
+    /// Ensure that when the finder is run with the largest directory option, only the directories
+    /// containing the most data are displayed, with a single directory by default.
+    #[test]
+    fn test_run_finder_largest_dir() {
+        for (largest_dir, expected_lines) in [
+            (1, vec!["1183 - test_resources/.hidden_dir"]),
+            (
+                5,
+                vec![
+                    "1183 - test_resources/.hidden_dir",
+                    "329 - test_resources/visible",
+                ],
+            ),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                largest_dir: Some(largest_dir),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(5, outcome.count);
+            assert_eq!(expected_lines, test_printer.0);
+            assert!(test_printer.1.is_empty());
+        }
+    }
+
+    /// Ensure that when the finder is run with the JSON formats, found files are output as a single
+    /// array, which is compact by default and indented when pretty, with both round-tripping to the
+    /// same structure.
+    #[test]
+    fn test_run_finder_json() {
+        let mut outputs: Vec<Vec<String>> = Vec::new();
+        for format in [OutputFormat::Json, OutputFormat::JsonPretty] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(5, outcome.count);
+            assert_eq!(1, test_printer.0.len());
+            outputs.push(test_printer.0);
+        }
+        let compact: &String = &outputs[0][0];
+        let pretty: &String = &outputs[1][0];
+        assert!(!compact.contains('\n'));
+        assert!(pretty.starts_with("[\n  {\n    \"name\": "));
+
+        let compact_value: Value = from_str(compact).unwrap();
+        let pretty_value: Value = from_str(pretty).unwrap();
+        assert_eq!(compact_value, pretty_value);
+        let objects: &Vec<Value> = compact_value.as_array().unwrap();
+        assert_eq!(5, objects.len());
+        assert_eq!(
+            json!({
+                "name": "test_resources/.hidden_dir/spider.txt",
+                "extension": "txt",
+                "size": 1183,
+                "formatted_size": "1183",
+                "hidden": false,
+                "is_dir": false,
+            }),
+            objects[0]
+        );
+    }
+
+    /// Ensure that when the finder is run with the YAML format, the output parses back to the same
+    /// structure as the JSON output, and that an empty sequence is output when no files are found.
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_run_finder_yaml() {
+        let mut outputs: Vec<Value> = Vec::new();
+        for format in [OutputFormat::Json, OutputFormat::Yaml] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                format,
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(1, test_printer.0.len());
+            outputs.push(serde_yaml::from_str(&test_printer.0[0]).unwrap());
+        }
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(
+            json!("test_resources/.hidden_dir/spider.txt"),
+            outputs[1][0]["name"]
+        );
+
+        let empty_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Yaml,
+            min_size_mib: 1.0,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut empty_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(empty_args, &mut empty_printer).unwrap();
+        assert_eq!(vec!["[]"], empty_printer.0);
+    }
+
+    /// Ensure that when the finder is run with the JSON format and no files are found, an empty
+    /// array is still output, so that the output is always valid JSON.
+    #[test]
+    fn test_run_finder_json_no_files() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Json,
+            min_size_mib: 1.0,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert_eq!(vec!["[]"], test_printer.0);
+        assert_eq!(vec![NO_FILES_FOUND_STR], test_printer.1);
+    }
+
+    /// Ensure that when the finder is run with the verbose flag, diagnostics don't affect the
+    /// displayed files, since they are written straight to standard error.
+    #[test]
+    fn test_run_finder_verbose() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            verbose: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(5, test_printer.0.len());
+        assert!(test_printer.1.is_empty());
+    }
+
+    /// Ensure that when the finder is run with the human format, a single sentence summarising the
+    /// found files is output, and that nothing is output for no files.
+    #[test]
+    fn test_run_finder_human() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Human,
+            pretty: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec!["Found 5 file(s) totalling 2.03 KiB; the largest is spider.txt at 1.16 KiB."],
+            test_printer.0
+        );
+
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        print_human_summary(&[], &BASE_ARGS, &mut test_printer);
+        assert!(test_printer.0.is_empty());
+    }
+
+    /// Ensure that when the finder is run with the Prometheus format, the overall and per-extension
+    /// metrics are output with their HELP and TYPE lines, and that they are zero for no files.
+    #[test]
+    fn test_run_finder_prometheus() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Prometheus,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "# HELP lff_matched_files The number of files found.",
+                "# TYPE lff_matched_files gauge",
+                "lff_matched_files 5",
+                "# HELP lff_matched_bytes_total The total size of the files found, in bytes.",
+                "# TYPE lff_matched_bytes_total gauge",
+                "lff_matched_bytes_total 2083",
+                "# HELP lff_extension_files The number of files found with each extension.",
+                "# TYPE lff_extension_files gauge",
+                "lff_extension_files{extension=\"\"} 2",
+                "lff_extension_files{extension=\"md\"} 1",
+                "lff_extension_files{extension=\"txt\"} 2",
+                "# HELP lff_extension_bytes The total size of the files found with each extension, \
+                in bytes.",
+                "# TYPE lff_extension_bytes gauge",
+                "lff_extension_bytes{extension=\"\"} 27",
+                "lff_extension_bytes{extension=\"md\"} 329",
+                "lff_extension_bytes{extension=\"txt\"} 1727",
+            ],
+            test_printer.0
+        );
+
+        let empty_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Prometheus,
+            min_size_bytes: Some(MEBIBYTE),
+            ..BASE_ARGS
+        };
+        let mut empty_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(empty_args, &mut empty_printer).unwrap();
+        assert_eq!(
+            vec!["lff_matched_files 0", "lff_matched_bytes_total 0"],
+            empty_printer
+                .0
+                .iter()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<&String>>()
+        );
+    }
+
+    /// Ensure that the automatic unit is the largest that the median size is at least one of, in
+    /// both bases, and that sizes are formatted in the supplied unit.
+    #[test]
+    fn test_auto_unit() {
+        assert_eq!(0, auto_unit(&[], false));
+        assert_eq!(0, auto_unit(&[0, 27, 329, 544, 1183], false));
+        assert_eq!(1, auto_unit(&[500, 2000, 3000], false));
+        assert_eq!(1, auto_unit(&[1000, 1000], true));
+        assert_eq!(0, auto_unit(&[1000, 1000], false));
+        assert_eq!(
+            2,
+            auto_unit(&[1, 5 * MEBIBYTE, 10 * MEBIBYTE, u64::MAX], false)
+        );
+        assert_eq!(6, auto_unit(&[u64::MAX], false));
+
+        assert_eq!("544 B", format_size_in_unit(544, 0, false));
+        assert_eq!("0.03 KiB", format_size_in_unit(27, 1, false));
+        assert_eq!("1.18 KB", format_size_in_unit(1183, 1, true));
+        assert_eq!("0.00 MiB", format_size_in_unit(0, 2, false));
+    }
+
+    /// Ensure that when the finder is run with the auto units flag, every file's size is displayed
+    /// in the same unit, picked from their median size.
+    #[test]
+    fn test_run_finder_auto_units() {
+        let test_dir: TempDir = tempdir().unwrap();
+        for (file_name, size) in [
+            ("small.txt", 500),
+            ("medium.txt", 2000),
+            ("large.txt", 3000),
+        ] {
+            write(test_dir.path().join(file_name), vec![0u8; size]).unwrap();
+        }
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            format: OutputFormat::Table,
+            columns: Some(vec![Column::Size]),
+            auto_units: true,
+            pretty: true,
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(3, outcome.count);
+        assert_eq!(
+            vec!["Size", "--------", "2.93 KiB", "1.95 KiB", "0.49 KiB"],
+            test_printer
+                .0
+                .iter()
+                .map(|line| line.trim_end())
+                .collect::<Vec<&str>>()
+        );
+    }
+
+    /// Ensure that when the finder is run with the percent flag, each file's share of the total
+    /// size is displayed in an aligned column, and that the shares sum to roughly 100%.
+    #[test]
+    fn test_run_finder_percent() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            percent: true,
+            sort_method: Some(SortMethod::Size),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            "1183   56.8%  \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[0]
+        );
+        assert_eq!(
+            "0       0.0%  \"test_resources/.hidden\"",
+            test_printer.0[4]
+        );
+        let percent_sum: f64 = test_printer
+            .0
+            .iter()
+            .map(|line| {
+                let percent: &str = line.split_whitespace().nth(1).unwrap();
+                percent.trim_end_matches('%').parse::<f64>().unwrap()
+            })
+            .sum();
+        assert!((percent_sum - 100.0).abs() < 0.5);
+    }
+
+    /// Ensure that when all of the displayed files are empty, their percentages are displayed as
+    /// zero, and that the percentage is separated like the other columns when a separator is
+    /// supplied.
+    #[test]
+    fn test_run_finder_percent_empty_files() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            max_size_bytes: Some(0),
+            percent: true,
+            separator: Some(String::from(",")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(vec!["0,0.0%,\"test_resources/.hidden\""], test_printer.0);
+    }
+
+    /// Ensure that when the finder is run with a minimum size percentage, only files at least that
+    /// percentage of the size of the largest file are output.
+    #[test]
+    fn test_run_finder_min_size_percent() {
+        for (percent, expected_lines) in [
+            (
+                50.0,
+                vec!["1183  \"test_resources/.hidden_dir/spider.txt\""],
+            ),
+            (
+                25.0,
+                vec![
+                    "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                    "544   \"test_resources/snow.txt\"",
+                    "329   \"test_resources/visible/mud.md\"",
+                ],
+            ),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                min_size_percent: Some(parse_percentage(&percent.to_string()).unwrap()),
+                sort_method: Some(SortMethod::Size),
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(expected_lines.len(), outcome.count);
+            assert_eq!(expected_lines, test_printer.0);
+        }
+    }
+
+    /// Ensure that when the finder is run unordered with a limit and a minimum size percentage, the
+    /// percentage is relative to the largest file in the whole tree, rather than only those found
+    /// before the limit was reached.
+    #[test]
+    fn test_run_finder_min_size_percent_unordered_limit() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            min_size_percent: Some(50.0),
+            limit: Some(1),
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(
+            vec!["1183  \"test_resources/.hidden_dir/spider.txt\""],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that the correct error message is generated when an invalid percentage is supplied.
+    #[test]
+    fn test_parse_percentage_invalid() {
+        for percentage in ["-1", "100.5", "half"] {
+            assert_eq!(
+                format!("Percentage must be a number from 0 to 100: '{percentage}'"),
+                parse_percentage(percentage).unwrap_err().to_string()
+            );
+        }
+    }
+
+    /// Ensure that extension thresholds are parsed into sizes for each extension, with any leading
+    /// dots removed, and that the correct error message is generated for invalid thresholds.
+    #[test]
+    fn test_parse_ext_thresholds() {
+        assert_eq!(
+            BTreeMap::from([
+                (OsString::from("mp4"), 500 * MEBIBYTE),
+                (OsString::from("txt"), MEBIBYTE),
+            ]),
+            parse_ext_thresholds("mp4=500M,.txt=1M").unwrap()
+        );
+        for threshold in ["mp4", "=1M", ".=1M"] {
+            assert_eq!(
+                format!("Extension threshold must be in the form ext=size: '{threshold}'"),
+                parse_ext_thresholds(threshold).unwrap_err().to_string()
+            );
+        }
+        assert!(parse_ext_thresholds("mp4=lots").is_err());
+    }
+
+    /// Ensure that when the finder is run with extension thresholds, files with those extensions
+    /// are filtered by them, and other files by the global minimum size.
+    #[test]
+    fn test_run_finder_ext_thresholds() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            format: OutputFormat::Path,
+            ext_thresholds: Some(BTreeMap::from([
+                (OsString::from("txt"), 500),
+                (OsString::from("md"), 1000),
+            ])),
+            min_size_bytes: Some(1),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(3, outcome.count);
+        // The md file is below its threshold, and the empty file below the global minimum.
+        assert_eq!(
+            vec![
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with the histogram flag, found files are counted in
+    /// power-of-two size ranges after the files, with bars proportional to the counts.
+    #[test]
+    fn test_run_finder_histogram() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            histogram: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        let full_bar: String = "#".repeat(40);
+        assert_eq!(
+            vec![
+                format!("0            {full_bar}  1 file(s), 0 total"),
+                format!("16 - 31      {full_bar}  1 file(s), 27 total"),
+                format!("256 - 511    {full_bar}  1 file(s), 329 total"),
+                format!("512 - 1023   {full_bar}  1 file(s), 544 total"),
+                format!("1024 - 2047  {full_bar}  1 file(s), 1183 total"),
+            ],
+            test_printer.0[5..]
+        );
+
+        // Files of sizes 0 to 3 bytes give two ranges with one file, and one with two files.
+        let test_tree: TempDir = create_test_tree(1, 4);
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_tree.path().to_string_lossy().into_owned()],
+            histogram: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(4, outcome.count);
+        let half_bar: String = format!("{:<40}", "#".repeat(20));
+        assert_eq!(
+            vec![
+                format!("0      {half_bar}  1 file(s), 0 total"),
+                format!("1 - 1  {half_bar}  1 file(s), 1 total"),
+                format!("2 - 3  {full_bar}  2 file(s), 5 total"),
+            ],
+            test_printer.0[4..]
+        );
+    }
+
+    /// Ensure that when the finder is run with a reference file, only files modified more recently
+    /// than it are output.
+    #[test]
+    fn test_run_finder_newer_than() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let now: SystemTime = SystemTime::now();
+        for (file_name, age_secs) in [("old.txt", 300), ("reference.txt", 200), ("new.txt", 100)] {
+            let file_path: PathBuf = test_dir.path().join(file_name);
+            write(&file_path, "lff").unwrap();
+            File::options()
+                .write(true)
+                .open(&file_path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            newer_than: Some(test_dir.path().join("reference.txt")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(1, test_printer.0.len());
+        assert!(test_printer.0[0].ends_with("new.txt\""));
+    }
+
+    /// Ensure that the correct error message is generated when the finder is run with a reference
+    /// file that does not exist.
+    #[test]
+    fn test_run_finder_newer_than_missing() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            newer_than: Some(PathBuf::from("test_resources/missing.txt")),
+            ..BASE_ARGS
+        };
+        let missing_error: Report = run_finder!(test_args).unwrap_err();
+        assert_eq!(
+            "Could not read modification time of reference file \"test_resources/missing.txt\"",
+            missing_error.to_string()
+        );
+    }
+
+    /// Creates a temporary directory of files with known relative ages, from oldest to newest, for
+    /// testing the reference file filters.
+    fn create_aged_test_dir() -> TempDir {
+        let test_dir: TempDir = tempdir().unwrap();
+        let now: SystemTime = SystemTime::now();
+        for (file_name, age_secs) in [
+            ("oldest.txt", 400),
+            ("old.txt", 300),
+            ("new.txt", 200),
+            ("newest.txt", 100),
+        ] {
+            let file_path: PathBuf = test_dir.path().join(file_name);
+            write(&file_path, "lff").unwrap();
+            File::options()
+                .write(true)
+                .open(&file_path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+        test_dir
+    }
+
+    /// Ensure that when the finder is run with age filters, only the files modified within the
+    /// window are output, and that the correct error message is generated for an empty window.
+    #[test]
+    fn test_run_finder_age_window() {
+        let test_dir: TempDir = create_aged_test_dir();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            min_age: Some(parse_duration("150s").unwrap()),
+            max_age: Some(parse_duration("350s").unwrap()),
+            sort_method: Some(SortMethod::Name),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert!(test_printer.0[0].ends_with("new.txt\""));
+        assert!(test_printer.0[1].ends_with("old.txt\""));
+
+        let stale_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            min_age: Some(parse_duration("250s").unwrap()),
+            ..BASE_ARGS
+        };
+        let mut stale_printer: LffTestPrinter = LffTestPrinter::default();
+        assert_eq!(
+            2,
+            run_finder!(stale_args, &mut stale_printer).unwrap().count
+        );
+        assert!(stale_printer.0.iter().all(|line| line.contains("old")));
+
+        let empty_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            min_age: Some(parse_duration("30d").unwrap()),
+            max_age: Some(parse_duration("1d").unwrap()),
+            ..BASE_ARGS
+        };
+        let empty_error: Report = run_finder!(empty_args).unwrap_err();
+        assert_eq!(
+            "The --min-age must be less than the --max-age, so no files could match",
+            empty_error.to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run with an older-than reference file, only files modified
+    /// before it are output.
+    #[test]
+    fn test_run_finder_older_than() {
+        let test_dir: TempDir = create_aged_test_dir();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            older_than: Some(test_dir.path().join("old.txt")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(1, test_printer.0.len());
+        assert!(test_printer.0[0].ends_with("oldest.txt\""));
+    }
+
+    /// Ensure that when the finder is run with both reference files, only files modified within the
+    /// window between them are output.
+    #[test]
+    fn test_run_finder_modified_window() {
+        let test_dir: TempDir = create_aged_test_dir();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            newer_than: Some(test_dir.path().join("oldest.txt")),
+            older_than: Some(test_dir.path().join("newest.txt")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert_eq!(2, test_printer.0.len());
+        assert!(test_printer.0[0].ends_with("new.txt\""));
+        assert!(test_printer.0[1].ends_with("old.txt\""));
+    }
+
+    /// Ensure that the correct error message is generated when the finder is run with an
+    /// older-than reference file that is not newer than the newer-than reference file.
+    #[test]
+    fn test_run_finder_modified_window_inconsistent() {
+        let test_dir: TempDir = create_aged_test_dir();
+        let newer_than: PathBuf = test_dir.path().join("newest.txt");
+        let older_than: PathBuf = test_dir.path().join("oldest.txt");
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            newer_than: Some(newer_than.clone()),
+            older_than: Some(older_than.clone()),
+            ..BASE_ARGS
+        };
+        let window_error: Report = run_finder!(test_args).unwrap_err();
+        assert_eq!(
+            format!(
+                "The --older-than reference file {:?} is not newer than the --newer-than \
+                reference file {:?}, so no files could match",
+                older_than, newer_than
+            ),
+            window_error.to_string()
+        );
+    }
+
+    /// Ensure that the boot time is parsed from the btime line of /proc/stat, and that the correct
+    /// error message is generated when it is missing or invalid.
+    #[test]
+    fn test_parse_boot_time() {
+        let proc_stat: &str = "cpu  1 2 3 4\nintr 5 6\nbtime 1700000000\nprocesses 789\n";
+        assert_eq!(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            parse_boot_time(proc_stat).unwrap()
+        );
+        for invalid_stat in ["cpu  1 2 3 4\n", "btime soon\n"] {
+            assert_eq!(
+                "Could not find the boot time in /proc/stat",
+                parse_boot_time(invalid_stat).unwrap_err().to_string()
+            );
+        }
+    }
+
+    /// Ensure that the syslog message summarises the found files, naming the largest of them if
+    /// there are any.
+    #[test]
+    fn test_syslog_message() {
+        let test_file: PathBuf = PathBuf::from("test_resources/.hidden_dir/spider.txt");
+        let largest: LffFile = handle_entry(test_file, &BASE_ARGS, None).unwrap();
+        assert_eq!(
+            "lff found 5 file(s) totalling 2083 bytes, the largest being \
+            \"test_resources/.hidden_dir/spider.txt\" at 1183 bytes",
+            syslog_message(5, 2083, Some(&largest))
+        );
+        assert_eq!(
+            "lff found 0 file(s) totalling 0 bytes",
+            syslog_message(0, 0, None)
+        );
+    }
+
+    /// Ensure that the automatic minimum size is a hundredth of the free space, but never less than
+    /// a mebibyte.
+    #[test]
+    fn test_auto_min_size() {
+        assert_eq!(10 * MEBIBYTE, auto_min_size(1000 * MEBIBYTE));
+        assert_eq!(1_000_000_000, auto_min_size(100_000_000_000));
+        assert_eq!(MEBIBYTE, auto_min_size(50 * MEBIBYTE));
+        assert_eq!(MEBIBYTE, auto_min_size(0));
+    }
+
+    /// Ensure that when the finder is run with the min size auto flag, files smaller than the
+    /// automatic minimum size are not output, and that the correct error message is generated when
+    /// the free space of a start directory can't be read.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_min_size_auto() {
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("small.txt"), "lff").unwrap();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            min_size_mib: 0.0,
+            min_size_auto: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        // The minimum size is at least a mebibyte, however much space is free.
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+
+        let missing_args: LffArgs = LffArgs {
+            directories: vec![String::from("does_not_exist")],
+            min_size_auto: true,
+            ..BASE_ARGS
+        };
+        let missing_error: Report =
+            run_finder!(missing_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            "Could not read the free space for directory \"does_not_exist\"",
+            missing_error.to_string()
+        );
+    }
+
+    /// Ensure that the differences between two snapshots are new, deleted, grown and shrunk files
+    /// in order of path, with the correct markers and changes in size, and unchanged files omitted.
+    #[test]
+    fn test_diff_snapshots() {
+        let previous: BTreeMap<String, u64> = BTreeMap::from([
+            (String::from("a/deleted.bin"), 300),
+            (String::from("a/grown.bin"), 100),
+            (String::from("a/shrunk.bin"), 500),
+            (String::from("a/unchanged.bin"), 50),
+        ]);
+        let current: BTreeMap<String, u64> = BTreeMap::from([
+            (String::from("a/grown.bin"), 250),
+            (String::from("a/new.bin"), 75),
+            (String::from("a/shrunk.bin"), 200),
+            (String::from("a/unchanged.bin"), 50),
+        ]);
+
+        let changes: Vec<LffSnapshotChange> = diff_snapshots(&previous, &current);
+        let summaries: Vec<(&str, char, i128)> = changes
+            .iter()
+            .map(|change| (change.name.as_str(), change.marker(), change.delta()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a/deleted.bin", '-', -300),
+                ("a/grown.bin", '~', 150),
+                ("a/new.bin", '+', 75),
+                ("a/shrunk.bin", '~', -300),
+            ],
+            summaries
+        );
+        assert!(diff_snapshots(&current, &current).is_empty());
+    }
+
+    /// Ensure that when the finder is run with a snapshot to compare to, only the changes since the
+    /// snapshot are output, regardless of the limit and ordering, and that the correct error
+    /// messages are generated when the snapshot is missing or invalid.
+    #[test]
+    fn test_run_finder_compare_to() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let snapshot_path: PathBuf = test_dir.path().join("snapshot.json");
+        write(
+            &snapshot_path,
+            json!([
+                {"name": "test_resources/snow.txt", "size": 544},
+                {"name": "test_resources/LICENCE", "size": 100},
+                {"name": "test_resources/visible/mud.md", "size": 29},
+                {"name": "test_resources/gone.txt", "size": 1000},
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            compare_to: Some(snapshot_path.clone()),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "+ +0  \"test_resources/.hidden\"",
+                "+ +1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "~ -73  \"test_resources/LICENCE\"",
+                "- -1000  \"test_resources/gone.txt\"",
+                "~ +300  \"test_resources/visible/mud.md\"",
+            ],
+            test_printer.0
+        );
+
+        let unordered_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            compare_to: Some(snapshot_path.clone()),
+            limit: Some(1),
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let mut unordered_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(unordered_args, &mut unordered_printer).unwrap();
+        assert_eq!(test_printer.0, unordered_printer.0);
+
+        write(&snapshot_path, "{\"files\": 5}").unwrap();
+        for (snapshot, expected_error) in [
+            (
+                snapshot_path.clone(),
+                format!("Invalid snapshot {:?}", snapshot_path),
+            ),
+            (
+                test_dir.path().join("missing.json"),
+                format!(
+                    "Could not read snapshot {:?}",
+                    test_dir.path().join("missing.json")
+                ),
+            ),
+        ] {
+            let error_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                compare_to: Some(snapshot),
+                ..BASE_ARGS
+            };
+            let error: Report =
+                run_finder!(error_args, &mut LffTestPrinter::default()).unwrap_err();
+            assert_eq!(expected_error, error.to_string());
+        }
+    }
+
+    /// Ensure that when the finder is run with a snapshot to save, all of the matched files are
+    /// saved to it as versioned JSON, regardless of the format, limit and ordering, that comparing
+    /// to it shows no changes, and that the correct error message is generated for snapshots from
+    /// newer versions.
+    #[test]
+    fn test_run_finder_save_snapshot() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let snapshot_path: PathBuf = test_dir.path().join("snapshot.json");
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            save_snapshot: Some(snapshot_path.clone()),
+            limit: Some(1),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(vec!["0     \"test_resources/.hidden\""], test_printer.0);
+        let snapshot: Value = from_str(&read_to_string(&snapshot_path).unwrap()).unwrap();
+        assert_eq!(json!(1), snapshot["version"]);
+        let sizes: Vec<(&str, u64)> = snapshot["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| {
+                (
+                    file["name"].as_str().unwrap(),
+                    file["size"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("test_resources/.hidden", 0),
+                ("test_resources/.hidden_dir/spider.txt", 1183),
+                ("test_resources/LICENCE", 27),
+                ("test_resources/snow.txt", 544),
+                ("test_resources/visible/mud.md", 329),
+            ],
+            sizes
+        );
+
+        let unordered_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            save_snapshot: Some(snapshot_path.clone()),
+            limit: Some(1),
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let outcome: LffScanOutcome =
+            run_finder!(unordered_args, &mut LffTestPrinter::default()).unwrap();
+        assert_eq!(1, outcome.count);
+        let unordered_snapshot: Value = from_str(&read_to_string(&snapshot_path).unwrap()).unwrap();
+        let mut unordered_names: Vec<&str> = unordered_snapshot["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["name"].as_str().unwrap())
+            .collect();
+        unordered_names.sort();
+        assert_eq!(
+            sizes.iter().map(|(name, _)| *name).collect::<Vec<&str>>(),
+            unordered_names
+        );
+
+        let compare_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            compare_to: Some(snapshot_path.clone()),
+            ..BASE_ARGS
+        };
+        let mut compare_printer: LffTestPrinter = LffTestPrinter::default();
+        run_finder!(compare_args, &mut compare_printer).unwrap();
+        assert!(compare_printer.0.is_empty());
+
+        write(&snapshot_path, "{\"version\": 2, \"files\": []}").unwrap();
+        let newer_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            compare_to: Some(snapshot_path.clone()),
+            ..BASE_ARGS
+        };
+        let newer_error: Report =
+            run_finder!(newer_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            format!(
+                "Snapshot {:?} has version 2, but only versions up to 1 are supported",
+                snapshot_path
+            ),
+            newer_error.to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run with the explain flag, the interpretation of the flags is
+    /// output without scanning, and that the correct error message is generated when the minimum
+    /// size is larger than the maximum size.
+    #[test]
+    fn test_run_finder_explain() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources"), String::from("src")],
+            explain: true,
+            min_size_mib: 1.5,
+            exclude_larger_than: Some(10 * MEBIBYTE),
+            ext_thresholds: Some(BTreeMap::from([(OsString::from("mp4"), 5 * MEBIBYTE)])),
+            extension: Some(OsString::from("txt")),
+            exclude_extensions: vec![OsString::from("iso"), OsString::from("img")],
+            name_pattern: Some(String::from("*snow*")),
+            ignore_case: true,
+            exclude_hidden: true,
+            sort_method: Some(SortMethod::Size),
+            reverse: true,
+            limit: Some(3),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert_eq!(
+            vec![
+                "Directories: test_resources, src",
+                "Subdirectories: searched",
+                "Minimum size: 1572864 bytes",
+                "Minimum sizes by extension: mp4 = 5242880 bytes",
+                "Maximum size: 10485760 bytes",
+                "Extension: txt",
+                "Excluded extensions: iso, img",
+                "Name: matching glob '*snow*', ignoring case",
+                "Hidden files: excluded",
+                "Empty files: included",
+                "Sort: size, reversed",
+                "Limit: 3 files",
+            ],
+            test_printer.0
+        );
+        assert!(test_printer.1.is_empty());
+
+        let other_args: LffArgs = LffArgs {
+            stdin: true,
+            explain: true,
+            no_recurse: true,
+            regex_pattern: Some(parse_regex("^snow").unwrap()),
+            hidden_only: true,
+            exclude_empty: true,
+            newer_than: Some(PathBuf::from("test_resources/LICENCE")),
+            unordered: true,
+            ..BASE_ARGS
+        };
+        let mut other_printer: LffTestPrinter = LffTestPrinter::default();
+
+        run_finder!(other_args, &mut other_printer).unwrap();
+        assert_eq!(
+            vec![
+                "Directories: read from standard input",
+                "Subdirectories: skipped",
+                "Minimum size: 0 bytes",
+                "Maximum size: none",
+                "Extension: any",
+                "Excluded extensions: none",
+                "Name: matching regex '^snow'",
+                "Hidden files: only",
+                "Empty files: excluded",
+            ],
+            other_printer.0[..9]
+        );
+        assert!(other_printer.0[9].starts_with("Modified after: "));
+        assert_eq!(vec!["Sort: none", "Limit: none"], other_printer.0[10..]);
+
+        let invalid_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            min_size_bytes: Some(2000),
+            max_size_bytes: Some(1000),
+            ..BASE_ARGS
+        };
+        let invalid_error: Report =
+            run_finder!(invalid_args, &mut LffTestPrinter::default()).unwrap_err();
+        assert_eq!(
+            "The minimum size of 2000 bytes is larger than the maximum size of 1000 bytes, so no \
+            files could match",
+            invalid_error.to_string()
+        );
+
+        // Extension thresholds within the maximum size mean that files could still match.
+        let threshold_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            ext_thresholds: Some(parse_ext_thresholds("txt=1").unwrap()),
+            min_size_mib: 50.0,
+            exclude_larger_than: Some(10 * MEBIBYTE),
+            ..BASE_ARGS
+        };
+        let mut threshold_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(threshold_args, &mut threshold_printer).unwrap();
+        assert_eq!(2, outcome.count);
+
+        let thresholds_error: Report = run_finder!(
+            LffArgs {
+                directories: vec![String::from("test_resources")],
+                ext_thresholds: Some(parse_ext_thresholds("txt=3000,md=4000").unwrap()),
+                min_size_bytes: Some(5000),
+                max_size_bytes: Some(1000),
+                ..BASE_ARGS
+            },
+            &mut LffTestPrinter::default()
+        )
+        .unwrap_err();
+        assert_eq!(
+            "The minimum size of 3000 bytes is larger than the maximum size of 1000 bytes, so no \
+            files could match",
+            thresholds_error.to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run with the since boot flag, only files modified since the
+    /// system booted are output, and that the correct error message is generated when the
+    /// older-than reference file predates the boot.
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_run_finder_since_boot() {
+        let test_dir: TempDir = tempdir().unwrap();
+        for (file_name, modified) in [
+            (
+                "ancient.txt",
+                SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+            ),
+            ("recent.txt", SystemTime::now()),
+        ] {
+            let file_path: PathBuf = test_dir.path().join(file_name);
+            write(&file_path, "lff").unwrap();
+            File::options()
+                .write(true)
+                .open(&file_path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            since_boot: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert!(test_printer.0[0].ends_with("recent.txt\""));
+
+        let older_than: PathBuf = test_dir.path().join("ancient.txt");
+        let window_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            since_boot: true,
+            older_than: Some(older_than.clone()),
+            ..BASE_ARGS
+        };
+        let window_error: Report = run_finder!(window_args).unwrap_err();
+        assert_eq!(
+            format!(
+                "The --older-than reference file {:?} was last modified before the system booted, \
+                so no files could match",
+                older_than
+            ),
+            window_error.to_string()
+        );
+    }
+
+    /// Ensure that when the finder is run with overlapping start directories, files within both are
+    /// displayed twice, unless the dedupe output flag is passed.
+    #[test]
+    fn test_run_finder_dedupe_output() {
+        for (dedupe_output, expected_mud_count) in [(false, 2), (true, 1)] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![
+                    String::from("test_resources"),
+                    String::from("test_resources/visible"),
+                ],
+                format: OutputFormat::Path,
+                dedupe_output,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(4 + expected_mud_count, outcome.count);
+            for name in [
+                "test_resources/.hidden",
+                "test_resources/.hidden_dir/spider.txt",
+                "test_resources/LICENCE",
+                "test_resources/snow.txt",
+            ] {
+                assert_eq!(
+                    1,
+                    test_printer.0.iter().filter(|line| *line == name).count()
+                );
+            }
+            assert_eq!(
+                expected_mud_count,
+                test_printer
+                    .0
+                    .iter()
+                    .filter(|line| line.ends_with("mud.md"))
+                    .count()
+            );
+        }
+    }
+
+    /// Ensure that when the finder is run with the dedup hardlinks flag, hardlinks to the same file
+    /// are each listed, but only counted once towards size totals.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_dedup_hardlinks() {
+        use std::fs::hard_link;
+
+        let test_dir: TempDir = tempdir().unwrap();
+        write(test_dir.path().join("original.bin"), vec![0; 100]).unwrap();
+        hard_link(
+            test_dir.path().join("original.bin"),
+            test_dir.path().join("link.bin"),
+        )
+        .unwrap();
+        write(test_dir.path().join("other.bin"), vec![0; 50]).unwrap();
+        for (dedup_hardlinks, expected_total) in [(false, 250), (true, 150)] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![test_dir.path().to_string_lossy().into_owned()],
+                format: OutputFormat::Human,
+                depth_summary: true,
+                dedup_hardlinks,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(
+                LffScanOutcome {
+                    count: 3,
+                    total_size: expected_total
+                },
+                outcome
+            );
+            assert_eq!(
+                vec![
+                    format!(
+                        "Found 3 file(s) totalling {}; the largest is link.bin at 100.",
+                        expected_total
+                    ),
+                    format!("Depth 0 - 3 file(s), {} total", expected_total),
+                ],
+                test_printer.0
+            );
+        }
+    }
+
+    /// Ensure that the finder succeeds when the number of found files is within the supplied count
+    /// bounds, and returns a count error otherwise.
+    #[test]
+    fn test_run_finder_count_bounds() {
+        // There are 5 files in test_resources.
+        for (min_count, max_count, expected_error) in [
+            (Some(5), Some(5), None),
+            (
+                Some(6),
+                None,
+                Some("Found 5 file(s), fewer than the minimum count of 6"),
+            ),
+            (
+                None,
+                Some(4),
+                Some("Found 5 file(s), more than the maximum count of 4"),
+            ),
+        ] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                min_count,
+                max_count,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let finder_result: eyre::Result<LffScanOutcome> =
+                run_finder!(test_args, &mut test_printer);
+            // The found files are displayed either way.
+            assert_eq!(5, test_printer.0.len());
+            match expected_error {
+                None => assert_eq!(5, finder_result.unwrap().count),
+                Some(message) => {
+                    let count_error: Report = finder_result.unwrap_err();
+                    assert!(count_error.downcast_ref::<LffCountError>().is_some());
+                    assert_eq!(message, count_error.to_string());
+                }
+            }
+        }
+    }
+
+    /// Ensure that when the finder is run with a move target in a dry run, the moves are output
+    /// without being performed.
+    #[test]
+    fn test_run_finder_move_to_dry_run() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            limit: Some(2),
+            move_to: Some(PathBuf::from("quarantine")),
+            dry_run: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert_eq!(
+            vec![
+                "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "544   \"test_resources/snow.txt\"",
+                "Would move \"test_resources/.hidden_dir/spider.txt\" to \
+                \"quarantine/test_resources/.hidden_dir/spider.txt\"",
+                "Would move \"test_resources/snow.txt\" to \"quarantine/test_resources/snow.txt\"",
+            ],
+            test_printer.0
+        );
+        assert!(!Path::new("quarantine").exists());
+    }
+
+    /// Ensure that when the finder is run with a move target, the found files are moved into it.
+    #[test]
+    fn test_run_finder_move_to() {
+        let source_dir: TempDir = create_test_tree(2, 2);
+        let target_dir: TempDir = tempdir().unwrap();
+        let test_args: LffArgs = LffArgs {
+            directories: vec![source_dir.path().to_string_lossy().into_owned()],
+            // Only the largest file, in subdir1, is moved.
+            min_size_bytes: Some(3),
+            move_to: Some(target_dir.path().to_path_buf()),
+            yes: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        let moved_file: PathBuf = source_dir.path().join("subdir1").join("file1.bin");
+        assert!(!moved_file.exists());
+        assert!(move_destination(target_dir.path(), &moved_file).exists());
+        // Files that weren't found are left where they are.
+        assert!(source_dir.path().join("subdir1").join("file0.bin").exists());
+    }
+
+    /// Ensure that moves never overwrite an existing destination, leaving both files untouched, and
+    /// that a failed rename isn't followed by a copy.
+    #[test]
+    fn test_move_action_existing_destination() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let source: PathBuf = test_dir.path().join("source.txt");
+        let destination: PathBuf = test_dir.path().join("target").join("source.txt");
+        write(&source, "new").unwrap();
+        create_dir_all(destination.parent().unwrap()).unwrap();
+        write(&destination, "old").unwrap();
+        let move_action: LffMoveAction = LffMoveAction {
+            source: source.clone(),
+            destination: destination.clone(),
+        };
+
+        let move_error: Report = move_action.perform().unwrap_err();
+        assert_eq!(
+            format!(
+                "Could not move {:?} to {:?}, since the destination already exists",
+                source, destination
+            ),
+            move_error.to_string()
+        );
+        assert_eq!("new", read_to_string(&source).unwrap());
+        assert_eq!("old", read_to_string(&destination).unwrap());
+
+        let missing_action: LffMoveAction = LffMoveAction {
+            source: test_dir.path().join("missing.txt"),
+            destination: test_dir.path().join("moved.txt"),
+        };
+        assert!(missing_action.perform().is_err());
+        assert!(!test_dir.path().join("moved.txt").exists());
+    }
+
+    /// Ensure that moves are run through the action log in order, so that when several files are
+    /// moved to the same destination, the first is always the one moved.
+    #[test]
+    fn test_action_log_run_moves_in_order() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let destination: PathBuf = test_dir.path().join("moved.txt");
+        let actions: Vec<Box<dyn LffAction>> = (0..20)
+            .map(|idx| {
+                let source: PathBuf = test_dir.path().join(format!("{idx}.txt"));
+                write(&source, idx.to_string()).unwrap();
+                Box::new(LffMoveAction {
+                    source,
+                    destination: destination.clone(),
+                }) as Box<dyn LffAction>
+            })
+            .collect();
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let run_error: Report = LffActionLog { dry_run: false }
+            .run(&actions, &mut test_printer)
+            .unwrap_err();
+        assert_eq!("19 of 20 actions failed", run_error.to_string());
+        assert_eq!("0", read_to_string(&destination).unwrap());
+        assert!(!actions[0].is_parallel());
+        assert!(exec_action("ls", OsStr::new("a"), false).is_parallel());
+    }
+
+    /// Ensure that the correct error message is generated when the finder is run with a move
+    /// target, but neither the yes flag nor the dry run flag.
+    #[test]
+    fn test_run_finder_move_to_unconfirmed() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            move_to: Some(PathBuf::from("quarantine")),
+            ..BASE_ARGS
+        };
+        let move_error: Report = run_finder!(test_args).unwrap_err();
+        assert_eq!(
+            "Moving files requires confirmation with --yes, or use --dry-run to preview",
+            move_error.to_string()
+        );
+    }
+
+    /// Ensure that exec actions substitute each {} in the command with the file's path, or append
+    /// the path if there is no {}.
+    #[test]
+    fn test_exec_action() {
+        let substituted: LffExecAction = exec_action("cp {} {}.bak", OsStr::new("a b.txt"), false);
+        assert_eq!(OsString::from("cp"), substituted.program);
+        assert_eq!(
+            vec![OsString::from("a b.txt"), OsString::from("a b.txt.bak")],
+            substituted.args
+        );
+
+        let appended: LffExecAction = exec_action("  gzip  -9 ", OsStr::new("a.txt"), false);
+        assert_eq!(OsString::from("gzip"), appended.program);
+        assert_eq!(
+            vec![OsString::from("-9"), OsString::from("a.txt")],
+            appended.args
+        );
+    }
+
+    /// Ensure that batch exec actions are split so that the arguments to each take up at most the
+    /// supplied number of bytes, with at least one path in each.
+    #[test]
+    fn test_batch_exec_actions() {
+        let paths: Vec<&OsStr> = vec![
+            OsStr::new("a"),
+            OsStr::new("b"),
+            OsStr::new("a_much_longer_path"),
+            OsStr::new("c"),
+        ];
+        // The program and flag take 11 bytes each, and each single-letter path takes 10 bytes.
+        let actions: Vec<LffExecAction> = batch_exec_actions("rm -f", &paths, 45);
+        let batches: Vec<Vec<OsString>> = actions.into_iter().map(|action| action.args).collect();
+        assert_eq!(
+            vec![
+                vec![
+                    OsString::from("-f"),
+                    OsString::from("a"),
+                    OsString::from("b")
+                ],
+                vec![OsString::from("-f"), OsString::from("a_much_longer_path")],
+                vec![OsString::from("-f"), OsString::from("c")],
+            ],
+            batches
+        );
+        // No actions are run when there are no paths.
+        assert!(batch_exec_actions("rm -f", &[], 45).is_empty());
+    }
+
+    /// Ensure that the correct error message is generated when parsing an empty command.
+    #[test]
+    fn test_parse_command_empty() {
+        for command in ["", "   "] {
+            assert_eq!(
+                "Command to execute must not be empty",
+                parse_command(command).unwrap_err().to_string()
+            );
+        }
+    }
+
+    /// Ensure that when the finder is run with a command to execute in a dry run, the commands are
+    /// output without being run.
+    #[test]
+    fn test_run_finder_exec_dry_run() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            limit: Some(2),
+            exec: Some(String::from("gzip {}")),
+            dry_run: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert_eq!(
+            vec![
+                "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "544   \"test_resources/snow.txt\"",
+                "Would run \"gzip\" \"test_resources/.hidden_dir/spider.txt\"",
+                "Would run \"gzip\" \"test_resources/snow.txt\"",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that when the finder is run with a batch command to execute in a dry run, a single
+    /// command receiving all found paths is output without being run.
+    #[test]
+    fn test_run_finder_batch_exec_dry_run() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            batch_exec: Some(String::from("rm -f")),
+            dry_run: true,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(5, outcome.count);
+        assert_eq!(
+            vec![
+                "Would run \"rm\" \"-f\" \"test_resources/.hidden\" \
+                \"test_resources/.hidden_dir/spider.txt\" \"test_resources/LICENCE\" \
+                \"test_resources/snow.txt\" \"test_resources/visible/mud.md\"",
+            ],
+            test_printer.0[5..]
+        );
+    }
+
+    /// Ensure that when the finder is run with a command to execute through the shell in a dry run,
+    /// the path is passed to the shell as an argument rather than substituted into the command.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_exec_shell_dry_run() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            limit: Some(1),
+            sort_method: Some(SortMethod::Size),
+            exec: Some(String::from("wc -c {} | sort")),
+            exec_shell: true,
+            dry_run: true,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert_eq!(
+            "Would run \"sh\" \"-c\" \"wc -c \\\"$1\\\" | sort\" \"sh\" \
+            \"test_resources/.hidden_dir/spider.txt\"",
+            test_printer.0[1]
+        );
+    }
+
+    /// Ensure that when the finder is run with an after scan command, it is run once with the
+    /// number and total size of the found files in its environment, or output without being run in
+    /// a dry run.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_after_scan_command() {
+        let test_dir: TempDir = tempdir().unwrap();
+        let output_path: PathBuf = test_dir.path().join("summary.txt");
+        let command: String = format!(
+            "echo \"$LFF_COUNT $LFF_TOTAL_BYTES\" >> '{}'",
+            output_path.display()
+        );
+        for dry_run in [true, false] {
+            let test_args: LffArgs = LffArgs {
+                directories: vec![String::from("test_resources")],
+                after_scan_command: Some(command.clone()),
+                dry_run,
+                quiet: true,
+                ..BASE_ARGS
+            };
+            let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+            let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+            assert_eq!(5, outcome.count);
+            if dry_run {
+                assert_eq!(
+                    format!(
+                        "Would run \"sh\" \"-c\" {:?} with LFF_COUNT=\"5\" \
+                        LFF_TOTAL_BYTES=\"2083\"",
+                        command
+                    ),
+                    test_printer.0[5]
+                );
+                assert!(!output_path.exists());
+            } else {
+                assert_eq!(5, test_printer.0.len());
+            }
+        }
+        // The command should only have been run once, for the run that wasn't dry.
+        assert_eq!("5 2083\n", read_to_string(&output_path).unwrap());
+    }
+
+    /// Ensure that when the finder is run with a command to execute, it is run for each found file.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_exec() {
+        let test_dir: TempDir = create_test_tree(2, 2);
+        let test_args: LffArgs = LffArgs {
+            directories: vec![test_dir.path().to_string_lossy().into_owned()],
+            // Only the largest file, in subdir1, is removed.
+            min_size_bytes: Some(3),
+            exec: Some(String::from("rm")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(1, outcome.count);
+        assert!(!test_dir.path().join("subdir1").join("file1.bin").exists());
+        assert!(test_dir.path().join("subdir1").join("file0.bin").exists());
+    }
+
+    /// Ensure that when a command to execute exits with a non-zero status, the status is reported.
+    #[test]
+    #[cfg(unix)]
+    fn test_run_finder_exec_failure() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            name_pattern: Some(String::from("*snow.txt")),
+            exec: Some(String::from("false")),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let exec_error: Report = run_finder!(test_args, &mut test_printer).unwrap_err();
+        assert_eq!("1 of 1 actions failed", exec_error.to_string());
+        assert_eq!(
+            vec!["Could not run \"false\" \"test_resources/snow.txt\": exit status: 1"],
+            test_printer.1
+        );
+    }
+
+    /// Ensure that when the finder is run with a results budget, only the largest files whose
+    /// combined size fits within it are output.
+    #[test]
+    fn test_run_finder_max_results_bytes() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Size),
+            // Just above the combined size of spider.txt and snow.txt.
+            max_results_bytes: Some(1183 + 544 + 1),
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(2, outcome.count);
+        assert_eq!(
+            vec![
+                "1183  \"test_resources/.hidden_dir/spider.txt\"",
+                "544   \"test_resources/snow.txt\"",
+            ],
+            test_printer.0
+        );
+    }
+
+    /// Ensure that the correct error message is generated when the finder is run with a results
+    /// budget without sorting by size.
+    #[test]
+    fn test_run_finder_max_results_bytes_unsorted() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            sort_method: Some(SortMethod::Name),
+            max_results_bytes: Some(MEBIBYTE),
+            ..BASE_ARGS
+        };
+        let budget_error: Report = run_finder!(test_args).unwrap_err();
+        assert_eq!(
+            "The maximum results bytes flag requires sorting by size, with --sort-method size",
+            budget_error.to_string()
+        );
+    }
+
+    /// Ensure that the correct message is output when no matching files are found.
+    #[test]
+    fn test_run_finder_no_files() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            // Naturally we don't have any test files at 100 MiB or more.
+            min_size_mib: 100.0,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        // Check that the correct diagnostic has been 'printed', and nothing else.
+        assert!(test_printer.0.is_empty());
+        assert_eq!(vec![NO_FILES_FOUND_STR], test_printer.1);
+    }
+
+    /// Ensure that nothing is output when no matching files are found and the quiet flag is
+    /// passed.
+    #[test]
+    fn test_run_finder_no_files_quiet() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("test_resources")],
+            min_size_mib: 100.0,
+            quiet: true,
+            ..BASE_ARGS
+        };
+        let mut test_printer: LffTestPrinter = LffTestPrinter::default();
+        let outcome: LffScanOutcome = run_finder!(test_args, &mut test_printer).unwrap();
+        assert_eq!(0, outcome.count);
+        assert!(test_printer.0.is_empty());
+        assert!(test_printer.1.is_empty());
+    }
+
+    /// Ensure that the correct error message is generated when the finder is run against a
+    /// non-existent directory.
+    #[test]
+    fn test_run_finder_invalid_dir() {
+        let test_args: LffArgs = LffArgs {
+            directories: vec![String::from("this is not real")],
+            ..BASE_ARGS
+        };
+        let dir_err: Report = run_finder!(test_args).unwrap_err();
+        assert_eq!(
+            "Invalid supplied start directory: 'this is not real'",
+            dir_err.to_string()
+        );
+    }
+}